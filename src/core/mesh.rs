@@ -5,9 +5,12 @@ use std::fs::File;
 use std::io::BufReader;
 use std::sync::Arc;
 
+use crate::core::{read_render_mesh_id, read_vec3, write_render_mesh_id, write_vec3};
 use crate::ecs::{ComponentActions, ProvisionalEntity};
 use crate::ecs::component::Component;
 use crate::ecs::entity::Entity;
+use crate::ecs::error::EcsError;
+use crate::ecs::snapshot::{SerializableComponent, SnapshotReader, SnapshotWriter};
 use crate::math::{vec2, vec3, Vec2, Vec3, VEC_2_ZERO, VEC_3_ZERO};
 
 // MeshBinding
@@ -43,12 +46,28 @@ impl MeshBinding {
 impl Component for MeshBinding {}
 
 impl ComponentActions for MeshBinding {
-    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) {
+    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
         if let Some(p) = self.provisional_mesh_wrapper.take() {
             self.mesh_wrapper = Some(
-                provisional_to_entities.get(&p).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &p)).clone()
+                *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?
             );
         }
+
+        Ok(())
+    }
+}
+
+impl SerializableComponent for MeshBinding {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write_optional_entity(self.mesh_wrapper.as_ref());
+        write_render_mesh_id(writer, self.id.as_ref());
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        let mesh_wrapper = reader.read_optional_entity()?;
+        let id = read_render_mesh_id(reader)?;
+
+        Ok(MeshBinding::new(id, mesh_wrapper))
     }
 }
 
@@ -76,6 +95,7 @@ pub struct GuiVertex {
 pub type Edge = (u32, u32);
 pub type Face = (u32, u32, u32);
 
+#[derive(Clone)]
 pub struct Mesh {
     pub vertices: Arc<Vec<Vertex>>,
     pub vertex_indices: Arc<Vec<u32>>,
@@ -120,6 +140,36 @@ impl Mesh {
 impl Component for Mesh {}
 impl ComponentActions for Mesh {}
 
+// Vertices are written as position, normal then texture coordinates, followed by the indices. Edges are rebuilt when it's loaded.
+impl SerializableComponent for Mesh {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write(self.vertices.len());
+        for vertex in self.vertices.iter() {
+            write_vec3(writer, &vertex.pos);
+            write_vec3(writer, &vertex.norm);
+            writer.write(vertex.tex_coord.x);
+            writer.write(vertex.tex_coord.y);
+        }
+
+        writer.write(self.vertex_indices.len());
+        for index in self.vertex_indices.iter() {
+            writer.write(index);
+        }
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        let vertex_count: usize = reader.read()?;
+        let vertices = (0..vertex_count)
+            .map(|_| Ok(Vertex { pos: read_vec3(reader)?, norm: read_vec3(reader)?, tex_coord: vec2(reader.read()?, reader.read()?) }))
+            .collect::<Result<Vec<Vertex>>>()?;
+
+        let index_count: usize = reader.read()?;
+        let vertex_indices = (0..index_count).map(|_| reader.read()).collect::<Result<Vec<u32>>>()?;
+
+        Mesh::new(vertices, vertex_indices)
+    }
+}
+
 pub fn load_obj_mesh(file_path: &str, normalize_positions: bool, switch_handedness: bool) -> Result<Mesh> {
     let mut reader = BufReader::new(File::open(file_path)?);
 
@@ -1,18 +1,55 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use std::time::{Duration, SystemTime};
 
 use crate::ecs::{ComponentActions, ProvisionalEntity};
 use crate::ecs::component::Component;
 use crate::ecs::entity::Entity;
-use crate::math::{get_scale_matrix, get_view_matrix, get_world_matrix, vec2, vec3, Mat4, Quat, Vec2, Vec3, QUAT_IDENTITY, VEC_2_ZERO, VEC_3_Y_AXIS, VEC_3_ZERO, VEC_3_Z_AXIS};
+use crate::ecs::error::EcsError;
+use crate::ecs::snapshot::{SerializableComponent, SnapshotReader, SnapshotWriter};
+use crate::math::{get_scale_matrix, get_view_matrix, get_world_matrix, quat, vec2, vec3, Mat4, Quat, Vec2, Vec3, MAT_4_IDENTITY, QUAT_IDENTITY, VEC_2_ZERO, VEC_3_Y_AXIS, VEC_3_ZERO, VEC_3_Z_AXIS};
 
 pub mod mesh;
 
+use mesh::RenderMeshId;
+
 /////////////////////////////////////////////////////////////////////////////
 /// Common
 /////////////////////////////////////////////////////////////////////////////
 
+// Snapshot values
+
+pub(in crate) fn write_vec3(writer: &mut SnapshotWriter, v: &Vec3) {
+    writer.write(v.x);
+    writer.write(v.y);
+    writer.write(v.z);
+}
+
+pub(in crate) fn read_vec3(reader: &mut SnapshotReader) -> Result<Vec3> {
+    Ok(vec3(reader.read()?, reader.read()?, reader.read()?))
+}
+
+pub(in crate) fn write_quat(writer: &mut SnapshotWriter, q: &Quat) {
+    writer.write(q.w);
+    writer.write(q.i);
+    writer.write(q.j);
+    writer.write(q.k);
+}
+
+pub(in crate) fn read_quat(reader: &mut SnapshotReader) -> Result<Quat> {
+    Ok(quat(reader.read()?, reader.read()?, reader.read()?, reader.read()?))
+}
+
+// Durations are written as whole nanoseconds, so that they're restored exactly
+pub(in crate) fn write_duration(writer: &mut SnapshotWriter, d: &Duration) {
+    writer.write(d.as_nanos());
+}
+
+pub(in crate) fn read_duration(reader: &mut SnapshotReader) -> Result<Duration> {
+    Ok(Duration::from_nanos(reader.read()?))
+}
+
 // Color
 
 #[derive(Clone, Copy, Debug)]
@@ -90,6 +127,77 @@ impl Default for Camera {
     }
 }
 
+// RenderAssets
+
+// Resource which names the meshes and textures that were created with the render engine. Render ids are only valid for the render
+//  engine they came from, so snapshots save these names instead, and look the ids up again when they're loaded.
+#[derive(Debug, Default)]
+pub struct RenderAssets {
+    mesh_ids: HashMap<String, RenderMeshId>,
+    mesh_names: HashMap<RenderMeshId, String>,
+    texture_ids: HashMap<String, RenderTextureId>,
+    texture_names: HashMap<RenderTextureId, String>,
+}
+
+impl RenderAssets {
+    pub fn insert_mesh(&mut self, name: &str, id: RenderMeshId) {
+        self.mesh_ids.insert(String::from(name), id);
+        self.mesh_names.insert(id, String::from(name));
+    }
+
+    pub fn insert_texture(&mut self, name: &str, id: RenderTextureId) {
+        self.texture_ids.insert(String::from(name), id);
+        self.texture_names.insert(id, String::from(name));
+    }
+
+    pub fn get_mesh_id(&self, name: &str) -> Option<RenderMeshId> {
+        self.mesh_ids.get(name).copied()
+    }
+
+    pub fn get_mesh_name(&self, id: &RenderMeshId) -> Option<&str> {
+        self.mesh_names.get(id).map(String::as_str)
+    }
+
+    pub fn get_texture_id(&self, name: &str) -> Option<RenderTextureId> {
+        self.texture_ids.get(name).copied()
+    }
+
+    pub fn get_texture_name(&self, id: &RenderTextureId) -> Option<&str> {
+        self.texture_names.get(id).map(String::as_str)
+    }
+}
+
+// Ids without a name are written as missing, since there'd be no way to find them again on load. Names are only looked up when the
+//  RenderAssets resource exists, so that e.g. headless games can load snapshots with unbound meshes and textures.
+
+pub(in crate) fn write_render_mesh_id(writer: &mut SnapshotWriter, id: Option<&RenderMeshId>) {
+    let name = id.and_then(|id| writer.get_resource::<RenderAssets>().and_then(|assets| assets.get_mesh_name(id).map(String::from)));
+    writer.write_option(name);
+}
+
+pub(in crate) fn read_render_mesh_id(reader: &mut SnapshotReader) -> Result<Option<RenderMeshId>> {
+    let name: Option<String> = reader.read_option()?;
+
+    match (name, reader.get_resource::<RenderAssets>()) {
+        (Some(name), Some(assets)) => assets.get_mesh_id(&name).map(Some).ok_or_else(|| anyhow!("Unknown mesh {}", name)),
+        _ => Ok(None),
+    }
+}
+
+pub(in crate) fn write_render_texture_id(writer: &mut SnapshotWriter, id: Option<&RenderTextureId>) {
+    let name = id.and_then(|id| writer.get_resource::<RenderAssets>().and_then(|assets| assets.get_texture_name(id).map(String::from)));
+    writer.write_option(name);
+}
+
+pub(in crate) fn read_render_texture_id(reader: &mut SnapshotReader) -> Result<Option<RenderTextureId>> {
+    let name: Option<String> = reader.read_option()?;
+
+    match (name, reader.get_resource::<RenderAssets>()) {
+        (Some(name), Some(assets)) => assets.get_texture_id(&name).map(Some).ok_or_else(|| anyhow!("Unknown texture {}", name)),
+        _ => Ok(None),
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 /// Components
 /////////////////////////////////////////////////////////////////////////////
@@ -123,19 +231,16 @@ impl ComponentActions for Viewport2D {}
 
 // Transform
 
+// The matrices are cached on first use, which only needs a shared borrow so that reading them doesn't count as changing the Transform
 #[derive(Clone, Debug)]
 pub struct Transform {
     pos: Vec3,
     rot: Quat,
     scl: Vec3,
 
-    pos_changed: bool,
-    rot_changed: bool,
-    scl_changed: bool,
-
-    world_mat: Option<Mat4>,
-    rot_mat: Option<Mat4>,
-    scl_mat: Option<Mat4>,
+    world_mat: OnceLock<Mat4>,
+    rot_mat: OnceLock<Mat4>,
+    scl_mat: OnceLock<Mat4>,
 }
 
 impl Transform {
@@ -145,44 +250,22 @@ impl Transform {
             rot,
             scl,
 
-            pos_changed: true,
-            rot_changed: true,
-            scl_changed: true,
-
-            world_mat: None,
-            rot_mat: None,
-            scl_mat: None,
+            world_mat: OnceLock::new(),
+            rot_mat: OnceLock::new(),
+            scl_mat: OnceLock::new(),
         }
     }
 
-    pub fn to_world_mat(&mut self) -> &Mat4 {
-        self.world_mat.get_or_insert_with(|| get_world_matrix(&self.pos, &self.rot, &self.scl))
-    }
-
-    pub fn to_rot_mat(&mut self) -> &Mat4 {
-        self.rot_mat.get_or_insert_with(|| self.rot.to_rotation_matrix())
-    }
-
-    pub fn to_scl_mat(&mut self) -> &Mat4 {
-        self.scl_mat.get_or_insert_with(|| get_scale_matrix(&self.scl))
-    }
-
-    pub fn is_pos_changed_since_last_frame(&self) -> bool {
-        self.pos_changed
+    pub fn to_world_mat(&self) -> &Mat4 {
+        self.world_mat.get_or_init(|| get_world_matrix(&self.pos, &self.rot, &self.scl))
     }
 
-    pub fn is_rot_changed_since_last_frame(&self) -> bool {
-        self.rot_changed
+    pub fn to_rot_mat(&self) -> &Mat4 {
+        self.rot_mat.get_or_init(|| self.rot.to_rotation_matrix())
     }
 
-    pub fn is_scl_changed_since_last_frame(&self) -> bool {
-        self.scl_changed
-    }
-
-    pub(in crate) fn reset_changed_flags(&mut self) {
-        self.pos_changed = false;
-        self.rot_changed = false;
-        self.scl_changed = false;
+    pub fn to_scl_mat(&self) -> &Mat4 {
+        self.scl_mat.get_or_init(|| get_scale_matrix(&self.scl))
     }
 
     pub fn get_pos(&self) -> &Vec3 {
@@ -193,9 +276,7 @@ impl Transform {
         if self.pos != pos {
             self.pos = pos;
 
-            self.pos_changed = true;
-
-            self.world_mat = None;
+            self.world_mat = OnceLock::new();
         }
     }
 
@@ -207,10 +288,8 @@ impl Transform {
         if self.rot != rot {
             self.rot = rot;
 
-            self.rot_changed = true;
-
-            self.world_mat = None;
-            self.rot_mat = None;
+            self.world_mat = OnceLock::new();
+            self.rot_mat = OnceLock::new();
         }
     }
 
@@ -222,10 +301,8 @@ impl Transform {
         if self.scl != scl {
             self.scl = scl;
 
-            self.scl_changed = true;
-
-            self.world_mat = None;
-            self.scl_mat = None;
+            self.world_mat = OnceLock::new();
+            self.scl_mat = OnceLock::new();
         }
     }
 }
@@ -237,13 +314,9 @@ impl Default for Transform {
             rot: QUAT_IDENTITY,
             scl: IDENTITY_SCALE_VEC,
 
-            pos_changed: true,
-            rot_changed: true,
-            scl_changed: true,
-
-            world_mat: None,
-            rot_mat: None,
-            scl_mat: None,
+            world_mat: OnceLock::new(),
+            rot_mat: OnceLock::new(),
+            scl_mat: OnceLock::new(),
         }
     }
 }
@@ -251,8 +324,54 @@ impl Default for Transform {
 impl Component for Transform {}
 impl ComponentActions for Transform {}
 
+// The cached matrices aren't saved, since they're rebuilt on demand anyway
+impl SerializableComponent for Transform {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        write_vec3(writer, &self.pos);
+        write_quat(writer, &self.rot);
+        write_vec3(writer, &self.scl);
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Transform::new(read_vec3(reader)?, read_quat(reader)?, read_vec3(reader)?))
+    }
+}
+
 pub const IDENTITY_SCALE_VEC: Vec3 = vec3(1.0, 1.0, 1.0);
 
+// GlobalTransform
+
+// The world matrix of an entity after applying the Transforms of all of its ancestors, which is kept up to date by the transform propagation system
+#[derive(Clone, Copy, Debug)]
+pub struct GlobalTransform {
+    world_mat: Mat4,
+}
+
+impl GlobalTransform {
+    pub fn get_world_mat(&self) -> &Mat4 {
+        &self.world_mat
+    }
+
+    pub fn get_pos(&self) -> Vec3 {
+        (self.world_mat * VEC_3_ZERO.to_vec4(1.0)).xyz()
+    }
+
+    pub(in crate) fn set_world_mat(&mut self, world_mat: Mat4) {
+        self.world_mat = world_mat;
+    }
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self {
+            world_mat: MAT_4_IDENTITY,
+        }
+    }
+}
+
+impl Component for GlobalTransform {}
+impl ComponentActions for GlobalTransform {}
+
 // ColorMaterial
 
 pub struct ColorMaterial {
@@ -294,11 +413,9 @@ impl Default for TimeDelta {
     }
 }
 
-impl Component for TimeDelta {}
-impl ComponentActions for TimeDelta {}
-
 // Timer
 
+#[derive(Clone)]
 pub struct Timer {
     pub start_value: f32,
     pub end_value: f32,
@@ -366,6 +483,26 @@ fn get_remaining_duration(start_value: f32, end_value: f32, initial_duration: Du
 impl Component for Timer {}
 impl ComponentActions for Timer {}
 
+impl SerializableComponent for Timer {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write(self.start_value);
+        writer.write(self.end_value);
+        write_duration(writer, &self.initial_duration);
+        writer.write(self.current_value);
+        writer.write_option(self.remaining_duration.map(|d| d.as_nanos()));
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Timer {
+            start_value: reader.read()?,
+            end_value: reader.read()?,
+            initial_duration: read_duration(reader)?,
+            current_value: reader.read()?,
+            remaining_duration: reader.read_option()?.map(Duration::from_nanos),
+        })
+    }
+}
+
 // Texture
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -399,11 +536,106 @@ impl TextureBinding {
 impl Component for TextureBinding {}
 
 impl ComponentActions for TextureBinding {
-    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) {
+    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
         if let Some(p) = self.provisional_texture_wrapper.take() {
             self.texture_wrapper = Some(
-                provisional_to_entities.get(&p).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &p)).clone()
+                *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?
             );
         }
+
+        Ok(())
+    }
+}
+
+impl SerializableComponent for TextureBinding {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write_optional_entity(self.texture_wrapper.as_ref());
+        write_render_texture_id(writer, self.id.as_ref());
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        let texture_wrapper = reader.read_optional_entity()?;
+        let id = read_render_texture_id(reader)?;
+
+        Ok(TextureBinding::new(id, texture_wrapper))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mesh::{create_cube_mesh, Mesh, MeshBinding};
+    use crate::ecs::component::ComponentManager;
+    use crate::ecs::resource::Resources;
+    use crate::ecs::system::SystemStage;
+    use crate::ecs::{ECSBuilder, ECSCommands, ECS};
+    use std::collections::HashSet;
+
+    fn build_ecs(render_assets: RenderAssets) -> ECS {
+        let mut ecs = ECSBuilder::default()
+            .with_serializable_component::<Mesh>("Mesh")
+            .with_serializable_component::<MeshBinding>("MeshBinding")
+            .build();
+        ecs.insert_resource(render_assets);
+
+        ecs
+    }
+
+    fn cube_assets(cube_mesh_id: usize) -> RenderAssets {
+        let mut render_assets = RenderAssets::default();
+        render_assets.insert_mesh("cube", RenderMeshId(cube_mesh_id));
+
+        render_assets
+    }
+
+    #[test]
+    fn snapshots_rebind_mesh_wrappers_and_render_ids_on_load() {
+        let mut ecs = build_ecs(cube_assets(7));
+        let wrapper = ecs.create_entity();
+        ecs.attach_provisional_component(&wrapper, create_cube_mesh());
+        ecs.attach_provisional_component(&wrapper, MeshBinding::new_provisional(Some(RenderMeshId(7)), Some(wrapper)));
+        let block = ecs.create_entity();
+        ecs.attach_provisional_component(&block, MeshBinding::new_provisional(Some(RenderMeshId(7)), Some(wrapper)));
+        ecs.invoke_systems();
+        let snapshot = ecs.save_snapshot();
+
+        // Entities already in the new game mean that loaded entities can't end up with their old indexes
+        let mut new_ecs = build_ecs(cube_assets(3));
+        (0..5).for_each(|_| { new_ecs.create_entity(); });
+        new_ecs.invoke_systems();
+        let loaded = new_ecs.load_snapshot(&snapshot).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        let (loaded_wrapper, loaded_block) = (loaded[0], loaded[1]);
+        new_ecs.insert_resource(None::<(MeshBinding, bool)>);
+        new_ecs.register_system(move |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+            let binding = *components.get_component::<MeshBinding>(&loaded_block).unwrap();
+            let is_wrapper_mesh = binding.mesh_wrapper.is_some_and(|e| components.get_component::<Mesh>(&e).is_some());
+            *resources.get_mut::<Option<(MeshBinding, bool)>>().unwrap() = Some((binding, is_wrapper_mesh));
+        }, vec![], SystemStage::Update);
+        new_ecs.invoke_systems();
+
+        let (binding, is_wrapper_mesh) = new_ecs.get_resource::<Option<(MeshBinding, bool)>>().unwrap().unwrap();
+        assert_eq!(binding, MeshBinding::new(Some(RenderMeshId(3)), Some(loaded_wrapper)));
+        assert!(is_wrapper_mesh);
+
+        assert!(build_ecs(RenderAssets::default()).load_snapshot(&snapshot).is_err());
+    }
+
+    #[test]
+    fn prefabs_loaded_from_data_keep_their_render_ids() {
+        let mut ecs = build_ecs(cube_assets(7));
+        let prefabs = ecs.load_prefabs("prefab Block\n  MeshBinding - cube\n").unwrap();
+        let block = ecs.spawn_prefab(&prefabs[0]);
+        ecs.invoke_systems();
+
+        let block = ecs.resolve(&block).unwrap();
+        ecs.insert_resource(None::<MeshBinding>);
+        ecs.register_system(move |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+            *resources.get_mut::<Option<MeshBinding>>().unwrap() = components.get_component::<MeshBinding>(&block).map(|b| *b);
+        }, vec![], SystemStage::Update);
+        ecs.invoke_systems();
+
+        assert_eq!(*ecs.get_resource::<Option<MeshBinding>>().unwrap(), Some(MeshBinding::new(Some(RenderMeshId(7)), None)));
     }
 }
@@ -0,0 +1,109 @@
+use std::time::{Duration, SystemTime};
+
+use crate::ecs::ECS;
+
+// Resource which frame time should be read from, rather than reading the system time directly. Manual clocks only move when advanced,
+//  and App advances them by a fixed step before every frame, so that a run's frame times are the same every time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Manual { now: SystemTime, step: Duration },
+}
+
+impl Clock {
+    // Starts at the Unix epoch
+    pub fn manual(step: Duration) -> Self {
+        Clock::Manual { now: SystemTime::UNIX_EPOCH, step }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        match self {
+            Clock::System => SystemTime::now(),
+            Clock::Manual { now, .. } => *now,
+        }
+    }
+
+    // Has no effect on the system clock
+    pub fn advance(&mut self, duration: Duration) {
+        if let Clock::Manual { now, .. } = self {
+            *now += duration;
+        }
+    }
+
+    fn tick(&mut self) {
+        if let Clock::Manual { step, .. } = *self {
+            self.advance(step);
+        }
+    }
+}
+
+// Runs the ECS frame by frame until it shuts down. Nothing here depends on a window or render engine, so with a manual clock
+//  systems can be run for a set number of frames and checked afterwards, e.g. in tests.
+pub struct App {
+    ecs: ECS,
+    frame_count: u64,
+}
+
+impl App {
+    pub fn new(ecs: ECS) -> Self {
+        Self {
+            ecs,
+            frame_count: 0,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.ecs.insert_resource(clock);
+
+        self
+    }
+
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+
+    // Returns the number of frames which were run, which is less than frame_count if the ECS shut down first
+    pub fn run_frames(&mut self, frame_count: u64) -> u64 {
+        let start_frame = self.frame_count;
+
+        for _ in 0..frame_count {
+            if !self.step() {
+                break;
+            }
+        }
+
+        self.frame_count - start_frame
+    }
+
+    // Runs a single frame, and returns false without running anything once the ECS has shut down
+    pub fn step(&mut self) -> bool {
+        if self.ecs.is_shutdown {
+            return false;
+        }
+
+        if let Some(mut clock) = self.ecs.get_resource_mut::<Clock>() {
+            clock.tick();
+        }
+
+        let is_running = self.ecs.invoke_systems();
+
+        if is_running {
+            self.frame_count += 1;
+        }
+
+        is_running
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_ecs(&self) -> &ECS {
+        &self.ecs
+    }
+
+    pub fn get_ecs_mut(&mut self) -> &mut ECS {
+        &mut self.ecs
+    }
+}
@@ -1,99 +1,345 @@
 use anyhow::{anyhow, Result};
-use std::any::{Any, TypeId};
-use std::cell::RefCell;
-use std::collections::HashMap;
-
-use crate::ecs::{ComponentActions, Signature};
+use std::any::{type_name, Any, TypeId};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::ecs::{ComponentActions, ECSCommands, ProvisionalEntity};
 use crate::ecs::entity::Entity;
+use crate::ecs::error::EcsError;
+use crate::ecs::inspect::{ComponentInfo, EntityInfo};
+use crate::ecs::query::{Query, QueryData, QueryFilter};
+use crate::ecs::signature::Signature;
+
+pub trait Component: ComponentActions + Send + Sync + Sized + 'static {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SystemSignature {
+    all: Signature,
+    none: Signature,
+    any: Signature,
+}
+
+impl SystemSignature {
+    fn new(all: Signature) -> Self {
+        Self {
+            all,
+            none: Signature::new(),
+            any: Signature::new(),
+        }
+    }
 
-pub trait Component: ComponentActions + Sized + 'static {}
+    // Excludes entities which have any of the components required by `excluded`
+    pub fn without(mut self, excluded: SystemSignature) -> Self {
+        self.none |= &excluded.all;
+
+        self
+    }
+
+    // Additionally requires at least one of the components required by `any_of`
+    pub fn with_any(mut self, any_of: SystemSignature) -> Self {
+        self.any |= &any_of.all;
+
+        self
+    }
+
+    pub(in crate::ecs) fn matches(&self, entity_signature: &Signature) -> bool {
+        entity_signature.contains_all(&self.all)
+            && !entity_signature.intersects(&self.none)
+            && (self.any.is_empty() || entity_signature.intersects(&self.any))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComponentEventKind {
+    Added,
+    Removed,
+    Changed,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct SystemSignature(pub(in crate::ecs) Signature);
+pub struct ComponentEvent {
+    pub entity: Entity,
+    pub kind: ComponentEventKind,
+}
+
+pub(in crate::ecs) trait AnyComponentArray: Send + Sync {
+    fn insert_component(&mut self, entity: &Entity, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError>;
+
+    fn replace_component(&mut self, entity: &Entity, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError>;
+
+    fn remove_component(&mut self, entity: &Entity, commands: &mut ECSCommands) -> Result<(), EcsError>;
+
+    fn remove_components(&mut self, entities: &HashSet<Entity>, commands: &mut ECSCommands);
+
+    fn advance_frame(&mut self, tick: u32);
 
-pub struct ComponentArray {
+    fn advance_tick(&mut self, tick: u32);
+
+    fn has_component(&self, entity: &Entity) -> bool;
+
+    fn get_entities(&self) -> &[Entity];
+
+    fn get_type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+thread_local! {
+    // Tick at which the system running on this thread last ran. Unset outside of systems and during a system's first run, in which
+    //  case anything added or changed since the start of the current frame matches.
+    static SYSTEM_LAST_RUN_TICK: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+// The changed tick is atomic since it's updated whenever a component is accessed mutably, which only needs a shared reference to the array
+struct ComponentTicks {
+    added: u32,
+    changed: AtomicU32,
+}
+
+impl ComponentTicks {
+    fn new(tick: u32) -> Self {
+        Self {
+            added: tick,
+            changed: AtomicU32::new(tick),
+        }
+    }
+}
+
+pub struct ComponentArray<T: Component> {
     entity_to_index: Vec<usize>,
     index_to_entity: Vec<Entity>,
-    components: Vec<RefCell<Box<dyn Any>>>,
+    components: Vec<RwLock<T>>,
+    ticks: Vec<ComponentTicks>,
+    tick: u32,
+    frame_tick: u32,
+    // Events are kept until the end of the frame after the one they were recorded in, so that every system sees each event at least once
+    previous_events: Vec<ComponentEvent>,
+    current_events: Vec<ComponentEvent>,
 }
 
 const INVALID_COMPONENT_INDEX: usize = usize::MAX;
 
-impl ComponentArray {
+impl<T: Component> ComponentArray<T> {
     pub(in crate::ecs) fn new(initial_capacity: usize) -> Self {
         Self {
             entity_to_index: vec![INVALID_COMPONENT_INDEX; initial_capacity],
             index_to_entity: Vec::with_capacity(initial_capacity),
             components: Vec::with_capacity(initial_capacity),
+            ticks: Vec::with_capacity(initial_capacity),
+            tick: 0,
+            frame_tick: 0,
+            previous_events: Vec::new(),
+            current_events: Vec::new(),
+        }
+    }
+
+    pub fn get_component(&self, entity: &Entity) -> Option<RwLockReadGuard<'_, T>> {
+        self.try_get_component(entity).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn get_mut_component(&self, entity: &Entity) -> Option<RwLockWriteGuard<'_, T>> {
+        self.try_get_mut_component(entity).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_component(&self, entity: &Entity) -> Result<Option<RwLockReadGuard<'_, T>>> {
+        let index = match self.get_index(entity) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let component = self.components[index].try_read()
+            .map_err(|_| anyhow!("Component {} for entity {:?} is already mutably borrowed", type_name::<T>(), entity))?;
+
+        Ok(Some(component))
+    }
+
+    pub fn try_get_mut_component(&self, entity: &Entity) -> Result<Option<RwLockWriteGuard<'_, T>>> {
+        let index = match self.get_index(entity) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let component = self.components[index].try_write()
+            .map_err(|_| anyhow!("Component {} for entity {:?} is already borrowed", type_name::<T>(), entity))?;
+
+        self.ticks[index].changed.store(self.tick, Ordering::Relaxed);
+
+        Ok(Some(component))
+    }
+
+    pub fn has_component(&self, entity: &Entity) -> bool {
+        self.get_index(entity).is_some()
+    }
+
+    pub fn is_added(&self, entity: &Entity) -> bool {
+        self.get_index(entity).is_some_and(|index| self.is_since_last_run(self.ticks[index].added))
+    }
+
+    // Any mutable access counts as a change, whether or not the component was actually modified
+    pub fn is_changed(&self, entity: &Entity) -> bool {
+        self.get_index(entity).is_some_and(|index| self.is_since_last_run(self.ticks[index].changed.load(Ordering::Relaxed)))
+    }
+
+    // Ticks wrap around, so they're compared by how long ago they were rather than by value
+    fn is_since_last_run(&self, tick: u32) -> bool {
+        let last_run_tick = SYSTEM_LAST_RUN_TICK.with(|t| t.get()).unwrap_or(self.frame_tick.wrapping_sub(1));
+
+        self.tick.wrapping_sub(tick) < self.tick.wrapping_sub(last_run_tick)
+    }
+
+    pub fn get_events(&self) -> impl Iterator<Item = &ComponentEvent> {
+        self.previous_events.iter().chain(self.current_events.iter())
+    }
+
+    fn take_component(&mut self, entity: &Entity) -> Result<T, EcsError> {
+        let dst_index = self.get_index(entity)
+            .ok_or(EcsError::ComponentNotFound { entity: *entity, component: type_name::<T>() })?;
+
+        self.entity_to_index[entity.index] = INVALID_COMPONENT_INDEX;
+
+        self.index_to_entity.swap_remove(dst_index);
+        let component = self.components.swap_remove(dst_index);
+        self.ticks.swap_remove(dst_index);
+
+        if let Some(moved_entity) = self.index_to_entity.get(dst_index) {
+            self.entity_to_index[moved_entity.index] = dst_index;
+        }
+
+        Ok(component.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn record_event(&mut self, entity: &Entity, kind: ComponentEventKind) {
+        self.current_events.push(ComponentEvent { entity: *entity, kind });
+    }
+
+    fn get_index(&self, entity: &Entity) -> Option<usize> {
+        match self.entity_to_index.get(entity.index) {
+            Some(&index) if index != INVALID_COMPONENT_INDEX && self.index_to_entity[index] == *entity => Some(index),
+            _ => None,
         }
     }
+}
 
-    pub(in crate::ecs) fn insert_component(&mut self, entity: &Entity, component: Box<dyn ComponentActions>) -> Result<()> {
-        if entity.0 < self.entity_to_index.len() && self.entity_to_index[entity.0] != INVALID_COMPONENT_INDEX {
-            return Err(anyhow!("Component already exists for entity {:?}", entity));
+impl<T: Component> AnyComponentArray for ComponentArray<T> {
+    fn insert_component(&mut self, entity: &Entity, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        if entity.index < self.entity_to_index.len() && self.entity_to_index[entity.index] != INVALID_COMPONENT_INDEX {
+            return Err(EcsError::DuplicateComponent { entity: *entity, component: type_name::<T>() });
         }
 
-        while entity.0 >= self.entity_to_index.len() {
-            self.entity_to_index.resize(self.entity_to_index.len() * 2, INVALID_COMPONENT_INDEX);
+        let mut component = component.as_any_box().downcast::<T>()
+            .unwrap_or_else(|_| panic!("Internal error: Failed to downcast component to {}", type_name::<T>()));
+
+        component.on_add(entity, commands);
+        self.record_event(entity, ComponentEventKind::Added);
+
+        while entity.index >= self.entity_to_index.len() {
+            let new_len = (self.entity_to_index.len() * 2).max(1);
+            self.entity_to_index.resize(new_len, INVALID_COMPONENT_INDEX);
         }
 
-        self.entity_to_index[entity.0] = self.index_to_entity.len();
+        self.entity_to_index[entity.index] = self.index_to_entity.len();
 
-        self.index_to_entity.push(entity.clone());
+        self.index_to_entity.push(*entity);
 
-        self.components.push(RefCell::new(component.as_any_box()));
+        self.components.push(RwLock::new(*component));
+        self.ticks.push(ComponentTicks::new(self.tick));
 
         Ok(())
     }
 
-    pub(in crate::ecs) fn remove_component(&mut self, entity: &Entity) -> Result<()> {
-        if entity.0 >= self.entity_to_index.len() || self.entity_to_index[entity.0] == INVALID_COMPONENT_INDEX {
-            return Err(anyhow!("No such component exists for entity {:?}", entity));
-        }
+    fn replace_component(&mut self, entity: &Entity, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        let index = match self.get_index(entity) {
+            Some(index) => index,
+            None => return self.insert_component(entity, component, commands),
+        };
 
-        let dst_index = self.entity_to_index[entity.0];
-        self.entity_to_index[entity.0] = INVALID_COMPONENT_INDEX;
+        let component = component.as_any_box().downcast::<T>()
+            .unwrap_or_else(|_| panic!("Internal error: Failed to downcast component to {}", type_name::<T>()));
 
-        let moved_entity = self.index_to_entity.pop().unwrap_or_else(|| panic!("Internal error: index_to_entity Vec is empty"));
-        let moved_component = self.components.pop().unwrap_or_else(|| panic!("Internal error: components Vec is empty"));
-        let should_move = moved_entity != *entity;
+        let mut old_component = std::mem::replace(&mut self.components[index], RwLock::new(*component)).into_inner().unwrap_or_else(|e| e.into_inner());
 
-        if should_move {
-            self.index_to_entity[dst_index] = moved_entity;
-            self.components[dst_index] = moved_component;
-            self.entity_to_index[moved_entity.0] = dst_index;
-        }
+        self.ticks[index].changed.store(self.tick, Ordering::Relaxed);
+
+        old_component.on_replace(entity, commands);
+        self.record_event(entity, ComponentEventKind::Changed);
 
         Ok(())
     }
 
-    pub fn get_component<T: Component>(&self, entity: &Entity) -> Option<&T> {
-        if entity.0 >= self.entity_to_index.len() || self.entity_to_index[entity.0] == INVALID_COMPONENT_INDEX {
-            return None;
-        }
+    fn remove_component(&mut self, entity: &Entity, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        let mut component = self.take_component(entity)?;
 
-        let index = self.entity_to_index[entity.0];
+        component.on_remove(entity, commands);
+        self.record_event(entity, ComponentEventKind::Removed);
 
-        unsafe { Some(&*(self.components[index].borrow().as_ref().downcast_ref::<T>().unwrap_or_else(|| panic!("Internal error: Failed to downcast component")) as *const T)) }
+        Ok(())
     }
 
-    pub fn get_mut_component<T: Component>(&self, entity: &Entity) -> Option<&mut T> {
-        if entity.0 >= self.entity_to_index.len() || self.entity_to_index[entity.0] == INVALID_COMPONENT_INDEX {
-            return None;
+    fn remove_components(&mut self, entities: &HashSet<Entity>, commands: &mut ECSCommands) {
+        // Sweep the dense array once rather than looking up every removed entity, unless the removed set is the smaller of the two
+        if entities.len() < self.index_to_entity.len() {
+            for entity in entities.iter() {
+                if self.has_component(entity) {
+                    self.remove_component(entity, commands).unwrap_or_else(|e| panic!("Internal error: {}", e));
+                }
+            }
+            return;
         }
 
-        let index = self.entity_to_index[entity.0];
+        let mut index = 0;
+        while index < self.index_to_entity.len() {
+            let entity = self.index_to_entity[index];
+
+            if entities.contains(&entity) {
+                self.remove_component(&entity, commands).unwrap_or_else(|e| panic!("Internal error: {}", e));
+            } else {
+                index += 1;
+            }
+        }
+    }
 
-        // TODO: ensure actual borrow checking here... there could be a way to do it with no unsafe code
-        // I luv Rust...
-        unsafe { Some(&mut *(self.components[index].borrow_mut().as_mut().downcast_mut::<T>().unwrap_or_else(|| panic!("Internal error: Failed to downcast component")) as *mut T)) }
+    fn advance_frame(&mut self, tick: u32) {
+        self.tick = tick;
+        self.frame_tick = tick;
+        self.previous_events = std::mem::take(&mut self.current_events);
+    }
+
+    fn advance_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    fn has_component(&self, entity: &Entity) -> bool {
+        ComponentArray::has_component(self, entity)
+    }
+
+    fn get_entities(&self) -> &[Entity] {
+        &self.index_to_entity
+    }
+
+    fn get_type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
+type ComponentFormatter = fn(&ComponentManager, &Entity) -> Result<Option<String>>;
+
 pub struct ComponentManager {
-    component_registration_bit: Signature,
+    component_count: usize,
     component_types_to_signatures: HashMap<TypeId, Signature>,
-    component_types_to_arrays: HashMap<TypeId, ComponentArray>,
+    component_types_to_arrays: HashMap<TypeId, Box<dyn AnyComponentArray>>,
+    component_types_to_formatters: HashMap<TypeId, ComponentFormatter>,
+    live_generations: Vec<Option<u32>>,
+    // Every entity is created from a provisional one, and that mapping is kept for as long as the entity is alive
+    provisional_to_entities: HashMap<ProvisionalEntity, Entity>,
+    entities_to_provisional: Vec<Option<ProvisionalEntity>>,
+    tick: u32,
     pub(in crate::ecs) initial_capacity: usize,
 }
 
@@ -102,93 +348,293 @@ const DEFAULT_INITIAL_COMPONENT_CAPACITY: usize = 64;
 impl ComponentManager {
     pub(in crate::ecs) fn new(initial_capacity: usize) -> Self {
         Self {
-            component_registration_bit: 1,
+            component_count: 0,
             component_types_to_signatures: HashMap::with_capacity(DEFAULT_INITIAL_COMPONENT_CAPACITY),
             component_types_to_arrays: HashMap::with_capacity(DEFAULT_INITIAL_COMPONENT_CAPACITY),
+            component_types_to_formatters: HashMap::new(),
+            live_generations: vec![None; initial_capacity],
+            provisional_to_entities: HashMap::with_capacity(initial_capacity),
+            entities_to_provisional: vec![None; initial_capacity],
+            tick: 0,
             initial_capacity,
         }
     }
 
-    pub(in crate::ecs) fn attach_component(&mut self, entity: &Entity, type_id: TypeId, component: Box<dyn ComponentActions>) -> Result<()> {
-        let comp_arr = self.component_types_to_arrays.get_mut(&type_id).map(|c| Ok(c))
-            .unwrap_or(Err(anyhow!("No such component has been registered")))?;
+    pub(in crate::ecs) fn attach_component(&mut self, entity: &Entity, type_id: TypeId, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::EntityNotFound(*entity));
+        }
+
+        let comp_arr = self.component_types_to_arrays.get_mut(&type_id)
+            .ok_or(EcsError::UnknownComponent(type_id))?;
 
-        comp_arr.insert_component(entity, component)?;
+        comp_arr.insert_component(entity, component, commands)?;
 
         Ok(())
     }
 
-    pub(in crate::ecs) fn detach_component(&mut self, entity: &Entity, type_id: TypeId) -> Result<()> {
-        let comp_arr = self.component_types_to_arrays.get_mut(&type_id).map(|c| Ok(c))
-            .unwrap_or(Err(anyhow!("No such component has been registered")))?;
+    pub(in crate::ecs) fn replace_component(&mut self, entity: &Entity, type_id: TypeId, component: Box<dyn ComponentActions>, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::EntityNotFound(*entity));
+        }
+
+        let comp_arr = self.component_types_to_arrays.get_mut(&type_id)
+            .ok_or(EcsError::UnknownComponent(type_id))?;
 
-        comp_arr.remove_component(entity)?;
+        comp_arr.replace_component(entity, component, commands)?;
 
         Ok(())
     }
 
-    pub(in crate::ecs) fn get_signature(&self, type_id: TypeId) -> Result<Signature> {
-        let signature = self.component_types_to_signatures.get(&type_id).map(|s| Ok(s))
-            .unwrap_or(Err(anyhow!("No such component has been registered")))?;
+    pub(in crate::ecs) fn detach_component(&mut self, entity: &Entity, type_id: TypeId, commands: &mut ECSCommands) -> Result<(), EcsError> {
+        let comp_arr = self.component_types_to_arrays.get_mut(&type_id)
+            .ok_or(EcsError::UnknownComponent(type_id))?;
+
+        comp_arr.remove_component(entity, commands)?;
 
-        Ok(*signature)
+        Ok(())
     }
 
-    pub(in crate::ecs) fn register_component<T: Component>(&mut self) -> Result<()> {
+    pub(in crate::ecs) fn get_signature(&self, type_id: TypeId) -> Result<Signature, EcsError> {
+        let signature = self.component_types_to_signatures.get(&type_id)
+            .ok_or(EcsError::UnknownComponent(type_id))?;
+
+        Ok(signature.clone())
+    }
+
+    pub(in crate::ecs) fn register_component<T: Component>(&mut self) -> Result<(), EcsError> {
         let type_id = TypeId::of::<T>();
 
         if self.component_types_to_arrays.contains_key(&type_id) {
-            return Err(anyhow!("The component is already registered"));
+            return Err(EcsError::ComponentAlreadyRegistered(type_name::<T>()));
         }
 
-        if self.component_registration_bit == 0 {
-            return Err(anyhow!("The maximum number of components has already been registered"));
-        }
-
-        let component_signature = self.component_registration_bit;
-        self.component_registration_bit <<= 1;
+        let component_signature = Signature::from_bit(self.component_count);
+        self.component_count += 1;
 
-        self.component_types_to_arrays.insert(type_id, ComponentArray::new(self.initial_capacity));
+        self.component_types_to_arrays.insert(type_id, Box::new(ComponentArray::<T>::new(self.initial_capacity)));
         self.component_types_to_signatures.insert(type_id, component_signature);
 
         Ok(())
     }
 
-    pub fn get_component<T: Component>(&self, entity: &Entity) -> Option<&T> {
-        match self.component_types_to_arrays.get(&TypeId::of::<T>()) {
-            Some(comp_arr) => comp_arr.get_component::<T>(entity),
-            None => None,
+    // Independent of registering the component itself, so that any component can opt in regardless of how it was registered
+    pub(in crate::ecs) fn register_debug_output<T: Component + Debug>(&mut self) {
+        self.component_types_to_formatters.insert(TypeId::of::<T>(), |components, entity| {
+            Ok(components.try_get_component::<T>(entity)?.map(|component| format!("{:?}", *component)))
+        });
+    }
+
+    pub fn get_component<T: Component>(&self, entity: &Entity) -> Option<RwLockReadGuard<'_, T>> {
+        self.get_array::<T>().and_then(|comp_arr| comp_arr.get_component(entity))
+    }
+
+    pub fn get_mut_component<T: Component>(&self, entity: &Entity) -> Option<RwLockWriteGuard<'_, T>> {
+        self.get_array::<T>().and_then(|comp_arr| comp_arr.get_mut_component(entity))
+    }
+
+    pub fn try_get_component<T: Component>(&self, entity: &Entity) -> Result<Option<RwLockReadGuard<'_, T>>> {
+        match self.get_array::<T>() {
+            Some(comp_arr) => comp_arr.try_get_component(entity),
+            None => Ok(None),
         }
     }
 
-    pub fn get_mut_component<T: Component>(&self, entity: &Entity) -> Option<&mut T> {
-        match self.component_types_to_arrays.get(&TypeId::of::<T>()) {
-            Some(comp_arr) => comp_arr.get_mut_component(entity),
-            None => None,
+    pub fn try_get_mut_component<T: Component>(&self, entity: &Entity) -> Result<Option<RwLockWriteGuard<'_, T>>> {
+        match self.get_array::<T>() {
+            Some(comp_arr) => comp_arr.try_get_mut_component(entity),
+            None => Ok(None),
         }
     }
 
-    pub(in crate::ecs) fn handle_entity_removed(&mut self, entity: &Entity) {
-        self.component_types_to_arrays.values_mut().for_each(|comp_arr| {
-            comp_arr.remove_component(entity).unwrap_or_default();
+    pub fn get_array<T: Component>(&self) -> Option<&ComponentArray<T>> {
+        self.component_types_to_arrays.get(&TypeId::of::<T>())
+            .map(|comp_arr| comp_arr.as_any().downcast_ref::<ComponentArray<T>>()
+                .unwrap_or_else(|| panic!("Internal error: component array has the wrong type for {}", type_name::<T>())))
+    }
+
+    // Components of type T which were added, removed or replaced during this frame or the previous one
+    pub fn get_component_events<T: Component>(&self) -> impl Iterator<Item = &ComponentEvent> {
+        self.get_array::<T>().into_iter().flat_map(|comp_arr| comp_arr.get_events())
+    }
+
+    // Outside of systems, added and changed ticks are compared against the current frame's first tick. The previous frame's component
+    //  events are dropped.
+    pub(in crate::ecs) fn advance_frame(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+
+        let tick = self.tick;
+        self.component_types_to_arrays.values_mut().for_each(|comp_arr| comp_arr.advance_frame(tick));
+    }
+
+    // Called before each batch of systems, so that every system can tell what was added or changed since it last ran
+    pub(in crate::ecs) fn advance_tick(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+
+        let tick = self.tick;
+        self.component_types_to_arrays.values_mut().for_each(|comp_arr| comp_arr.advance_tick(tick));
+    }
+
+    // Added and Changed filters used by the system match anything added or changed after last_run_tick. Returns the tick the system
+    //  ran at.
+    pub(in crate::ecs) fn run_as_system(&self, last_run_tick: Option<u32>, run: impl FnOnce()) -> u32 {
+        SYSTEM_LAST_RUN_TICK.with(|t| t.set(last_run_tick));
+        run();
+        SYSTEM_LAST_RUN_TICK.with(|t| t.set(None));
+
+        self.tick
+    }
+
+    pub fn has_component<T: Component>(&self, entity: &Entity) -> bool {
+        self.component_types_to_arrays.get(&TypeId::of::<T>()).is_some_and(|comp_arr| comp_arr.has_component(entity))
+    }
+
+    pub(in crate::ecs) fn get_entities_with(&self, type_id: &TypeId) -> &[Entity] {
+        self.component_types_to_arrays.get(type_id).map(|comp_arr| comp_arr.get_entities()).unwrap_or_default()
+    }
+
+    pub fn query<Q: QueryData>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(self)
+    }
+
+    pub fn is_alive(&self, entity: &Entity) -> bool {
+        self.live_generations.get(entity.index).is_some_and(|g| *g == Some(entity.generation))
+    }
+
+    pub fn resolve(&self, provisional_entity: &ProvisionalEntity) -> Option<Entity> {
+        self.provisional_to_entities.get(provisional_entity).copied()
+    }
+
+    // Every live entity, ordered by index
+    pub fn get_entities(&self) -> Vec<Entity> {
+        self.live_generations.iter().enumerate()
+            .filter_map(|(index, generation)| generation.map(|generation| Entity { index, generation }))
+            .collect()
+    }
+
+    // Components are ordered by type name, so that the same entity is always inspected the same way
+    pub fn inspect_entity(&self, entity: &Entity) -> Option<EntityInfo> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        let mut components: Vec<&'static str> = self.component_types_to_arrays.values()
+            .filter(|comp_arr| comp_arr.has_component(entity))
+            .map(|comp_arr| comp_arr.get_type_name())
+            .collect();
+
+        components.sort_unstable();
+
+        Some(EntityInfo { entity: *entity, components })
+    }
+
+    pub fn inspect_entities(&self) -> Vec<EntityInfo> {
+        self.get_entities().iter().filter_map(|entity| self.inspect_entity(entity)).collect()
+    }
+
+    // Every registered component type, ordered by type name, along with the number of entities which have it
+    pub fn inspect_components(&self) -> Vec<ComponentInfo> {
+        let mut components: Vec<ComponentInfo> = self.component_types_to_arrays.values()
+            .map(|comp_arr| ComponentInfo { name: comp_arr.get_type_name(), entity_count: comp_arr.get_entities().len() })
+            .collect();
+
+        components.sort_unstable_by_key(|c| c.name);
+
+        components
+    }
+
+    // Lists the entity's components, with the values of those registered with ECSBuilder::with_debug_output. Components which are
+    //  currently mutably borrowed are skipped rather than waited on, so this is safe to call from within a system.
+    pub fn dump_entity(&self, entity: &Entity) -> Option<String> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        let mut components: Vec<(&'static str, Option<ComponentFormatter>)> = self.component_types_to_arrays.iter()
+            .filter(|(_, comp_arr)| comp_arr.has_component(entity))
+            .map(|(type_id, comp_arr)| (comp_arr.get_type_name(), self.component_types_to_formatters.get(type_id).copied()))
+            .collect();
+
+        components.sort_unstable_by_key(|(name, _)| *name);
+
+        let mut dump = format!("{:?}\n", entity);
+
+        for (name, formatter) in components {
+            let value = match formatter.map(|format| format(self, entity)) {
+                Some(Ok(Some(value))) => format!(": {}", value),
+                Some(Err(_)) => String::from(": <borrowed>"),
+                _ => String::new(),
+            };
+
+            dump.push_str(&format!("  {}{}\n", name, value));
+        }
+
+        Some(dump)
+    }
+
+    pub fn dump_world(&self) -> String {
+        self.get_entities().iter().filter_map(|entity| self.dump_entity(entity)).collect()
+    }
+
+    pub(in crate::ecs) fn get_provisional_entities(&self) -> &HashMap<ProvisionalEntity, Entity> {
+        &self.provisional_to_entities
+    }
+
+    pub(in crate::ecs) fn handle_entity_created(&mut self, entity: &Entity, provisional_entity: ProvisionalEntity) {
+        if entity.index >= self.live_generations.len() {
+            let new_len = (entity.index + 1).max(self.live_generations.len() * 2);
+            self.live_generations.resize(new_len, None);
+            self.entities_to_provisional.resize(new_len, None);
+        }
+
+        self.live_generations[entity.index] = Some(entity.generation);
+        self.entities_to_provisional[entity.index] = Some(provisional_entity);
+        self.provisional_to_entities.insert(provisional_entity, *entity);
+    }
+
+    pub(in crate::ecs) fn handle_entity_removed(&mut self, entity: &Entity, commands: &mut ECSCommands) {
+        self.component_types_to_arrays.values_mut().filter(|comp_arr| comp_arr.has_component(entity)).for_each(|comp_arr| {
+            comp_arr.remove_component(entity, commands).unwrap_or_else(|e| panic!("Internal error: {}", e));
         });
+
+        self.forget_entity(entity);
+    }
+
+    pub(in crate::ecs) fn handle_entities_removed(&mut self, entities: &HashSet<Entity>, commands: &mut ECSCommands) {
+        self.component_types_to_arrays.values_mut().for_each(|comp_arr| comp_arr.remove_components(entities, commands));
+
+        entities.iter().for_each(|entity| self.forget_entity(entity));
+    }
+
+    fn forget_entity(&mut self, entity: &Entity) {
+        if self.is_alive(entity) {
+            self.live_generations[entity.index] = None;
+
+            if let Some(provisional_entity) = self.entities_to_provisional[entity.index].take() {
+                self.provisional_to_entities.remove(&provisional_entity);
+            }
+        }
     }
 
     pub fn get_system_signature_0(&self) -> Result<SystemSignature> {
-        Ok(SystemSignature(0))
+        Ok(SystemSignature::new(Signature::new()))
     }
 
     pub fn get_system_signature_1<A: Component>(&self) -> Result<SystemSignature> {
         let sig = self.get_signature(TypeId::of::<A>())?;
 
-        Ok(SystemSignature(sig))
+        Ok(SystemSignature::new(sig))
     }
 
     pub fn get_system_signature_2<A: Component, B: Component>(&self) -> Result<SystemSignature> {
         let sig_a = self.get_signature(TypeId::of::<A>())?;
         let sig_b = self.get_signature(TypeId::of::<B>())?;
 
-        Ok(SystemSignature(sig_a | sig_b))
+        Ok(SystemSignature::new(sig_a | &sig_b))
     }
 
     pub fn get_system_signature_3<A: Component, B: Component, C: Component>(&self) -> Result<SystemSignature> {
@@ -196,7 +642,7 @@ impl ComponentManager {
         let sig_b = self.get_signature(TypeId::of::<B>())?;
         let sig_c = self.get_signature(TypeId::of::<C>())?;
 
-        Ok(SystemSignature(sig_a | sig_b | sig_c))
+        Ok(SystemSignature::new(sig_a | &sig_b | &sig_c))
     }
 
     pub fn get_system_signature_4<A: Component, B: Component, C: Component, D: Component>(&self) -> Result<SystemSignature> {
@@ -205,6 +651,6 @@ impl ComponentManager {
         let sig_c = self.get_signature(TypeId::of::<C>())?;
         let sig_d = self.get_signature(TypeId::of::<D>())?;
 
-        Ok(SystemSignature(sig_a | sig_b | sig_c | sig_d))
+        Ok(SystemSignature::new(sig_a | &sig_b | &sig_c | &sig_d))
     }
 }
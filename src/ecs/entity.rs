@@ -1,85 +1,99 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use std::cmp::min;
 use std::collections::{HashMap, VecDeque};
 
-use crate::ecs::Signature;
+use crate::ecs::error::EcsError;
+use crate::ecs::signature::Signature;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Entity(pub(in crate) usize);
+pub struct Entity {
+    pub(in crate) index: usize,
+    pub(in crate) generation: u32,
+}
 
 pub(in crate::ecs) struct EntityManager {
     entity_counter: usize,
     max_capacity: usize,
-    usable_entities: VecDeque<Entity>, // TODO: only use these after a certain large size it reached? to prevent collisions with any refs to entities which may have changed
+    usable_indexes: VecDeque<usize>,
     signatures: Vec<Signature>,
+    generations: Vec<u32>,
     entity_destroyed: Vec<bool>,
 }
 
-const DEFAULT_ENTITY_SIGNATURE: Signature = 0;
-
 impl EntityManager {
     pub(in crate::ecs) fn new(initial_capacity: usize, max_capacity: usize) -> Self {
         Self {
             entity_counter: 0,
             max_capacity,
-            usable_entities: VecDeque::new(),
-            signatures: vec![DEFAULT_ENTITY_SIGNATURE; initial_capacity],
+            usable_indexes: VecDeque::new(),
+            signatures: vec![Signature::new(); initial_capacity],
+            generations: vec![0; initial_capacity],
             entity_destroyed: vec![true; initial_capacity],
         }
     }
 
-    pub(in crate::ecs) fn create_entity(&mut self) -> Result<Entity> {
-        let new_entity = self.usable_entities.pop_front().unwrap_or_else(|| {
-            let new_entity = Entity(self.entity_counter);
-            self.entity_counter += 1;
-            new_entity
-        });
-
-        if new_entity.0 >= self.max_capacity {
-            return Err(anyhow!("Exceeded max entity capacity of {:?}", self.max_capacity));
+    pub(in crate::ecs) fn create_entity(&mut self) -> Result<Entity, EcsError> {
+        let index = match self.usable_indexes.pop_front() {
+            Some(index) => index,
+            None if self.entity_counter < self.max_capacity => {
+                self.entity_counter += 1;
+                self.entity_counter - 1
+            },
+            None => return Err(EcsError::CapacityExceeded(self.max_capacity)),
+        };
+
+        while self.signatures.len() <= index {
+            self.signatures.resize(min(self.signatures.len() * 2, self.max_capacity), Signature::new());
         }
-
-        while self.signatures.len() <= new_entity.0 {
-            self.signatures.resize(min(self.signatures.len() * 2, self.max_capacity), DEFAULT_ENTITY_SIGNATURE);
+        while self.generations.len() <= index {
+            self.generations.resize(min(self.generations.len() * 2, self.max_capacity), 0);
         }
-        while self.entity_destroyed.len() <= new_entity.0 {
+        while self.entity_destroyed.len() <= index {
             self.entity_destroyed.resize(min(self.entity_destroyed.len() * 2, self.max_capacity), true);
         }
 
-        self.entity_destroyed[new_entity.0] = false;
+        self.entity_destroyed[index] = false;
 
-        Ok(new_entity)
+        Ok(Entity { index, generation: self.generations[index] })
     }
 
-    pub(in crate::ecs) fn destroy_entity(&mut self, entity: &Entity) -> Result<()> {
-        if entity.0 >= self.entity_counter || self.entity_destroyed[entity.0] {
-            return Err(anyhow!("Entity {:?} does not exist", entity));
+    pub(in crate::ecs) fn destroy_entity(&mut self, entity: &Entity) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::EntityNotFound(*entity));
         }
 
-        self.signatures[entity.0] = DEFAULT_ENTITY_SIGNATURE;
-        self.entity_destroyed[entity.0] = true;
+        self.signatures[entity.index] = Signature::new();
+        self.entity_destroyed[entity.index] = true;
 
-        self.usable_entities.push_back(entity.clone());
+        // Bumping the generation invalidates any handles to the destroyed entity that are still floating around
+        self.generations[entity.index] = self.generations[entity.index].wrapping_add(1);
+
+        self.usable_indexes.push_back(entity.index);
 
         Ok(())
     }
 
-    pub(in crate::ecs) fn set_signature(&mut self, entity: &Entity, signature: Signature) -> Result<()> {
-        if entity.0 >= self.entity_counter || self.entity_destroyed[entity.0] {
-            return Err(anyhow!("Entity {:?} does not exist", entity));
+    pub(in crate::ecs) fn set_signature(&mut self, entity: &Entity, signature: Signature) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::EntityNotFound(*entity));
         }
 
-        self.signatures[entity.0] = signature;
+        self.signatures[entity.index] = signature;
 
         Ok(())
     }
 
-    pub(in crate::ecs) fn get_signature(&self, entity: &Entity) -> Result<Signature> {
-        if entity.0 >= self.entity_counter || self.entity_destroyed[entity.0] {
-            return Err(anyhow!("Entity {:?} does not exist", entity));
+    pub(in crate::ecs) fn get_signature(&self, entity: &Entity) -> Result<Signature, EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::EntityNotFound(*entity));
         }
 
-        Ok(self.signatures[entity.0])
+        Ok(self.signatures[entity.index].clone())
+    }
+
+    pub(in crate::ecs) fn is_alive(&self, entity: &Entity) -> bool {
+        // Handles can come from elsewhere (e.g. another ECS), so their index isn't necessarily one this manager has ever used
+        self.entity_destroyed.get(entity.index).is_some_and(|is_destroyed| !is_destroyed) && self.generations[entity.index] == entity.generation
     }
 
     pub(in crate::ecs) fn get_all_entities_and_signatures(&self) -> HashMap<Entity, Signature> {
@@ -87,7 +101,7 @@ impl EntityManager {
 
         for i in 0..self.entity_destroyed.len() {
             if !self.entity_destroyed[i] {
-                let entity = Entity(i);
+                let entity = Entity { index: i, generation: self.generations[i] };
                 let signature = self.get_signature(&entity).unwrap_or_else(|_| panic!("Internal error: no signature exists for non-destroyed entity {:?}", entity));
 
                 result.insert(entity, signature);
@@ -0,0 +1,57 @@
+use std::any::TypeId;
+use std::fmt::{Display, Formatter};
+
+use crate::ecs::entity::Entity;
+use crate::ecs::system::SystemId;
+use crate::ecs::ProvisionalEntity;
+
+// Errors from applying commands, which are handled according to the ECS's ErrorPolicy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcsError {
+    UnknownComponent(TypeId),
+    ComponentAlreadyRegistered(&'static str),
+    EntityNotFound(Entity),
+    ProvisionalEntityNotFound(ProvisionalEntity),
+    DuplicateComponent { entity: Entity, component: &'static str },
+    ComponentNotFound { entity: Entity, component: &'static str },
+    CapacityExceeded(usize),
+    HierarchyCycle { parent: Entity, child: Entity },
+    UnknownEvent(&'static str),
+    SystemAlreadyRegistered(SystemId),
+    SystemNotRegistered(SystemId),
+    InvalidSchedule(String),
+}
+
+impl Display for EcsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcsError::UnknownComponent(type_id) => write!(f, "Component type {:?} has not been registered", type_id),
+            EcsError::ComponentAlreadyRegistered(component) => write!(f, "Component type {} is already registered", component),
+            EcsError::EntityNotFound(entity) => write!(f, "Entity {:?} does not exist", entity),
+            EcsError::ProvisionalEntityNotFound(provisional_entity) => write!(f, "Provisional entity {:?} does not exist", provisional_entity),
+            EcsError::DuplicateComponent { entity, component } => write!(f, "Component {} already exists for entity {:?}", component, entity),
+            EcsError::ComponentNotFound { entity, component } => write!(f, "No component {} exists for entity {:?}", component, entity),
+            EcsError::CapacityExceeded(max_capacity) => write!(f, "Exceeded max entity capacity of {}", max_capacity),
+            EcsError::HierarchyCycle { parent, child } => write!(f, "Making {:?} the parent of {:?} would form a cycle", parent, child),
+            EcsError::UnknownEvent(event) => write!(f, "Event type {} has not been registered", event),
+            EcsError::SystemAlreadyRegistered(id) => write!(f, "System {:?} is already registered", id),
+            EcsError::SystemNotRegistered(id) => write!(f, "System {:?} is not registered", id),
+            EcsError::InvalidSchedule(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EcsError {}
+
+// How failed commands are handled. A failed command never stops the commands after it from being applied, and the policy only
+//  takes effect once the flush it failed in has finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    // Panics with the first error, aborting the frame
+    #[default]
+    Panic,
+    // Errors are logged as warnings and dropped, so that e.g. destroying an already destroyed entity is harmless
+    Log,
+    // Errors are kept until they're taken with ECS::take_errors
+    Collect,
+}
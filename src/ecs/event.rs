@@ -0,0 +1,67 @@
+use std::marker::PhantomData;
+
+// Events are double-buffered, so that they're kept until the end of the frame after the one they were sent in. That way every
+//  system sees each event regardless of whether it runs before or after the system that sent it.
+pub struct Events<T: Send + Sync + 'static> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    event_count: usize,
+}
+
+impl<T: Send + Sync + 'static> Events<T> {
+    pub(in crate::ecs) fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+
+    pub(in crate::ecs) fn send(&mut self, event: T) {
+        self.current.push(event);
+        self.event_count += 1;
+    }
+
+    pub(in crate::ecs) fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    // Every live event the reader hasn't seen yet. All of them count as read once this is called, even if the iterator isn't exhausted.
+    pub fn read(&self, reader: &mut EventReader<T>) -> impl Iterator<Item = &T> {
+        let oldest_event = self.event_count - self.previous.len() - self.current.len();
+        let skip = reader.next_event.saturating_sub(oldest_event);
+
+        reader.next_event = self.event_count;
+
+        self.iter().skip(skip)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+}
+
+// Owned by the reading system (e.g. captured by its closure), so that each reader has its own cursor
+pub struct EventReader<T: Send + Sync + 'static> {
+    next_event: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Send + Sync + 'static> EventReader<T> {
+    pub fn new() -> Self {
+        Self {
+            next_event: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::ecs::component::Component;
+use crate::ecs::entity::Entity;
+use crate::ecs::error::EcsError;
+use crate::ecs::snapshot::{SerializableComponent, SnapshotReader, SnapshotWriter};
+use crate::ecs::{ComponentActions, ProvisionalEntity};
+
+// Attaching or detaching a Parent keeps the parent's Children in sync, and destroying an entity destroys all of its descendants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent {
+    entity: Option<Entity>,
+    provisional_entity: Option<ProvisionalEntity>,
+}
+
+impl Parent {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity: Some(entity),
+            provisional_entity: None,
+        }
+    }
+
+    pub fn new_provisional(provisional_entity: ProvisionalEntity) -> Self {
+        Self {
+            entity: None,
+            provisional_entity: Some(provisional_entity),
+        }
+    }
+
+    pub fn get_entity(&self) -> Entity {
+        self.entity.unwrap_or_else(|| panic!("Internal error: provisional parent {:?} was never resolved", self.provisional_entity))
+    }
+}
+
+impl Component for Parent {}
+
+impl SerializableComponent for Parent {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write_entity(&self.get_entity());
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Self::new(reader.read_entity()?))
+    }
+}
+
+impl ComponentActions for Parent {
+    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
+        if let Some(p) = self.provisional_entity.take() {
+            self.entity = Some(
+                *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// Only ever attached and updated by the ECS itself, in response to Parent changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Children {
+    pub(in crate::ecs) entities: Vec<Entity>,
+}
+
+impl Children {
+    pub fn get_entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+impl Component for Children {}
+impl ComponentActions for Children {}
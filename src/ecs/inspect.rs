@@ -0,0 +1,24 @@
+use crate::ecs::entity::Entity;
+use crate::ecs::system::{SystemId, SystemStage};
+
+// Components are listed by type name, e.g. "game::core::Transform"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityInfo {
+    pub entity: Entity,
+    pub components: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentInfo {
+    pub name: &'static str,
+    pub entity_count: usize,
+}
+
+// Entity counts are per signature, in the order the system was registered with them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemInfo {
+    pub id: SystemId,
+    pub name: String,
+    pub stage: SystemStage,
+    pub entity_counts: Vec<usize>,
+}
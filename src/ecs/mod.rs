@@ -1,22 +1,57 @@
-use anyhow::{anyhow, Error, Result};
-use std::any::{Any, TypeId};
-use std::cell::RefCell;
+use anyhow::{anyhow, Result};
+use log::warn;
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::default::Default;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Instant;
 
 use component::{Component, ComponentManager, SystemSignature};
 use entity::{Entity, EntityManager};
-use system::{System, SystemManager};
-
+use error::{EcsError, ErrorPolicy};
+use event::Events;
+use hierarchy::{Children, Parent};
+use inspect::{ComponentInfo, EntityInfo, SystemInfo};
+use pool::WorkerPool;
+use prefab::Prefab;
+use profile::{FrameRecorder, FrameStats, SystemTiming};
+use resource::Resources;
+use signature::Signature;
+use snapshot::{SerializableComponent, SnapshotRegistry};
+use system::{build_schedule, check_constraint_names, FixedTimestep, System, SystemId, SystemManager, SystemStage};
+
+pub mod app;
 pub mod entity;
 pub mod component;
+pub mod error;
+pub mod event;
+pub mod hierarchy;
+pub mod inspect;
+mod pool;
+pub mod prefab;
+pub mod profile;
+pub mod query;
+pub mod resource;
+mod signature;
+pub mod snapshot;
 pub mod system;
 
-pub(in crate::ecs) type Signature = u64;
-
+// Provisional entities are unique across all commands, so they can still be resolved to real entities after the flush that created them
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ProvisionalEntity(pub(in crate) usize);
 
+static PROVISIONAL_ENTITY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl ProvisionalEntity {
+    fn next() -> Self {
+        Self(PROVISIONAL_ENTITY_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 pub trait AsAnyBox {
     fn as_any_box(self: Box<Self>) -> Box<dyn Any>;
 }
@@ -25,15 +60,27 @@ impl<T: 'static> AsAnyBox for T {
     fn as_any_box(self: Box<Self>) -> Box<dyn Any> { self }
 }
 
+// The lifecycle hooks are called while commands are being flushed, and any commands they issue are applied within that same flush
 pub trait ComponentActions: AsAnyBox {
-    fn update_provisional_entities(&mut self, _provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) {}
+    fn update_provisional_entities(&mut self, _provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
+        Ok(())
+    }
+
+    fn on_add(&mut self, _entity: &Entity, _commands: &mut ECSCommands) {}
+
+    fn on_remove(&mut self, _entity: &Entity, _commands: &mut ECSCommands) {}
+
+    // Called on the old component when it's replaced by a new one
+    fn on_replace(&mut self, _entity: &Entity, _commands: &mut ECSCommands) {}
 }
 
 enum EntityComponentCommandType {
     CreateEntity,
     DestroyEntity,
+    DestroyEntitiesWith,
     AttachComponent,
     AttachProvisionalComponent,
+    ReplaceComponent,
     DetachComponent,
 }
 
@@ -43,17 +90,23 @@ enum SystemCommandType {
     Shutdown,
 }
 
+type SystemRegistration = (SystemId, Box<dyn System>, Vec<SystemSignature>, SystemStage);
+
+type EventSender = Box<dyn FnOnce(&Resources) -> Result<(), EcsError> + Send>;
+
 pub struct ECSCommands {
     entity_component_command_order: VecDeque<EntityComponentCommandType>,
     system_command_order: VecDeque<SystemCommandType>,
-    provisional_entity_counter: usize,
     to_create: VecDeque<ProvisionalEntity>,
     to_destroy: VecDeque<Entity>,
-    to_attach: VecDeque<(Entity, TypeId, Box<dyn ComponentActions>)>,
-    to_attach_provisional: VecDeque<(ProvisionalEntity, TypeId, Box<dyn ComponentActions>)>,
+    to_destroy_with: VecDeque<TypeId>,
+    to_attach: VecDeque<(Entity, TypeId, Box<dyn ComponentActions + Send>)>,
+    to_attach_provisional: VecDeque<(ProvisionalEntity, TypeId, Box<dyn ComponentActions + Send>)>,
+    to_replace: VecDeque<(Entity, TypeId, Box<dyn ComponentActions + Send>)>,
     to_detach: VecDeque<(Entity, TypeId)>,
-    to_register: VecDeque<(System, HashSet<SystemSignature>, i16)>,
-    to_unregister: VecDeque<System>,
+    to_send: VecDeque<EventSender>,
+    to_register: VecDeque<SystemRegistration>,
+    to_unregister: VecDeque<SystemId>,
     to_shutdown: bool,
 }
 
@@ -64,22 +117,37 @@ impl ECSCommands {
         Self {
             entity_component_command_order: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             system_command_order: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
-            provisional_entity_counter: 0,
             to_create: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_destroy: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
+            to_destroy_with: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_attach: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_attach_provisional: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
+            to_replace: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_detach: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
+            to_send: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_register: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_unregister: VecDeque::with_capacity(INITIAL_COMMAND_CAPACITY),
             to_shutdown: false,
         }
     }
 
-    pub fn create_entity(&mut self) -> ProvisionalEntity {
-        let entity = ProvisionalEntity(self.provisional_entity_counter);
+    // Commands for a system running in a parallel batch, which are flushed and merged back once the batch completes
+    fn for_parallel_system(&self) -> Self {
+        let mut commands = Self::new();
+        commands.to_shutdown = self.to_shutdown;
 
-        self.provisional_entity_counter += 1;
+        commands
+    }
+
+    fn append_system_commands(&mut self, other: &mut ECSCommands) {
+        self.system_command_order.append(&mut other.system_command_order);
+        self.to_register.append(&mut other.to_register);
+        self.to_unregister.append(&mut other.to_unregister);
+        self.to_shutdown |= other.to_shutdown;
+    }
+
+    pub fn create_entity(&mut self) -> ProvisionalEntity {
+        let entity = ProvisionalEntity::next();
 
         self.to_create.push_back(entity);
         self.entity_component_command_order.push_back(EntityComponentCommandType::CreateEntity);
@@ -92,28 +160,73 @@ impl ECSCommands {
         self.entity_component_command_order.push_back(EntityComponentCommandType::DestroyEntity);
     }
 
+    // Destroys every entity with a T component (and their descendants) in one pass, which is much cheaper than destroying them one at a time
+    pub fn destroy_entities_with<T: Component>(&mut self) {
+        self.to_destroy_with.push_back(TypeId::of::<T>());
+        self.entity_component_command_order.push_back(EntityComponentCommandType::DestroyEntitiesWith);
+    }
+
     pub fn attach_component<T: Component>(&mut self, entity: &Entity, component: T) {
-        self.to_attach.push_back((entity.clone(), TypeId::of::<T>(), self.component_to_box(component)));
-        self.entity_component_command_order.push_back(EntityComponentCommandType::AttachComponent);
+        self.attach_boxed_component(entity, TypeId::of::<T>(), Box::new(component));
     }
 
     pub fn attach_provisional_component<T: Component>(&mut self, provisional_entity: &ProvisionalEntity, component: T) {
-        self.to_attach_provisional.push_back((provisional_entity.clone(), TypeId::of::<T>(), self.component_to_box(component)));
+        self.attach_boxed_provisional_component(provisional_entity, TypeId::of::<T>(), Box::new(component));
+    }
+
+    // Creates an entity with a fresh copy of each of the prefab's components
+    pub fn spawn_prefab(&mut self, prefab: &Prefab) -> ProvisionalEntity {
+        let entity = self.create_entity();
+
+        prefab.instantiate().for_each(|(type_id, component)| self.attach_boxed_provisional_component(&entity, type_id, component));
+
+        entity
+    }
+
+    pub(in crate::ecs) fn attach_boxed_component(&mut self, entity: &Entity, type_id: TypeId, component: Box<dyn ComponentActions + Send>) {
+        self.to_attach.push_back((*entity, type_id, component));
+        self.entity_component_command_order.push_back(EntityComponentCommandType::AttachComponent);
+    }
+
+    fn attach_boxed_provisional_component(&mut self, provisional_entity: &ProvisionalEntity, type_id: TypeId, component: Box<dyn ComponentActions + Send>) {
+        self.to_attach_provisional.push_back((*provisional_entity, type_id, component));
         self.entity_component_command_order.push_back(EntityComponentCommandType::AttachProvisionalComponent);
     }
 
+    // Like attach_component, except that an existing T component is replaced rather than being an error
+    pub fn replace_component<T: Component>(&mut self, entity: &Entity, component: T) {
+        self.to_replace.push_back((*entity, TypeId::of::<T>(), Box::new(component)));
+        self.entity_component_command_order.push_back(EntityComponentCommandType::ReplaceComponent);
+    }
+
     pub fn detach_component<T: Component>(&mut self, entity: &Entity) {
         self.to_detach.push_back((entity.clone(), TypeId::of::<T>()));
         self.entity_component_command_order.push_back(EntityComponentCommandType::DetachComponent);
     }
 
-    pub fn register_system(&mut self, system: System, signatures: HashSet<SystemSignature>, precedence: i16) {
-        self.to_register.push_back((system, signatures, precedence));
+    // Sent events become readable once the sending system's commands are flushed
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.to_send.push_back(Box::new(move |resources: &Resources| {
+            let mut events = resources.get_mut::<Events<T>>()
+                .ok_or(EcsError::UnknownEvent(type_name::<T>()))?;
+
+            events.send(event);
+
+            Ok(())
+        }));
+    }
+
+    pub fn register_system<S: System>(&mut self, system: S, signatures: Vec<SystemSignature>, stage: SystemStage) -> SystemId {
+        let id = SystemId::next();
+
+        self.to_register.push_back((id, Box::new(system), signatures, stage));
         self.system_command_order.push_back(SystemCommandType::RegisterSystem);
+
+        id
     }
 
-    pub fn unregister_system(&mut self, system: System) {
-        self.to_unregister.push_back(system);
+    pub fn unregister_system(&mut self, id: SystemId) {
+        self.to_unregister.push_back(id);
         self.system_command_order.push_back(SystemCommandType::UnregisterSystem);
     }
 
@@ -125,19 +238,21 @@ impl ECSCommands {
     pub fn is_shutting_down(&self) -> bool {
         self.to_shutdown
     }
-
-    fn component_to_box<T: Component>(&self, component: T) -> Box<T> {
-        // TODO: allocate from a per-component custom allocator for better cache locality
-        Box::new(component)
-    }
 }
 
 pub struct ECS {
     entity_manager: EntityManager,
     component_manager: ComponentManager,
+    resources: Resources,
+    event_updaters: Vec<fn(&Resources)>,
+    snapshot_registry: SnapshotRegistry,
     system_managers: Vec<RefCell<SystemManager>>,
-    system_hashes: HashSet<System>,
+    schedule: Vec<Vec<usize>>,
     commands: ECSCommands,
+    error_policy: ErrorPolicy,
+    errors: Vec<EcsError>,
+    frame_recorder: Option<FrameRecorder>,
+    worker_pool: WorkerPool,
     initial_entity_capacity: usize,
     is_shutdown: bool,
 }
@@ -145,13 +260,29 @@ pub struct ECS {
 const INTIIAL_SYSTEM_CAPACITY: usize = 256;
 
 impl ECS {
-    fn new(component_manager: ComponentManager, initial_entity_capacity: usize, max_entity_capacity: usize) -> Self {
+    fn new(
+        component_manager: ComponentManager,
+        resources: Resources,
+        event_updaters: Vec<fn(&Resources)>,
+        snapshot_registry: SnapshotRegistry,
+        error_policy: ErrorPolicy,
+        initial_entity_capacity: usize,
+        max_entity_capacity: usize,
+    ) -> Self {
         Self {
             entity_manager: EntityManager::new(initial_entity_capacity, max_entity_capacity),
             component_manager,
+            resources,
+            event_updaters,
+            snapshot_registry,
             system_managers: Vec::with_capacity(INTIIAL_SYSTEM_CAPACITY),
-            system_hashes: HashSet::with_capacity(INTIIAL_SYSTEM_CAPACITY),
+            schedule: Vec::new(),
             commands: ECSCommands::new(),
+            error_policy,
+            errors: Vec::new(),
+            frame_recorder: None,
+            // The thread which invokes the systems runs them as well
+            worker_pool: WorkerPool::new(thread::available_parallelism().map_or(1, |n| n.get()) - 1),
             initial_entity_capacity,
             is_shutdown: false,
         }
@@ -165,6 +296,10 @@ impl ECS {
         self.commands.destroy_entity(entity);
     }
 
+    pub fn destroy_entities_with<T: Component>(&mut self) {
+        self.commands.destroy_entities_with::<T>();
+    }
+
     pub fn attach_component<T: Component>(&mut self, entity: &Entity, component: T) {
         self.commands.attach_component(entity, component);
     }
@@ -173,22 +308,145 @@ impl ECS {
         self.commands.attach_provisional_component(provisional_entity, component);
     }
 
+    pub fn replace_component<T: Component>(&mut self, entity: &Entity, component: T) {
+        self.commands.replace_component(entity, component);
+    }
+
+    pub fn spawn_prefab(&mut self, prefab: &Prefab) -> ProvisionalEntity {
+        self.commands.spawn_prefab(prefab)
+    }
+
+    // Prefabs can only be loaded with serializable components, see ECSBuilder::with_serializable_component
+    pub fn load_prefabs(&self, data: &str) -> Result<Vec<Prefab>> {
+        prefab::parse_prefabs(data, &self.snapshot_registry, &self.component_manager, &self.resources)
+    }
+
     pub fn detach_component<T: Component>(&mut self, entity: &Entity) {
         self.commands.detach_component::<T>(entity);
     }
 
-    pub fn register_system(&mut self, system: System, signatures: HashSet<SystemSignature>, precedence: i16) {
-        self.commands.register_system(system, signatures, precedence);
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.commands.send_event(event);
+    }
+
+    // Ordering constraints are checked against the systems registered so far, including those which haven't been flushed yet. A system
+    //  whose constraints name any other system is handled like a failed command, and is never registered.
+    pub fn register_system<S: System>(&mut self, system: S, signatures: Vec<SystemSignature>, stage: SystemStage) -> SystemId {
+        let result = {
+            let managers: Vec<Ref<SystemManager>> = self.system_managers.iter().map(|m| m.borrow()).collect();
+            let registered_names: Vec<&str> = managers.iter().map(|m| m.get_name())
+                .chain(self.commands.to_register.iter().map(|(_, system, _, _)| system.name()))
+                .collect();
+
+            check_constraint_names(&system, &registered_names)
+        };
+
+        match result {
+            Ok(()) => self.commands.register_system(system, signatures, stage),
+            Err(e) => {
+                self.errors.push(e);
+                self.apply_error_policy();
+
+                SystemId::next()
+            },
+        }
     }
 
-    pub fn unregister_system(&mut self, system: System) {
-        self.commands.unregister_system(system);
+    pub fn unregister_system(&mut self, id: SystemId) {
+        self.commands.unregister_system(id);
     }
 
     pub fn shutdown(&mut self) {
         self.commands.shutdown();
     }
 
+    pub fn is_alive(&self, entity: &Entity) -> bool {
+        self.entity_manager.is_alive(entity)
+    }
+
+    pub fn resolve(&self, provisional_entity: &ProvisionalEntity) -> Option<Entity> {
+        self.component_manager.resolve(provisional_entity)
+    }
+
+    // Saves every entity with at least one serializable component, along with all of its serializable components
+    pub fn save_snapshot(&self) -> String {
+        self.snapshot_registry.save(&self.component_manager, &self.resources)
+    }
+
+    // Adds the snapshot's entities to the world rather than replacing it, and returns them in snapshot order. Pending commands are
+    //  flushed first, and the loaded entities and components are all in place by the time this returns.
+    pub fn load_snapshot(&mut self, snapshot: &str) -> Result<Vec<Entity>> {
+        let parsed = self.snapshot_registry.parse(snapshot)?;
+
+        let provisional_entities: Vec<ProvisionalEntity> = parsed.iter().map(|_| self.commands.create_entity()).collect();
+        self.flush_entity_component_commands();
+
+        let entities = provisional_entities.iter()
+            .map(|p| self.resolve(p).ok_or_else(|| anyhow!("Failed to create an entity for the snapshot")))
+            .collect::<Result<Vec<Entity>>>()?;
+
+        let result = self.snapshot_registry.load(&parsed, &entities, &self.component_manager, &self.resources, &mut self.commands);
+
+        // A snapshot that fails part way through isn't loaded at all
+        if result.is_err() {
+            entities.iter().for_each(|entity| self.commands.destroy_entity(entity));
+        }
+
+        self.flush_entity_component_commands();
+
+        result.map(|_| entities)
+    }
+
+    pub fn get_entities(&self) -> Vec<Entity> {
+        self.component_manager.get_entities()
+    }
+
+    pub fn inspect_entity(&self, entity: &Entity) -> Option<EntityInfo> {
+        self.component_manager.inspect_entity(entity)
+    }
+
+    pub fn inspect_entities(&self) -> Vec<EntityInfo> {
+        self.component_manager.inspect_entities()
+    }
+
+    pub fn inspect_components(&self) -> Vec<ComponentInfo> {
+        self.component_manager.inspect_components()
+    }
+
+    // Systems are listed in the order they run in, and only include systems whose registration has already been flushed
+    pub fn inspect_systems(&self) -> Vec<SystemInfo> {
+        self.schedule.iter().flatten().map(|i| self.system_managers[*i].borrow().inspect()).collect()
+    }
+
+    pub fn dump_entity(&self, entity: &Entity) -> Option<String> {
+        self.component_manager.dump_entity(entity)
+    }
+
+    pub fn dump_world(&self) -> String {
+        self.component_manager.dump_world()
+    }
+
+    // Only ever non-empty with ErrorPolicy::Collect, in which case errors accumulate until they're taken
+    pub fn take_errors(&mut self) -> Vec<EcsError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn insert_resource<T: Send + Sync + 'static>(&mut self, resource: T) {
+        self.resources.insert(resource);
+    }
+
+    pub fn remove_resource<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+
+    pub fn get_resource<T: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.resources.get::<T>()
+    }
+
+    pub fn get_resource_mut<T: Send + Sync + 'static>(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.resources.get_mut::<T>()
+    }
+
     pub fn get_system_signature_0(&self) -> Result<SystemSignature> {
         self.component_manager.get_system_signature_0()
     }
@@ -210,98 +468,178 @@ impl ECS {
     }
 
     pub(in crate) fn invoke_systems(&mut self) -> bool {
-        // TODO: There's definitely a better way to handle errors here than always panicking... we could maybe add user configurable error handling and then crash the
-        //  application, print a warning, etc. depending on the error. Also, considering using more specific Error types here rather than anyhow. Also, do we want
-        //  to continue flushing remaining commands even when one fails?
-
         if self.is_shutdown {
             return false;
         }
 
-        flush_all_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &mut self.system_managers, &mut self.system_hashes, self.initial_entity_capacity, &mut self.is_shutdown)
-            .unwrap_or_else(|e| panic!("{}", e));
+        self.component_manager.advance_frame();
+        self.event_updaters.iter().for_each(|update| update(&self.resources));
+
+        self.frame_recorder = self.resources.get::<FrameStats>().map(|stats| FrameRecorder::start(&stats));
+
+        let flush_start = Instant::now();
+        flush_all_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &mut self.system_managers, &mut self.schedule, self.initial_entity_capacity, &mut self.is_shutdown, &mut self.errors);
+        flush_event_commands(&mut self.commands, &self.resources, &mut self.errors);
+        self.record_flush(flush_start);
+        self.apply_error_policy();
 
         if self.is_shutdown {
             return false;
         }
 
-        self.system_managers.iter().for_each(|manager| {
-            manager.borrow_mut().invoke_system(&mut self.component_manager, &mut self.commands);
-            flush_entity_component_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &self.system_managers)
-                .unwrap_or_else(|e| panic!("{}", e));
-        });
+        let mut stage_start = 0;
 
-        flush_all_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &mut self.system_managers, &mut self.system_hashes, self.initial_entity_capacity, &mut self.is_shutdown)
-            .unwrap_or_else(|e| panic!("{}", e));
+        while stage_start < self.schedule.len() {
+            let stage = self.get_batch_stage(stage_start);
+            let stage_end = (stage_start..self.schedule.len()).find(|&i| self.get_batch_stage(i) != stage).unwrap_or(self.schedule.len());
 
-        true
-    }
-}
+            // With a FixedTimestep resource, the Physics stage runs once per whole step of accumulated time instead of once per frame
+            let runs = match (stage, self.resources.get_mut::<FixedTimestep>()) {
+                (SystemStage::Physics, Some(mut fixed_timestep)) => fixed_timestep.take_steps(),
+                _ => 1,
+            };
 
-fn flush_entity_component_commands(
-    commands: &mut ECSCommands,
-    entity_manager: &mut EntityManager,
-    component_manager: &mut ComponentManager,
-    system_managers: &Vec<RefCell<SystemManager>>,
-) -> Result<()> {
-    let mut provisional_entity_map: HashMap<ProvisionalEntity, Entity> = HashMap::with_capacity(commands.to_create.len());
+            for _ in 0..runs {
+                (stage_start..stage_end).for_each(|batch_index| self.invoke_batch(batch_index));
+            }
 
-    while let Some(command_type) = commands.entity_component_command_order.pop_front() {
-        match command_type {
-            EntityComponentCommandType::CreateEntity => {
-                let provisional_entity = commands.to_create.pop_front().unwrap_or_else(|| panic!("Internal error: expected a provisional entity to create"));
+            stage_start = stage_end;
+        }
 
-                let entity = entity_manager.create_entity()?;
+        let flush_start = Instant::now();
+        flush_all_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &mut self.system_managers, &mut self.schedule, self.initial_entity_capacity, &mut self.is_shutdown, &mut self.errors);
+        flush_event_commands(&mut self.commands, &self.resources, &mut self.errors);
+        self.record_flush(flush_start);
+        self.apply_error_policy();
 
-                system_managers.iter().map(|manager| {
-                    let updated_signature = entity_manager.get_signature(&entity)?;
-                    manager.borrow_mut().handle_entity_updated(&entity, updated_signature);
+        if let (Some(recorder), Some(mut stats)) = (self.frame_recorder.take(), self.resources.get_mut::<FrameStats>()) {
+            recorder.finish(&mut stats);
+        }
 
-                    Ok::<_, Error>(())
-                }).find(|r| r.is_err()).unwrap_or(Ok(()))?;
+        true
+    }
 
-                provisional_entity_map.insert(provisional_entity, entity);
-            },
-            EntityComponentCommandType::DestroyEntity => {
-                let entity = commands.to_destroy.pop_front().unwrap_or_else(|| panic!("Internal error: expected an entity to destroy"));
+    fn get_batch_stage(&self, batch_index: usize) -> SystemStage {
+        self.system_managers[self.schedule[batch_index][0]].borrow().stage
+    }
 
-                component_manager.handle_entity_removed(&entity);
-                system_managers.iter().for_each(|manager| manager.borrow_mut().handle_entity_removed(&entity));
+    // Systems run a tick after anything earlier in the frame, and their commands are applied a tick after that, so that every system
+    //  sees what was added or changed since it last ran, including by systems in the same batch
+    fn invoke_batch(&mut self, batch_index: usize) {
+        self.component_manager.advance_tick();
 
-                entity_manager.destroy_entity(&entity)?;
-            },
-            EntityComponentCommandType::AttachComponent => {
-                let (entity, type_id, component) = commands.to_attach.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to attach"));
+        let batch = &self.schedule[batch_index];
 
-                component_manager.attach_component(&entity, type_id, component)?;
+        if let [index] = batch.as_slice() {
+            let start = Instant::now();
+            self.system_managers[*index].borrow_mut().invoke_system(&self.component_manager, &self.resources, &mut self.commands);
 
-                let component_signature = component_manager.get_signature(type_id)?;
-                apply_entity_signature_update(entity, component_signature, entity_manager, system_managers)?;
-            },
-            EntityComponentCommandType::AttachProvisionalComponent => {
-                let (provisional_entity, type_id, mut component) = commands.to_attach_provisional.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to attach"));
+            if let Some(recorder) = &mut self.frame_recorder {
+                recorder.record_system(self.system_managers[*index].borrow().inspect(), SystemTiming { start, end: Instant::now(), thread: 0 });
+            }
 
-                component.as_mut().update_provisional_entities(&provisional_entity_map);
+            self.component_manager.advance_tick();
+            let flush_start = Instant::now();
+            flush_entity_component_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &self.system_managers, &mut self.errors);
+            flush_event_commands(&mut self.commands, &self.resources, &mut self.errors);
+            self.record_flush(flush_start);
+        } else {
+            let mut batch_results = invoke_parallel_batch(batch, &self.system_managers, &self.component_manager, &self.resources, &self.commands, &mut self.worker_pool);
 
-                let entity = *provisional_entity_map.get(&provisional_entity).unwrap_or_else(|| panic!("Internal error: provisional entity {:?} was not created before attaching a component to it", provisional_entity));
+            if let Some(recorder) = &mut self.frame_recorder {
+                for (index, (_, timing)) in batch.iter().zip(batch_results.iter()) {
+                    recorder.record_system(self.system_managers[*index].borrow().inspect(), *timing);
+                }
+            }
+
+            // Flushed in registration order so results don't depend on which thread finished first
+            self.component_manager.advance_tick();
+            let flush_start = Instant::now();
+            for (commands, _) in batch_results.iter_mut() {
+                flush_entity_component_commands(commands, &mut self.entity_manager, &mut self.component_manager, &self.system_managers, &mut self.errors);
+                flush_event_commands(commands, &self.resources, &mut self.errors);
+                self.commands.append_system_commands(commands);
+            }
+            self.record_flush(flush_start);
+        }
 
-                component_manager.attach_component(&entity, type_id, component)?;
+        self.apply_error_policy();
+    }
 
-                let component_signature = component_manager.get_signature(type_id)?;
-                apply_entity_signature_update(entity, component_signature, entity_manager, system_managers)?;
-            },
-            EntityComponentCommandType::DetachComponent => {
-                let (entity, type_id) = commands.to_detach.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to detach"));
+    fn flush_entity_component_commands(&mut self) {
+        flush_entity_component_commands(&mut self.commands, &mut self.entity_manager, &mut self.component_manager, &self.system_managers, &mut self.errors);
+        flush_event_commands(&mut self.commands, &self.resources, &mut self.errors);
+        self.apply_error_policy();
+    }
 
-                component_manager.detach_component(&entity, type_id)?;
+    fn record_flush(&mut self, start: Instant) {
+        if let Some(recorder) = &mut self.frame_recorder {
+            recorder.record_flush(start);
+        }
+    }
 
-                let component_signature = component_manager.get_signature(type_id)?;
-                apply_entity_signature_update(entity, component_signature, entity_manager, system_managers)?;
+    fn apply_error_policy(&mut self) {
+        match self.error_policy {
+            ErrorPolicy::Panic => {
+                if let Some(e) = self.errors.first() {
+                    panic!("{}", e);
+                }
             },
+            ErrorPolicy::Log => self.errors.drain(..).for_each(|e| warn!("Skipped ECS command: {}", e)),
+            ErrorPolicy::Collect => {},
         }
     }
+}
+
+fn invoke_parallel_batch(
+    batch: &[usize],
+    system_managers: &[RefCell<SystemManager>],
+    component_manager: &ComponentManager,
+    resources: &Resources,
+    commands: &ECSCommands,
+    worker_pool: &mut WorkerPool,
+) -> Vec<(ECSCommands, SystemTiming)> {
+    let mut managers: Vec<RefMut<SystemManager>> = batch.iter().map(|&i| system_managers[i].borrow_mut()).collect();
+
+    let jobs: Vec<Mutex<(&mut SystemManager, ECSCommands, Option<SystemTiming>)>> = managers.iter_mut()
+        .map(|manager| Mutex::new((&mut **manager, commands.for_parallel_system(), None)))
+        .collect();
+
+    let next_job = AtomicUsize::new(0);
+
+    // Every thread in the pool takes systems from the batch until there are none left
+    worker_pool.run(&|thread: usize| {
+        while let Some(job) = jobs.get(next_job.fetch_add(1, Ordering::Relaxed)) {
+            let mut job = job.lock().unwrap_or_else(|_| panic!("Internal error: system job was poisoned"));
+            let (manager, commands, timing) = &mut *job;
+
+            let start = Instant::now();
+            manager.invoke_system(component_manager, resources, commands);
+            *timing = Some(SystemTiming { start, end: Instant::now(), thread });
+        }
+    });
 
-    commands.provisional_entity_counter = 0;
+    jobs.into_iter()
+        .map(|job| {
+            let (_, commands, timing) = job.into_inner().unwrap_or_else(|_| panic!("Internal error: system job was poisoned"));
+
+            (commands, timing.unwrap_or_else(|| panic!("Internal error: system job was never run")))
+        })
+        .collect()
+}
+
+fn flush_entity_component_commands(
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    component_manager: &mut ComponentManager,
+    system_managers: &Vec<RefCell<SystemManager>>,
+    errors: &mut Vec<EcsError>,
+) {
+    while let Some(command_type) = commands.entity_component_command_order.pop_front() {
+        if let Err(e) = apply_entity_component_command(command_type, commands, entity_manager, component_manager, system_managers) {
+            errors.push(e);
+        }
+    }
 
     #[cfg(debug_assertions)] {
         if !commands.to_create.is_empty() {
@@ -310,78 +648,294 @@ fn flush_entity_component_commands(
         if !commands.to_destroy.is_empty() {
             panic!("Internal error: to_destroy was not drained")
         }
+        if !commands.to_destroy_with.is_empty() {
+            panic!("Internal error: to_destroy_with was not drained")
+        }
         if !commands.to_attach.is_empty() {
             panic!("Internal error: to_attach was not drained")
         }
         if !commands.to_attach_provisional.is_empty() {
             panic!("Internal error: to_attach_provisional was not drained")
         }
+        if !commands.to_replace.is_empty() {
+            panic!("Internal error: to_replace was not drained")
+        }
         if !commands.to_detach.is_empty() {
             panic!("Internal error: to_detach was not drained")
         }
     }
+}
+
+// Each command pops its own data before anything can fail, so that a failed command never affects the ones after it
+fn apply_entity_component_command(
+    command_type: EntityComponentCommandType,
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    component_manager: &mut ComponentManager,
+    system_managers: &Vec<RefCell<SystemManager>>,
+) -> Result<(), EcsError> {
+    match command_type {
+        EntityComponentCommandType::CreateEntity => {
+            let provisional_entity = commands.to_create.pop_front().unwrap_or_else(|| panic!("Internal error: expected a provisional entity to create"));
+
+            let entity = entity_manager.create_entity()?;
+            component_manager.handle_entity_created(&entity, provisional_entity);
+
+            system_managers.iter().map(|manager| {
+                let updated_signature = entity_manager.get_signature(&entity)?;
+                manager.borrow_mut().handle_entity_updated(&entity, &updated_signature);
+
+                Ok::<_, EcsError>(())
+            }).find(|r| r.is_err()).unwrap_or(Ok(()))?;
+        },
+        EntityComponentCommandType::DestroyEntity => {
+            let entity = commands.to_destroy.pop_front().unwrap_or_else(|| panic!("Internal error: expected an entity to destroy"));
+
+            if !entity_manager.is_alive(&entity) {
+                return Err(EcsError::EntityNotFound(entity));
+            }
+
+            detach_from_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            destroy_entity_and_descendants(&entity, commands, entity_manager, component_manager, system_managers)?;
+        },
+        EntityComponentCommandType::DestroyEntitiesWith => {
+            let type_id = commands.to_destroy_with.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component type to destroy entities with"));
+
+            // Fails for unregistered component types
+            component_manager.get_signature(type_id)?;
+
+            let tagged = component_manager.get_entities_with(&type_id).to_vec();
+
+            // Tagged entities whose parent survives have to be removed from its Children first
+            tagged.iter().map(|entity| detach_from_parent(entity, commands, entity_manager, component_manager, system_managers))
+                .find(|r| r.is_err()).unwrap_or(Ok(()))?;
+
+            let mut to_destroy: HashSet<Entity> = HashSet::with_capacity(tagged.len());
+            let mut to_visit = tagged;
+
+            while let Some(entity) = to_visit.pop() {
+                if to_destroy.insert(entity) {
+                    if let Some(children) = component_manager.get_component::<Children>(&entity) {
+                        to_visit.extend_from_slice(children.get_entities());
+                    }
+                }
+            }
+
+            component_manager.handle_entities_removed(&to_destroy, commands);
+            system_managers.iter().for_each(|manager| manager.borrow_mut().handle_entities_removed(&to_destroy));
+
+            to_destroy.iter().map(|entity| entity_manager.destroy_entity(entity))
+                .find(|r| r.is_err()).unwrap_or(Ok(()))?;
+        },
+        EntityComponentCommandType::AttachComponent => {
+            let (entity, type_id, mut component) = commands.to_attach.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to attach"));
+
+            component.as_mut().update_provisional_entities(component_manager.get_provisional_entities())?;
+            let component = check_parent(&entity, type_id, component, entity_manager, component_manager)?;
+
+            component_manager.attach_component(&entity, type_id, component, commands)?;
+
+            let component_signature = component_manager.get_signature(type_id)?;
+            apply_entity_signature_update(entity, |sig| *sig |= &component_signature, entity_manager, system_managers)?;
+
+            if type_id == TypeId::of::<Parent>() {
+                attach_to_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            }
+        },
+        EntityComponentCommandType::AttachProvisionalComponent => {
+            let (provisional_entity, type_id, mut component) = commands.to_attach_provisional.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to attach"));
+
+            component.as_mut().update_provisional_entities(component_manager.get_provisional_entities())?;
+
+            let entity = component_manager.resolve(&provisional_entity)
+                .ok_or(EcsError::ProvisionalEntityNotFound(provisional_entity))?;
+            let component = check_parent(&entity, type_id, component, entity_manager, component_manager)?;
+
+            component_manager.attach_component(&entity, type_id, component, commands)?;
+
+            let component_signature = component_manager.get_signature(type_id)?;
+            apply_entity_signature_update(entity, |sig| *sig |= &component_signature, entity_manager, system_managers)?;
+
+            if type_id == TypeId::of::<Parent>() {
+                attach_to_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            }
+        },
+        EntityComponentCommandType::ReplaceComponent => {
+            let (entity, type_id, mut component) = commands.to_replace.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to replace"));
+
+            component.as_mut().update_provisional_entities(component_manager.get_provisional_entities())?;
+            let component = check_parent(&entity, type_id, component, entity_manager, component_manager)?;
+
+            if type_id == TypeId::of::<Parent>() {
+                detach_from_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            }
+
+            component_manager.replace_component(&entity, type_id, component, commands)?;
+
+            let component_signature = component_manager.get_signature(type_id)?;
+            apply_entity_signature_update(entity, |sig| *sig |= &component_signature, entity_manager, system_managers)?;
+
+            if type_id == TypeId::of::<Parent>() {
+                attach_to_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            }
+        },
+        EntityComponentCommandType::DetachComponent => {
+            let (entity, type_id) = commands.to_detach.pop_front().unwrap_or_else(|| panic!("Internal error: expected a component to detach"));
+
+            if type_id == TypeId::of::<Parent>() {
+                detach_from_parent(&entity, commands, entity_manager, component_manager, system_managers)?;
+            }
+
+            component_manager.detach_component(&entity, type_id, commands)?;
+
+            let component_signature = component_manager.get_signature(type_id)?;
+            apply_entity_signature_update(entity, |sig| sig.remove_all(&component_signature), entity_manager, system_managers)?;
+        },
+    }
 
     Ok(())
 }
 
+fn flush_event_commands(commands: &mut ECSCommands, resources: &Resources, errors: &mut Vec<EcsError>) {
+    while let Some(send) = commands.to_send.pop_front() {
+        if let Err(e) = send(resources) {
+            errors.push(e);
+        }
+    }
+}
+
 fn apply_entity_signature_update(
     entity: Entity,
-    component_signature: Signature,
+    update: impl FnOnce(&mut Signature),
     entity_manager: &mut EntityManager,
     system_managers: &Vec<RefCell<SystemManager>>,
-) -> Result<()> {
+) -> Result<(), EcsError> {
     let mut entity_signature = entity_manager.get_signature(&entity)?;
-    entity_signature |= component_signature;
+    update(&mut entity_signature);
 
-    entity_manager.set_signature(&entity, entity_signature)?;
-    system_managers.iter().for_each(|manager| manager.borrow_mut().handle_entity_updated(&entity, entity_signature));
+    entity_manager.set_signature(&entity, entity_signature.clone())?;
+    system_managers.iter().for_each(|manager| manager.borrow_mut().handle_entity_updated(&entity, &entity_signature));
 
     Ok(())
 }
 
-fn flush_all_commands(
+// A Parent is checked before it's attached, so that a rejected parent leaves the hierarchy exactly as it was
+fn check_parent(
+    child: &Entity,
+    type_id: TypeId,
+    component: Box<dyn ComponentActions>,
+    entity_manager: &EntityManager,
+    component_manager: &ComponentManager,
+) -> Result<Box<dyn ComponentActions>, EcsError> {
+    if type_id != TypeId::of::<Parent>() {
+        return Ok(component);
+    }
+
+    let component = component.as_any_box().downcast::<Parent>().unwrap_or_else(|_| panic!("Internal error: Failed to downcast component to Parent"));
+    let parent = component.get_entity();
+
+    if !entity_manager.is_alive(&parent) {
+        return Err(EcsError::EntityNotFound(parent));
+    }
+
+    let mut ancestor = Some(parent);
+    while let Some(a) = ancestor {
+        if a == *child {
+            return Err(EcsError::HierarchyCycle { parent, child: *child });
+        }
+
+        ancestor = component_manager.get_component::<Parent>(&a).map(|p| p.get_entity());
+    }
+
+    Ok(component)
+}
+
+fn attach_to_parent(
+    child: &Entity,
     commands: &mut ECSCommands,
     entity_manager: &mut EntityManager,
     component_manager: &mut ComponentManager,
-    system_managers: &mut Vec<RefCell<SystemManager>>,
-    system_hashes: &mut HashSet<System>,
-    initial_entity_capacity: usize,
-    is_shutdown: &mut bool,
-) -> Result<()> {
-    flush_entity_component_commands(commands, entity_manager, component_manager, system_managers)?;
+    system_managers: &Vec<RefCell<SystemManager>>,
+) -> Result<(), EcsError> {
+    let parent = component_manager.get_component::<Parent>(child).unwrap_or_else(|| panic!("Internal error: expected a parent for entity {:?}", child)).get_entity();
 
-    while let Some(command_type) = commands.system_command_order.pop_front() {
-        match command_type {
-            SystemCommandType::RegisterSystem => {
-                let (system, system_signatures, precedence) = commands.to_register.pop_front().unwrap_or_else(|| panic!("Internal error: expected a system to register"));
+    let has_children = component_manager.get_mut_component::<Children>(&parent).map(|mut c| c.entities.push(*child)).is_some();
 
-                if system_hashes.contains(&system) {
-                    return Err(anyhow!("System is already registered"));
-                }
+    if !has_children {
+        component_manager.attach_component(&parent, TypeId::of::<Children>(), Box::new(Children { entities: vec![*child] }), commands)?;
 
-                let raw_signatures = system_signatures.iter().map(|sig| sig.0).collect();
+        let children_signature = component_manager.get_signature(TypeId::of::<Children>())?;
+        apply_entity_signature_update(parent, |sig| *sig |= &children_signature, entity_manager, system_managers)?;
+    }
 
-                let mut system_manager = SystemManager::new(system, raw_signatures, precedence, initial_entity_capacity);
+    Ok(())
+}
 
-                entity_manager.get_all_entities_and_signatures().iter().for_each(|(e, s)| system_manager.handle_entity_updated(e, *s));
+fn detach_from_parent(
+    child: &Entity,
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    component_manager: &mut ComponentManager,
+    system_managers: &Vec<RefCell<SystemManager>>,
+) -> Result<(), EcsError> {
+    let parent = match component_manager.get_component::<Parent>(child) {
+        Some(parent) => parent.get_entity(),
+        None => return Ok(()),
+    };
+
+    let is_last_child = match component_manager.get_mut_component::<Children>(&parent) {
+        Some(mut children) => {
+            children.entities.retain(|e| e != child);
+            children.entities.is_empty()
+        },
+        None => false,
+    };
+
+    // An entity only has Children while it actually has any, so that queries over With<Children> stay meaningful
+    if is_last_child {
+        component_manager.detach_component(&parent, TypeId::of::<Children>(), commands)?;
+
+        let children_signature = component_manager.get_signature(TypeId::of::<Children>())?;
+        apply_entity_signature_update(parent, |sig| sig.remove_all(&children_signature), entity_manager, system_managers)?;
+    }
 
-                system_managers.push(RefCell::new(system_manager));
-                system_managers.sort_by_key(|c| c.borrow().precedence);
+    Ok(())
+}
 
-                system_hashes.insert(system);
-            },
-            SystemCommandType::UnregisterSystem => {
-                let system = commands.to_unregister.pop_front().unwrap_or_else(|| panic!("Internal error: expected a system to unregister"));
+fn destroy_entity_and_descendants(
+    entity: &Entity,
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    component_manager: &mut ComponentManager,
+    system_managers: &Vec<RefCell<SystemManager>>,
+) -> Result<(), EcsError> {
+    let children = component_manager.get_component::<Children>(entity).map(|c| c.entities.clone()).unwrap_or_default();
 
-                if !system_hashes.remove(&system) {
-                    return Err(anyhow!("System is not registered"));
-                }
+    component_manager.handle_entity_removed(entity, commands);
+    system_managers.iter().for_each(|manager| manager.borrow_mut().handle_entity_removed(entity));
 
-                system_managers.retain(|m| m.borrow().system != system);
-            },
-            SystemCommandType::Shutdown => {
-                *is_shutdown = true;
-            },
+    entity_manager.destroy_entity(entity)?;
+
+    children.iter().map(|child| destroy_entity_and_descendants(child, commands, entity_manager, component_manager, system_managers))
+        .find(|r| r.is_err()).unwrap_or(Ok(()))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn flush_all_commands(
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    component_manager: &mut ComponentManager,
+    system_managers: &mut Vec<RefCell<SystemManager>>,
+    schedule: &mut Vec<Vec<usize>>,
+    initial_entity_capacity: usize,
+    is_shutdown: &mut bool,
+    errors: &mut Vec<EcsError>,
+) {
+    flush_entity_component_commands(commands, entity_manager, component_manager, system_managers, errors);
+
+    while let Some(command_type) = commands.system_command_order.pop_front() {
+        if let Err(e) = apply_system_command(command_type, commands, entity_manager, system_managers, schedule, initial_entity_capacity, is_shutdown) {
+            errors.push(e);
         }
     }
 
@@ -393,12 +947,71 @@ fn flush_all_commands(
             panic!("Internal error: to_unregister was not drained")
         }
     }
+}
+
+fn apply_system_command(
+    command_type: SystemCommandType,
+    commands: &mut ECSCommands,
+    entity_manager: &mut EntityManager,
+    system_managers: &mut Vec<RefCell<SystemManager>>,
+    schedule: &mut Vec<Vec<usize>>,
+    initial_entity_capacity: usize,
+    is_shutdown: &mut bool,
+) -> Result<(), EcsError> {
+    match command_type {
+        SystemCommandType::RegisterSystem => {
+            let (id, system, system_signatures, stage) = commands.to_register.pop_front().unwrap_or_else(|| panic!("Internal error: expected a system to register"));
+
+            if system_managers.iter().any(|m| m.borrow().id == id) {
+                return Err(EcsError::SystemAlreadyRegistered(id));
+            }
+
+            let mut system_manager = SystemManager::new(id, system, system_signatures, stage, initial_entity_capacity);
+
+            entity_manager.get_all_entities_and_signatures().iter().for_each(|(e, s)| system_manager.handle_entity_updated(e, s));
+
+            system_managers.push(RefCell::new(system_manager));
+            system_managers.sort_by_key(|c| c.borrow().stage);
+
+            // A system whose ordering constraints can't be satisfied is rejected, leaving the previous schedule intact
+            match build_schedule(system_managers) {
+                Ok(new_schedule) => *schedule = new_schedule,
+                Err(e) => {
+                    system_managers.retain(|m| m.borrow().id != id);
+                    return Err(e);
+                },
+            }
+        },
+        SystemCommandType::UnregisterSystem => {
+            let id = commands.to_unregister.pop_front().unwrap_or_else(|| panic!("Internal error: expected a system to unregister"));
+
+            let index = system_managers.iter().position(|m| m.borrow().id == id)
+                .ok_or(EcsError::SystemNotRegistered(id))?;
+            let system_manager = system_managers.remove(index);
+
+            // Systems which other systems are ordered against stay registered until those systems are unregistered
+            match build_schedule(system_managers) {
+                Ok(new_schedule) => *schedule = new_schedule,
+                Err(e) => {
+                    system_managers.insert(index, system_manager);
+                    return Err(e);
+                },
+            }
+        },
+        SystemCommandType::Shutdown => {
+            *is_shutdown = true;
+        },
+    }
 
     Ok(())
 }
 
 pub struct ECSBuilder {
     component_manager: ComponentManager,
+    resources: Resources,
+    event_updaters: Vec<fn(&Resources)>,
+    snapshot_registry: SnapshotRegistry,
+    error_policy: ErrorPolicy,
     initial_entity_capacity: usize,
     max_entity_capacity: usize,
 }
@@ -410,6 +1023,10 @@ impl ECSBuilder {
     pub fn with_initial_entity_capacity(initial_entity_capacity: usize) -> Self {
         Self {
             component_manager: ComponentManager::new(initial_entity_capacity),
+            resources: Resources::new(),
+            event_updaters: Vec::new(),
+            snapshot_registry: SnapshotRegistry::new(),
+            error_policy: ErrorPolicy::default(),
             initial_entity_capacity,
             max_entity_capacity: DEFAULT_MAX_ENTITY_CAPACITY,
         }
@@ -421,14 +1038,65 @@ impl ECSBuilder {
         self
     }
 
+    // Registers the component and includes it in snapshots under the given name, which has to stay the same for old snapshots to load
+    pub fn with_serializable_component<T: SerializableComponent>(mut self, name: &'static str) -> Self {
+        self.component_manager.register_component::<T>().unwrap_or_else(|e| panic!("{}", e));
+        self.snapshot_registry.register::<T>(name).unwrap_or_else(|e| panic!("{}", e));
+
+        self
+    }
+
+    // Adds an Events<T> resource, which is updated at the start of every frame
+    pub fn with_event<T: Send + Sync + 'static>(mut self) -> Self {
+        if self.resources.contains::<Events<T>>() {
+            panic!("The event type {} is already registered", type_name::<T>());
+        }
+
+        self.resources.insert(Events::<T>::new());
+        self.event_updaters.push(|resources| {
+            if let Some(mut events) = resources.get_mut::<Events<T>>() {
+                events.update();
+            }
+        });
+
+        self
+    }
+
+    // Includes T's values in entity and world dumps. T still has to be registered as a component separately.
+    pub fn with_debug_output<T: Component + Debug>(mut self) -> Self {
+        self.component_manager.register_debug_output::<T>();
+
+        self
+    }
+
+    // Adds a FrameStats resource, which keeps per-system and command flush timings for the last max_frames frames
+    pub fn with_profiling(mut self, max_frames: usize) -> Self {
+        self.resources.insert(FrameStats::new(max_frames));
+
+        self
+    }
+
     pub fn with_max_entity_capacity(mut self, max_entity_capacity: usize) -> Self {
         self.max_entity_capacity = max_entity_capacity;
 
         self
     }
 
-    pub fn build(self) -> ECS {
-        ECS::new(self.component_manager, self.initial_entity_capacity, self.max_entity_capacity)
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+
+        self
+    }
+
+    pub fn build(mut self) -> ECS {
+        // The hierarchy components are always registered, since flushing commands relies on them
+        self.component_manager.register_component::<Parent>().unwrap_or_else(|e| panic!("{}", e));
+        self.component_manager.register_component::<Children>().unwrap_or_else(|e| panic!("{}", e));
+
+        // Children are rebuilt from each Parent as it's attached, so they don't need to be saved
+        self.snapshot_registry.register::<Parent>("Parent").unwrap_or_else(|e| panic!("{}", e));
+
+        ECS::new(self.component_manager, self.resources, self.event_updaters, self.snapshot_registry, self.error_policy, self.initial_entity_capacity, self.max_entity_capacity)
     }
 }
 
@@ -436,8 +1104,1010 @@ impl Default for ECSBuilder {
     fn default() -> Self {
         Self {
             component_manager: ComponentManager::new(DEFAULT_INITIAL_ENTITY_CAPACITY),
+            resources: Resources::new(),
+            event_updaters: Vec::new(),
+            snapshot_registry: SnapshotRegistry::new(),
+            error_policy: ErrorPolicy::default(),
             initial_entity_capacity: DEFAULT_INITIAL_ENTITY_CAPACITY,
             max_entity_capacity: DEFAULT_MAX_ENTITY_CAPACITY,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::app::{App, Clock};
+    use crate::ecs::component::ComponentEventKind;
+    use crate::ecs::event::EventReader;
+    use crate::ecs::query::{Added, Changed, With, Without};
+    use crate::ecs::prefab::Prefab;
+    use crate::ecs::snapshot::{SnapshotReader, SnapshotWriter};
+    use crate::ecs::system::{FixedTimestep, SystemAccess, SystemFn};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+    use std::collections::HashSet;
+
+    #[derive(Clone)]
+    struct Marker {}
+    impl Component for Marker {}
+    impl ComponentActions for Marker {}
+
+    struct Other {}
+    impl Component for Other {}
+    impl ComponentActions for Other {}
+
+    #[derive(Debug)]
+    struct Hits(u32);
+    impl Component for Hits {}
+    impl ComponentActions for Hits {}
+
+    struct Hooked {}
+    impl Component for Hooked {}
+    impl ComponentActions for Hooked {
+        fn on_add(&mut self, entity: &Entity, commands: &mut ECSCommands) {
+            commands.attach_component(entity, Marker {});
+        }
+
+        fn on_remove(&mut self, entity: &Entity, commands: &mut ECSCommands) {
+            commands.detach_component::<Marker>(entity);
+        }
+
+        fn on_replace(&mut self, entity: &Entity, commands: &mut ECSCommands) {
+            commands.attach_component(entity, Other {});
+        }
+    }
+
+    #[derive(Clone)]
+    struct Link {
+        target: Option<Entity>,
+        weight: f32,
+    }
+    impl Component for Link {}
+    impl ComponentActions for Link {}
+    impl SerializableComponent for Link {
+        fn serialize(&self, writer: &mut SnapshotWriter) {
+            writer.write_optional_entity(self.target.as_ref());
+            writer.write(self.weight);
+        }
+
+        fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+            Ok(Self { target: reader.read_optional_entity()?, weight: reader.read()? })
+        }
+    }
+
+    const COUNT_MARKED: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+        for (_, mut hits) in components.query::<&mut Hits>().iter_over(entities[0].iter()) {
+            hits.0 += 1;
+        }
+    };
+
+    fn build_ecs() -> ECS {
+        ECSBuilder::default()
+            .with_component::<Marker>()
+            .with_component::<Other>()
+            .with_component::<Hits>()
+            .with_component::<Hooked>()
+            .build()
+    }
+
+    fn spawn_tracked(ecs: &mut ECS, signature: SystemSignature) -> Entity {
+        let e = ecs.create_entity();
+        ecs.attach_provisional_component(&e, Hits(0));
+        ecs.attach_provisional_component(&e, Marker {});
+        ecs.attach_provisional_component(&e, Other {});
+
+        ecs.register_system(COUNT_MARKED, vec![signature], SystemStage::Update);
+        ecs.invoke_systems();
+
+        ecs.component_manager.query_filtered::<Entity, With<Hits>>().single_entity().unwrap()
+    }
+
+    fn hits(ecs: &ECS, entity: &Entity) -> u32 {
+        ecs.component_manager.get_component::<Hits>(entity).unwrap().0
+    }
+
+    struct Numbered<const TENS: usize, const ONES: usize> {}
+    impl<const TENS: usize, const ONES: usize> Component for Numbered<TENS, ONES> {}
+    impl<const TENS: usize, const ONES: usize> ComponentActions for Numbered<TENS, ONES> {}
+
+    fn with_numbered_components<const TENS: usize>(builder: ECSBuilder) -> ECSBuilder {
+        builder
+            .with_component::<Numbered<TENS, 0>>()
+            .with_component::<Numbered<TENS, 1>>()
+            .with_component::<Numbered<TENS, 2>>()
+            .with_component::<Numbered<TENS, 3>>()
+            .with_component::<Numbered<TENS, 4>>()
+            .with_component::<Numbered<TENS, 5>>()
+            .with_component::<Numbered<TENS, 6>>()
+            .with_component::<Numbered<TENS, 7>>()
+            .with_component::<Numbered<TENS, 8>>()
+            .with_component::<Numbered<TENS, 9>>()
+    }
+
+    #[test]
+    fn excluded_entity_is_admitted_once_the_exclusion_is_detached() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap()
+            .without(ecs.get_system_signature_1::<Other>().unwrap());
+        let e = spawn_tracked(&mut ecs, signature);
+        assert_eq!(hits(&ecs, &e), 0);
+
+        ecs.detach_component::<Other>(&e);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 1);
+    }
+
+    #[test]
+    fn with_any_matches_when_only_one_alternative_is_present() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Hits>().unwrap()
+            .with_any(ecs.get_system_signature_2::<Marker, Other>().unwrap());
+        let e = spawn_tracked(&mut ecs, signature);
+        assert_eq!(hits(&ecs, &e), 1);
+
+        ecs.detach_component::<Marker>(&e);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2);
+
+        ecs.detach_component::<Other>(&e);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2);
+    }
+
+    #[test]
+    fn signatures_match_across_more_than_64_component_types() {
+        let mut builder = ECSBuilder::default();
+        builder = with_numbered_components::<0>(builder);
+        builder = with_numbered_components::<1>(builder);
+        builder = with_numbered_components::<2>(builder);
+        builder = with_numbered_components::<3>(builder);
+        builder = with_numbered_components::<4>(builder);
+        builder = with_numbered_components::<5>(builder);
+        builder = with_numbered_components::<6>(builder);
+        let mut ecs = builder.with_component::<Marker>().with_component::<Other>().with_component::<Hits>().build();
+
+        // Numbered components take bits 0-69, so the exclusion sits at the end of the first word and the alternative starts the second
+        let signature = ecs.get_system_signature_2::<Numbered<0, 0>, Marker>().unwrap()
+            .without(ecs.get_system_signature_1::<Numbered<6, 3>>().unwrap())
+            .with_any(ecs.get_system_signature_2::<Other, Numbered<6, 4>>().unwrap());
+        let e = spawn_tracked(&mut ecs, signature);
+        assert_eq!(hits(&ecs, &e), 0);
+
+        ecs.attach_component(&e, Numbered::<0, 0> {});
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 1);
+
+        ecs.detach_component::<Other>(&e);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 1);
+
+        ecs.attach_component(&e, Numbered::<6, 4> {});
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2);
+
+        ecs.attach_component(&e, Numbered::<6, 3> {});
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2);
+    }
+
+    #[test]
+    fn conflicting_component_borrows_are_rejected() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+        let components = &ecs.component_manager;
+
+        let write = components.try_get_mut_component::<Hits>(&e).unwrap().unwrap();
+        let second_write = components.try_get_mut_component::<Hits>(&e).err().unwrap();
+        let read = components.try_get_component::<Hits>(&e).err().unwrap();
+        assert!(second_write.to_string().contains("is already borrowed"));
+        assert!(read.to_string().contains("is already mutably borrowed"));
+        drop(write);
+
+        let first_read = components.try_get_component::<Hits>(&e).unwrap().unwrap();
+        let second_read = components.try_get_component::<Hits>(&e).unwrap().unwrap();
+        assert_eq!(first_read.0, second_read.0);
+        let write = components.try_get_mut_component::<Hits>(&e).err().unwrap();
+        assert!(write.to_string().contains("is already borrowed"));
+        drop((first_read, second_read));
+
+        assert!(components.try_get_mut_component::<Hits>(&e).unwrap().is_some());
+    }
+
+    #[test]
+    fn stale_handles_cannot_reach_an_entity_which_reuses_their_index() {
+        let mut ecs = ECSBuilder::default().with_component::<Hits>().with_error_policy(ErrorPolicy::Collect).build();
+        let stale = ecs.create_entity();
+        ecs.attach_provisional_component(&stale, Hits(1));
+        ecs.invoke_systems();
+        let stale = ecs.resolve(&stale).unwrap();
+
+        ecs.destroy_entity(&stale);
+        ecs.invoke_systems();
+
+        let e = ecs.create_entity();
+        ecs.attach_provisional_component(&e, Hits(2));
+        ecs.invoke_systems();
+        let e = ecs.resolve(&e).unwrap();
+
+        assert_eq!(e.index, stale.index);
+        assert!(!ecs.is_alive(&stale));
+        assert!(ecs.is_alive(&e));
+        assert!(ecs.component_manager.get_component::<Hits>(&stale).is_none());
+        assert!(!ecs.component_manager.has_component::<Hits>(&stale));
+
+        ecs.replace_component(&stale, Hits(3));
+        ecs.detach_component::<Hits>(&stale);
+        ecs.destroy_entity(&stale);
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.take_errors(), vec![
+            EcsError::EntityNotFound(stale),
+            EcsError::ComponentNotFound { entity: stale, component: type_name::<Hits>() },
+            EcsError::EntityNotFound(stale),
+        ]);
+        assert!(ecs.is_alive(&e));
+        assert_eq!(hits(&ecs, &e), 2);
+    }
+
+    #[test]
+    fn entities_beyond_the_max_capacity_are_rejected() {
+        let mut ecs = ECSBuilder::with_initial_entity_capacity(1).with_max_entity_capacity(2).with_error_policy(ErrorPolicy::Collect).build();
+        (0..3).for_each(|_| { ecs.create_entity(); });
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.take_errors(), vec![EcsError::CapacityExceeded(2)]);
+        assert!(!ecs.is_alive(&Entity { index: 2, generation: 0 }));
+        assert!(!ecs.is_alive(&Entity { index: 100, generation: 0 }));
+
+        let first = ecs.get_entities()[0];
+        ecs.destroy_entity(&first);
+        ecs.create_entity();
+        ecs.invoke_systems();
+
+        assert!(ecs.take_errors().is_empty());
+        assert_eq!(ecs.get_entities().len(), 2);
+    }
+
+    #[test]
+    fn detached_entity_is_removed_from_system() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+        assert_eq!(hits(&ecs, &e), 1);
+
+        ecs.detach_component::<Marker>(&e);
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+
+        assert_eq!(hits(&ecs, &e), 1);
+        assert!(!ecs.component_manager.has_component::<Marker>(&e));
+    }
+
+    #[test]
+    fn reattached_entity_is_returned_to_system() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+
+        ecs.detach_component::<Marker>(&e);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 1);
+
+        ecs.attach_component(&e, Marker {});
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2);
+    }
+
+    #[test]
+    fn detach_and_reattach_in_same_flush_keeps_entity_in_system() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+
+        ecs.detach_component::<Marker>(&e);
+        ecs.attach_component(&e, Marker {});
+        ecs.invoke_systems();
+
+        assert_eq!(hits(&ecs, &e), 2);
+    }
+
+    #[test]
+    fn detach_only_clears_the_detached_component() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Other>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+
+        ecs.detach_component::<Marker>(&e);
+        ecs.invoke_systems();
+
+        assert_eq!(hits(&ecs, &e), 2);
+        let expected_signature = ecs.component_manager.get_signature(TypeId::of::<Hits>()).unwrap()
+            | &ecs.component_manager.get_signature(TypeId::of::<Other>()).unwrap();
+        assert_eq!(ecs.entity_manager.get_signature(&e).unwrap(), expected_signature);
+    }
+
+    #[test]
+    fn closure_systems_keep_their_own_state() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature.clone());
+
+        let mut frames = 0;
+        let step = 2;
+        ecs.register_system(move |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+            frames += 1;
+            for (_, mut hits) in components.query::<&mut Hits>().iter_over(entities[0].iter()) {
+                hits.0 = frames * step;
+            }
+        }, vec![signature], SystemStage::PostUpdate);
+
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 4);
+    }
+
+    #[test]
+    fn unregister_only_removes_that_instance() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature.clone());
+
+        let second = ecs.register_system(COUNT_MARKED.named("COUNT_MARKED_AGAIN"), vec![signature], SystemStage::Update);
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 3);
+
+        ecs.unregister_system(second);
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 5);
+    }
+
+    #[test]
+    fn only_conflicting_systems_are_split_into_separate_batches() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        ecs.register_system(COUNT_MARKED.with_access(SystemAccess::new().writes::<Hits>()), vec![signature.clone()], SystemStage::Update);
+        ecs.register_system(NOOP.with_access(SystemAccess::new().reads::<Other>()), vec![], SystemStage::Update);
+        ecs.register_system(COUNT_MARKED.with_access(SystemAccess::new().writes::<Hits>()), vec![signature.clone()], SystemStage::Update);
+        ecs.register_system(NOOP, vec![], SystemStage::Update);
+        ecs.register_system(NOOP.with_access(SystemAccess::new().reads::<Other>()), vec![], SystemStage::PostUpdate);
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.schedule, vec![vec![0, 1], vec![2], vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn parallel_batch_commands_are_flushed_in_registration_order() {
+        let mut ecs = build_ecs();
+
+        for i in 0..4 {
+            ecs.register_system((move |_: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+                if components.query::<&Hits>().is_empty() {
+                    let e = commands.create_entity();
+                    commands.attach_provisional_component(&e, Hits(i));
+                }
+            }).with_access(SystemAccess::new().reads::<Hits>()), vec![], SystemStage::Update);
+        }
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.schedule, vec![vec![0, 1, 2, 3]]);
+
+        let mut created: Vec<(Entity, u32)> = ecs.component_manager.query::<&Hits>().iter().map(|(e, hits)| (e, hits.0)).collect();
+        created.sort_by_key(|(e, _)| e.index);
+
+        assert_eq!(created.iter().map(|(_, hits)| *hits).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn worker_pool_reuses_its_threads_for_every_job() {
+        let mut pool = WorkerPool::new(3);
+        let runs: Mutex<Vec<(usize, thread::ThreadId)>> = Mutex::new(Vec::new());
+
+        for _ in 0..10 {
+            pool.run(&|thread: usize| runs.lock().unwrap().push((thread, thread::current().id())));
+        }
+
+        let runs = runs.into_inner().unwrap();
+        let threads: HashSet<(usize, thread::ThreadId)> = runs.iter().copied().collect();
+
+        assert_eq!(runs.len(), 40);
+        assert_eq!(threads.len(), 4);
+        assert_eq!(threads.iter().map(|(thread, _)| *thread).collect::<HashSet<_>>(), HashSet::from([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn worker_pool_waits_for_its_workers_before_a_panic_leaves_run() {
+        let mut pool = WorkerPool::new(3);
+
+        for panicking_thread in [0, 2] {
+            let finished = AtomicUsize::new(0);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| pool.run(&|thread: usize| {
+                if thread == panicking_thread {
+                    panic!("job panicked");
+                }
+
+                thread::sleep(Duration::from_millis(10));
+                finished.fetch_add(1, Ordering::Relaxed);
+            })));
+
+            assert!(result.is_err());
+            assert_eq!(finished.load(Ordering::Relaxed), 3);
+        }
+
+        let runs = AtomicUsize::new(0);
+        pool.run(&|_: usize| { runs.fetch_add(1, Ordering::Relaxed); });
+        assert_eq!(runs.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn panic_in_a_parallel_batch_reaches_the_caller() {
+        let mut ecs = build_ecs();
+        let ran = Arc::new(AtomicUsize::new(0));
+        let ran_by_system = ran.clone();
+
+        ecs.register_system((move |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+            thread::sleep(Duration::from_millis(10));
+            ran_by_system.fetch_add(1, Ordering::Relaxed);
+        }).with_access(SystemAccess::new()), vec![], SystemStage::Update);
+        ecs.register_system((|_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+            panic!("system panicked");
+        }).with_access(SystemAccess::new()), vec![], SystemStage::Update);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| ecs.invoke_systems()));
+
+        assert!(result.is_err());
+        assert_eq!(ecs.schedule, vec![vec![0, 1]]);
+        assert_eq!(ran.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn ordering_constraints_override_registration_order() {
+        let mut ecs = build_ecs();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        ecs.register_system(NOOP.named("A").with_access(SystemAccess::new()), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("B").with_access(SystemAccess::new()), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("C").before("A").with_access(SystemAccess::new()), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("D").before("C").with_access(SystemAccess::new()), vec![], SystemStage::Update);
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.schedule, vec![vec![1, 3], vec![2], vec![0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "form a cycle")]
+    fn cyclic_ordering_constraints_are_rejected() {
+        let mut ecs = build_ecs();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        ecs.register_system(NOOP.named("A"), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("B").after("A"), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("C").after("B").before("A"), vec![], SystemStage::Update);
+        ecs.invoke_systems();
+    }
+
+    #[test]
+    fn constraints_against_stage_order_are_rejected_at_registration() {
+        let mut ecs = build_ecs();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        ecs.register_system(NOOP.named("A"), vec![], SystemStage::Update);
+        ecs.invoke_systems();
+
+        ecs.register_system(NOOP.named("B").before("A"), vec![], SystemStage::Render);
+        let mut errors = Vec::new();
+        flush_all_commands(&mut ecs.commands, &mut ecs.entity_manager, &mut ecs.component_manager, &mut ecs.system_managers, &mut ecs.schedule, ecs.initial_entity_capacity, &mut ecs.is_shutdown, &mut errors);
+
+        assert!(matches!(errors.as_slice(), [EcsError::InvalidSchedule(_)]));
+        assert_eq!(ecs.system_managers.len(), 1);
+        assert_eq!(ecs.schedule, vec![vec![0]]);
+    }
+
+    #[test]
+    fn constraints_against_unknown_systems_are_rejected_when_registering() {
+        let mut ecs = ECSBuilder::default().with_error_policy(ErrorPolicy::Collect).build();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        ecs.register_system(NOOP.named("A"), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("B").after("MISSING"), vec![], SystemStage::Update);
+
+        assert!(matches!(ecs.take_errors().as_slice(), [EcsError::InvalidSchedule(reason)] if reason.contains("MISSING")));
+
+        ecs.invoke_systems();
+        assert_eq!(ecs.system_managers.len(), 1);
+        assert_eq!(ecs.schedule, vec![vec![0]]);
+    }
+
+    #[test]
+    fn systems_which_others_are_ordered_against_cannot_be_unregistered() {
+        let mut ecs = build_ecs();
+        const NOOP: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {};
+
+        let a = ecs.register_system(NOOP.named("A"), vec![], SystemStage::Update);
+        ecs.register_system(NOOP.named("B").after("A"), vec![], SystemStage::Update);
+        ecs.invoke_systems();
+
+        ecs.unregister_system(a);
+        let mut errors = Vec::new();
+        flush_all_commands(&mut ecs.commands, &mut ecs.entity_manager, &mut ecs.component_manager, &mut ecs.system_managers, &mut ecs.schedule, ecs.initial_entity_capacity, &mut ecs.is_shutdown, &mut errors);
+
+        assert!(matches!(errors.as_slice(), [EcsError::InvalidSchedule(_)]));
+        assert_eq!(ecs.system_managers.len(), 2);
+        assert_eq!(ecs.schedule, vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn physics_stage_runs_once_per_fixed_step() {
+        let mut ecs = build_ecs();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature.clone());
+        ecs.register_system(COUNT_MARKED, vec![signature], SystemStage::Physics);
+        ecs.insert_resource(FixedTimestep::new(Duration::from_millis(10), 3));
+
+        ecs.get_resource_mut::<FixedTimestep>().unwrap().accumulate(Duration::from_millis(25));
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 2 + 2);
+        assert!((ecs.get_resource::<FixedTimestep>().unwrap().get_alpha() - 0.5).abs() < 0.001);
+
+        ecs.get_resource_mut::<FixedTimestep>().unwrap().accumulate(Duration::from_millis(100));
+        ecs.invoke_systems();
+        assert_eq!(hits(&ecs, &e), 4 + 1 + 3);
+        assert_eq!(ecs.get_resource::<FixedTimestep>().unwrap().get_alpha(), 0.0);
+    }
+
+    #[test]
+    fn destroying_a_parent_destroys_its_descendants() {
+        let mut ecs = build_ecs();
+        let root = ecs.create_entity();
+        let child = ecs.create_entity();
+        let grandchild = ecs.create_entity();
+        let sibling = ecs.create_entity();
+        ecs.attach_provisional_component(&child, Parent::new_provisional(root));
+        ecs.attach_provisional_component(&grandchild, Parent::new_provisional(child));
+        ecs.attach_provisional_component(&sibling, Marker {});
+        ecs.invoke_systems();
+
+        let root = ecs.component_manager.query_filtered::<Entity, (With<Children>, Without<Parent>)>().single_entity().unwrap();
+        let child = ecs.component_manager.get_component::<Children>(&root).unwrap().get_entities()[0];
+        let grandchild = ecs.component_manager.get_component::<Children>(&child).unwrap().get_entities()[0];
+        let sibling = ecs.component_manager.query_filtered::<Entity, With<Marker>>().single_entity().unwrap();
+
+        ecs.destroy_entity(&root);
+        ecs.invoke_systems();
+
+        assert!(!ecs.is_alive(&root));
+        assert!(!ecs.is_alive(&child));
+        assert!(!ecs.is_alive(&grandchild));
+        assert!(ecs.is_alive(&sibling));
+    }
+
+    #[test]
+    fn detaching_the_last_child_removes_children() {
+        let mut ecs = build_ecs();
+        let parent = ecs.create_entity();
+        ecs.attach_provisional_component(&parent, Marker {});
+        let child = ecs.create_entity();
+        ecs.attach_provisional_component(&child, Parent::new_provisional(parent));
+        ecs.invoke_systems();
+
+        let parent = ecs.component_manager.query_filtered::<Entity, With<Marker>>().single_entity().unwrap();
+        let child = ecs.component_manager.query_filtered::<Entity, With<Parent>>().single_entity().unwrap();
+        assert_eq!(ecs.component_manager.get_component::<Children>(&parent).unwrap().get_entities(), &[child]);
+
+        ecs.detach_component::<Parent>(&child);
+        ecs.invoke_systems();
+        assert!(!ecs.component_manager.has_component::<Children>(&parent));
+
+        ecs.attach_component(&child, Parent::new(parent));
+        ecs.destroy_entity(&child);
+        ecs.invoke_systems();
+        assert!(ecs.is_alive(&parent));
+        assert!(!ecs.component_manager.has_component::<Children>(&parent));
+    }
+
+    #[test]
+    fn parent_which_would_form_a_cycle_is_never_attached() {
+        let mut ecs = ECSBuilder::default().with_component::<Marker>().with_component::<Other>().with_error_policy(ErrorPolicy::Collect).build();
+        let a = ecs.create_entity();
+        ecs.attach_provisional_component(&a, Marker {});
+        let b = ecs.create_entity();
+        ecs.attach_provisional_component(&b, Other {});
+        ecs.attach_provisional_component(&b, Parent::new_provisional(a));
+        ecs.invoke_systems();
+
+        let a = ecs.component_manager.query_filtered::<Entity, With<Marker>>().single_entity().unwrap();
+        let b = ecs.component_manager.query_filtered::<Entity, With<Other>>().single_entity().unwrap();
+
+        ecs.attach_component(&a, Parent::new(b));
+        ecs.invoke_systems();
+
+        assert!(matches!(ecs.take_errors().as_slice(), [EcsError::HierarchyCycle { .. }]));
+        assert!(!ecs.component_manager.has_component::<Parent>(&a));
+        assert!(!ecs.component_manager.has_component::<Children>(&b));
+
+        let c = ecs.create_entity();
+        ecs.attach_provisional_component(&c, Parent::new(b));
+        ecs.invoke_systems();
+
+        assert!(ecs.take_errors().is_empty());
+        assert_eq!(ecs.component_manager.get_component::<Children>(&b).unwrap().get_entities().len(), 1);
+    }
+
+    #[test]
+    fn parent_from_another_ecs_is_reported_as_unknown_provisional_entity() {
+        let mut ecs = ECSBuilder::default().with_error_policy(ErrorPolicy::Collect).build();
+        let foreign = ECSBuilder::default().build().create_entity();
+        let child = ecs.create_entity();
+        ecs.invoke_systems();
+        let child = ecs.resolve(&child).unwrap();
+
+        ecs.attach_component(&child, Parent::new_provisional(foreign));
+        ecs.invoke_systems();
+
+        assert_eq!(ecs.take_errors(), vec![EcsError::ProvisionalEntityNotFound(foreign)]);
+        assert!(!ecs.component_manager.has_component::<Parent>(&child));
+    }
+
+    #[test]
+    fn destroy_entities_with_removes_tagged_entities_and_their_descendants() {
+        let mut ecs = build_ecs();
+        let parent = ecs.create_entity();
+        ecs.attach_provisional_component(&parent, Other {});
+        let tagged = ecs.create_entity();
+        ecs.attach_provisional_component(&tagged, Marker {});
+        ecs.attach_provisional_component(&tagged, Parent::new_provisional(parent));
+        let untagged_child = ecs.create_entity();
+        ecs.attach_provisional_component(&untagged_child, Other {});
+        ecs.attach_provisional_component(&untagged_child, Parent::new_provisional(tagged));
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let seen_by_system = seen.clone();
+        ecs.register_system(move |entities: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+            seen_by_system.store(entities[0].len(), Ordering::Relaxed);
+        }, vec![ecs.get_system_signature_1::<Other>().unwrap()], SystemStage::Update);
+        ecs.invoke_systems();
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+
+        let parent = ecs.component_manager.query_filtered::<Entity, (With<Other>, Without<Parent>)>().single_entity().unwrap();
+        let tagged = ecs.component_manager.query_filtered::<Entity, With<Marker>>().single_entity().unwrap();
+        let untagged_child = ecs.component_manager.query_filtered::<Entity, (With<Other>, With<Parent>)>().single_entity().unwrap();
+
+        ecs.destroy_entities_with::<Marker>();
+        ecs.invoke_systems();
+
+        assert!(!ecs.is_alive(&tagged));
+        assert!(!ecs.is_alive(&untagged_child));
+        assert!(ecs.is_alive(&parent));
+        assert!(!ecs.component_manager.has_component::<Children>(&parent));
+        assert_eq!(seen.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn lifecycle_hook_commands_are_applied_in_the_same_flush() {
+        let mut ecs = build_ecs();
+        let e = ecs.create_entity();
+        ecs.attach_provisional_component(&e, Hooked {});
+        ecs.invoke_systems();
+
+        let e = ecs.component_manager.query_filtered::<Entity, With<Hooked>>().single_entity().unwrap();
+        assert!(ecs.component_manager.has_component::<Marker>(&e));
+
+        ecs.replace_component(&e, Hooked {});
+        ecs.invoke_systems();
+        assert!(ecs.component_manager.has_component::<Other>(&e));
+
+        ecs.detach_component::<Hooked>(&e);
+        ecs.invoke_systems();
+        assert!(!ecs.component_manager.has_component::<Marker>(&e));
+        assert!(ecs.component_manager.has_component::<Other>(&e));
+    }
+
+    #[test]
+    fn component_events_last_until_the_end_of_the_next_frame() {
+        let mut ecs = build_ecs();
+        let e = ecs.create_entity();
+        ecs.attach_provisional_component(&e, Other {});
+        ecs.invoke_systems();
+
+        let e = ecs.component_manager.query_filtered::<Entity, With<Other>>().single_entity().unwrap();
+        let events = |ecs: &ECS| ecs.component_manager.get_component_events::<Other>().map(|event| event.kind).collect::<Vec<_>>();
+        assert_eq!(events(&ecs), vec![ComponentEventKind::Added]);
+
+        ecs.replace_component(&e, Other {});
+        ecs.invoke_systems();
+        assert_eq!(events(&ecs), vec![ComponentEventKind::Added, ComponentEventKind::Changed]);
+
+        ecs.destroy_entity(&e);
+        ecs.invoke_systems();
+        assert_eq!(events(&ecs), vec![ComponentEventKind::Changed, ComponentEventKind::Removed]);
+
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+        assert!(events(&ecs).is_empty());
+    }
+
+    #[test]
+    fn added_and_changed_only_match_during_the_current_frame() {
+        let mut ecs = build_ecs();
+        let e = ecs.create_entity();
+        ecs.attach_provisional_component(&e, Other {});
+        ecs.invoke_systems();
+
+        let e = ecs.component_manager.query::<(Entity, &Other)>().single().unwrap().0;
+        assert_eq!(ecs.component_manager.query_filtered::<Entity, Added<Other>>().count(), 1);
+        assert_eq!(ecs.component_manager.query_filtered::<Entity, Changed<Other>>().count(), 1);
+
+        ecs.invoke_systems();
+        assert!(ecs.component_manager.query_filtered::<Entity, Added<Other>>().is_empty());
+        assert!(ecs.component_manager.query_filtered::<Entity, Changed<Other>>().is_empty());
+
+        ecs.component_manager.get_mut_component::<Other>(&e);
+        assert!(ecs.component_manager.query_filtered::<Entity, Added<Other>>().is_empty());
+        assert_eq!(ecs.component_manager.query_filtered::<Entity, Changed<Other>>().single_entity(), Some(e));
+    }
+
+    #[test]
+    fn added_matches_entities_spawned_since_the_system_last_ran() {
+        let mut ecs = build_ecs();
+        ecs.insert_resource(Vec::<usize>::new());
+
+        // Runs before the spawning system, so each spawned entity is only seen on the next frame
+        ecs.register_system(|_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+            resources.get_mut::<Vec<usize>>().unwrap().push(components.query_filtered::<Entity, Added<Marker>>().count());
+        }, vec![], SystemStage::Update);
+        ecs.register_system(|_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+            let e = commands.create_entity();
+            commands.attach_provisional_component(&e, Marker {});
+        }, vec![], SystemStage::PostUpdate);
+
+        (0..3).for_each(|_| { ecs.invoke_systems(); });
+
+        assert_eq!(*ecs.get_resource::<Vec<usize>>().unwrap(), vec![0, 1, 1]);
+        assert_eq!(ecs.component_manager.query::<&Marker>().count(), 3);
+    }
+
+    #[test]
+    fn each_event_reader_sees_every_event_exactly_once() {
+        struct Ping;
+
+        let mut ecs = ECSBuilder::default().with_event::<Ping>().build();
+        let read_before = Arc::new(AtomicUsize::new(0));
+        let read_after = Arc::new(AtomicUsize::new(0));
+
+        let reader = |read: Arc<AtomicUsize>| {
+            let mut reader = EventReader::<Ping>::new();
+
+            move |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+                let events = resources.get::<Events<Ping>>().unwrap();
+                read.fetch_add(events.read(&mut reader).count(), Ordering::Relaxed);
+            }
+        };
+
+        ecs.register_system(reader(read_before.clone()), vec![], SystemStage::PreUpdate);
+        ecs.register_system(|_: &[HashSet<Entity>], _: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+            commands.send_event(Ping);
+        }, vec![], SystemStage::Update);
+        ecs.register_system(reader(read_after.clone()), vec![], SystemStage::PostUpdate);
+
+        ecs.invoke_systems();
+        assert_eq!((read_before.load(Ordering::Relaxed), read_after.load(Ordering::Relaxed)), (0, 1));
+
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+        assert_eq!((read_before.load(Ordering::Relaxed), read_after.load(Ordering::Relaxed)), (2, 3));
+        assert_eq!(ecs.get_resource::<Events<Ping>>().unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn provisional_entities_resolve_across_flushes_and_attach_methods() {
+        let mut ecs = build_ecs();
+        let root = ecs.create_entity();
+        ecs.invoke_systems();
+        let root = ecs.resolve(&root).unwrap();
+
+        let provisional_child = ecs.create_entity();
+        ecs.attach_component(&root, Parent::new_provisional(provisional_child));
+        ecs.invoke_systems();
+
+        let child = ecs.resolve(&provisional_child).unwrap();
+        assert_eq!(ecs.component_manager.get_component::<Parent>(&root).unwrap().get_entity(), child);
+        assert_eq!(ecs.component_manager.get_component::<Children>(&child).unwrap().get_entities(), &[root]);
+
+        ecs.destroy_entity(&child);
+        ecs.invoke_systems();
+        assert!(!ecs.is_alive(&root));
+        assert_eq!(ecs.resolve(&provisional_child), None);
+    }
+
+    #[test]
+    fn failed_commands_are_collected_and_skipped() {
+        let mut ecs = ECSBuilder::default().with_component::<Marker>().with_error_policy(ErrorPolicy::Collect).build();
+        let e = ecs.create_entity();
+        ecs.invoke_systems();
+        let e = ecs.resolve(&e).unwrap();
+
+        ecs.destroy_entity(&e);
+        ecs.invoke_systems();
+
+        let other = ecs.create_entity();
+        ecs.destroy_entity(&e);
+        ecs.attach_provisional_component(&other, Marker {});
+        ecs.attach_provisional_component(&other, Marker {});
+        ecs.invoke_systems();
+
+        let other = ecs.resolve(&other).unwrap();
+        assert!(ecs.component_manager.has_component::<Marker>(&other));
+        assert_eq!(ecs.take_errors(), vec![
+            EcsError::EntityNotFound(e),
+            EcsError::DuplicateComponent { entity: other, component: type_name::<Marker>() },
+        ]);
+        assert!(ecs.take_errors().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn failed_commands_panic_by_default() {
+        let mut ecs = build_ecs();
+        let e = ecs.create_entity();
+        ecs.invoke_systems();
+        let e = ecs.resolve(&e).unwrap();
+
+        ecs.destroy_entity(&e);
+        ecs.destroy_entity(&e);
+        ecs.invoke_systems();
+    }
+
+    #[test]
+    fn snapshots_remap_entity_references_on_load() {
+        let mut ecs = ECSBuilder::default().with_component::<Marker>().with_serializable_component::<Link>("Link").build();
+        let (a, b, c) = (ecs.create_entity(), ecs.create_entity(), ecs.create_entity());
+        ecs.attach_provisional_component(&c, Marker {});
+        ecs.invoke_systems();
+        let (a, b, c) = (ecs.resolve(&a).unwrap(), ecs.resolve(&b).unwrap(), ecs.resolve(&c).unwrap());
+
+        ecs.attach_component(&a, Link { target: Some(b), weight: 0.1 });
+        ecs.attach_component(&b, Link { target: Some(c), weight: 2.5 });
+        ecs.attach_component(&b, Parent::new(a));
+        ecs.invoke_systems();
+
+        let snapshot = ecs.save_snapshot();
+        let loaded = ecs.load_snapshot(&snapshot).unwrap();
+        let components = &ecs.component_manager;
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(components.get_component::<Link>(&loaded[0]).unwrap().target, Some(loaded[1]));
+        assert_eq!(components.get_component::<Link>(&loaded[0]).unwrap().weight, 0.1);
+        assert_eq!(components.get_component::<Link>(&loaded[1]).unwrap().target, Some(c));
+        assert_eq!(components.get_component::<Parent>(&loaded[1]).unwrap().get_entity(), loaded[0]);
+        assert_eq!(components.get_component::<Children>(&loaded[0]).unwrap().get_entities(), &[loaded[1]]);
+        assert_eq!(ecs.save_snapshot().matches("Link").count(), 4);
+
+        assert!(ecs.load_snapshot("snapshot 1\nentity 0\n  Link @0 1.0\nentity 1\n  Link @2 1.0\n").is_err());
+        assert_eq!(ecs.component_manager.query::<&Link>().count(), 4);
+
+        ecs.destroy_entity(&c);
+        ecs.invoke_systems();
+        let snapshot = ecs.save_snapshot();
+        assert!(ecs.load_snapshot(&snapshot).is_err());
+        assert_eq!(ecs.component_manager.query::<&Link>().count(), 4);
+    }
+
+    #[test]
+    fn prefabs_spawn_fresh_components_with_overrides() {
+        let mut ecs = ECSBuilder::default().with_component::<Marker>().with_serializable_component::<Link>("Link").build();
+        let linked = Prefab::new("Linked").with(Link { target: None, weight: 1.0 }).with(Marker {});
+        let heavy = ecs.load_prefabs("prefab Heavy\n  Link - 3.5\n").unwrap();
+        assert_eq!(heavy[0].get_name(), "Heavy");
+
+        let spawned = [
+            ecs.spawn_prefab(&linked),
+            ecs.spawn_prefab(&linked.clone().with(Link { target: None, weight: 2.0 })),
+            ecs.spawn_prefab(&heavy[0]),
+            ecs.spawn_prefab(&heavy[0]),
+        ];
+        ecs.invoke_systems();
+
+        let spawned = spawned.map(|p| ecs.resolve(&p).unwrap());
+        let weights = spawned.map(|e| ecs.component_manager.get_component::<Link>(&e).unwrap().weight);
+        let marked = spawned.map(|e| ecs.component_manager.has_component::<Marker>(&e));
+        assert_eq!(weights, [1.0, 2.0, 3.5, 3.5]);
+        assert_eq!(marked, [true, true, false, false]);
+
+        assert!(ecs.load_prefabs("prefab Broken\n  Link - heavy\n").is_err());
+        assert!(ecs.load_prefabs("  Link - 1.0\n").is_err());
+    }
+
+    #[test]
+    fn inspection_lists_entities_components_and_systems() {
+        let mut ecs = ECSBuilder::default()
+            .with_component::<Marker>()
+            .with_component::<Other>()
+            .with_component::<Hits>()
+            .with_debug_output::<Hits>()
+            .build();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        let e = spawn_tracked(&mut ecs, signature);
+
+        let other = ecs.create_entity();
+        ecs.attach_provisional_component(&other, Other {});
+        ecs.invoke_systems();
+        let other = ecs.resolve(&other).unwrap();
+
+        assert_eq!(ecs.get_entities(), vec![e, other]);
+        assert_eq!(ecs.inspect_entity(&other).unwrap().components, vec![type_name::<Other>()]);
+        assert_eq!(ecs.inspect_entity(&e).unwrap().components.len(), 3);
+
+        let other_info = ecs.inspect_components().into_iter().find(|c| c.name == type_name::<Other>()).unwrap();
+        assert_eq!(other_info.entity_count, 2);
+
+        let systems = ecs.inspect_systems();
+        assert_eq!(systems.len(), 1);
+        assert_eq!(systems[0].entity_counts, vec![1]);
+
+        // Only components registered for debug output have their values dumped
+        let dump = ecs.dump_entity(&e).unwrap();
+        assert!(dump.contains(&format!("  {}: Hits(2)\n", type_name::<Hits>())));
+        assert!(dump.contains(&format!("  {}\n", type_name::<Marker>())));
+
+        ecs.destroy_entity(&other);
+        ecs.invoke_systems();
+        assert!(ecs.inspect_entity(&other).is_none());
+        assert_eq!(ecs.inspect_entities().len(), 1);
+    }
+
+    #[test]
+    fn profiling_keeps_a_rolling_window_of_frame_samples() {
+        let mut ecs = ECSBuilder::default()
+            .with_component::<Marker>()
+            .with_component::<Other>()
+            .with_component::<Hits>()
+            .with_profiling(2)
+            .build();
+        let signature = ecs.get_system_signature_1::<Marker>().unwrap();
+        spawn_tracked(&mut ecs, signature);
+        ecs.invoke_systems();
+        ecs.invoke_systems();
+
+        let stats = ecs.get_resource::<FrameStats>().unwrap();
+        assert_eq!(stats.get_frames().count(), 2);
+        assert!(stats.get_frames().all(|frame| frame.systems.len() == 1 && !frame.flushes.is_empty()));
+
+        let system_stats = stats.get_system_stats();
+        assert_eq!(system_stats.len(), 1);
+        assert_eq!(system_stats[0].runs, 2);
+        assert_eq!(system_stats[0].last_entity_count, 1);
+
+        let trace = stats.to_chrome_trace();
+        assert_eq!(trace.matches("\"cat\":\"system\"").count(), 2);
+        assert!(trace.contains("\"args\":{\"entities\":1}"));
+    }
+
+    #[test]
+    fn app_runs_frames_with_a_manual_clock_until_shutdown() {
+        let mut ecs = build_ecs();
+        ecs.insert_resource(Vec::<SystemTime>::new());
+        ecs.register_system(|_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+            let mut frame_times = resources.get_mut::<Vec<SystemTime>>().unwrap();
+            frame_times.push(resources.get::<Clock>().unwrap().now());
+
+            if frame_times.len() == 5 {
+                commands.shutdown();
+            }
+        }, vec![], SystemStage::Update);
+
+        let mut app = App::new(ecs).with_clock(Clock::manual(Duration::from_millis(10)));
+        assert_eq!(app.run_frames(3), 3);
+        assert_eq!(app.run_frames(10), 2);
+        assert_eq!(app.get_frame_count(), 5);
+        assert!(!app.step());
+
+        let frame_times = app.get_ecs().get_resource::<Vec<SystemTime>>().unwrap();
+        let expected: Vec<SystemTime> = (1..=5).map(|i| SystemTime::UNIX_EPOCH + Duration::from_millis(10 * i)).collect();
+        assert_eq!(*frame_times, expected);
+    }
+}
@@ -0,0 +1,158 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+// Called with the number of the thread it's running on, with the thread that runs the pool always being thread 0
+type Job<'a> = dyn Fn(usize) + Sync + 'a;
+
+struct PoolState {
+    // Jobs borrow from whatever runs them, so the lifetime is erased while workers share it. See WorkerPool::run for why that's sound.
+    job: Option<&'static Job<'static>>,
+    generation: u64,
+    running_workers: usize,
+    has_panicked: bool,
+    is_shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<PoolState>,
+    job_started: Condvar,
+    job_finished: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // Jobs are run outside of the lock and panics are caught, so it's never held by a thread which panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Threads which are kept for as long as the ECS is, so that parallel batches don't start new threads every frame. Workers are only
+//  started the first time a job is run.
+pub(in crate::ecs) struct WorkerPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+    worker_count: usize,
+}
+
+impl WorkerPool {
+    pub(in crate::ecs) fn new(worker_count: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(PoolState {
+                    job: None,
+                    generation: 0,
+                    running_workers: 0,
+                    has_panicked: false,
+                    is_shutdown: false,
+                }),
+                job_started: Condvar::new(),
+                job_finished: Condvar::new(),
+            }),
+            workers: Vec::with_capacity(worker_count),
+            worker_count,
+        }
+    }
+
+    // The calling thread runs the job too, so it's run worker_count + 1 times at once. Returns once every thread has finished it.
+    pub(in crate::ecs) fn run(&mut self, job: &Job<'_>) {
+        if self.workers.len() < self.worker_count {
+            self.start_workers();
+        }
+
+        // SAFETY: workers only call the job between being woken for its generation and decrementing running_workers, and never
+        //  keep it past that. The guard is in place before the job is published, and until running_workers is back to 0 it
+        //  blocks this function from either returning or unwinding, so the job can't be called once its borrows have ended.
+        let shared_job = unsafe { std::mem::transmute::<&Job<'_>, &'static Job<'static>>(job) };
+        let running_job = RunningJob { shared: &self.shared };
+
+        {
+            let mut state = self.shared.lock();
+            state.job = Some(shared_job);
+            state.generation += 1;
+            state.running_workers = self.workers.len();
+        }
+        self.shared.job_started.notify_all();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| job(0)));
+
+        drop(running_job);
+        let has_worker_panicked = std::mem::take(&mut self.shared.lock().has_panicked);
+
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+
+        if has_worker_panicked {
+            panic!("A job panicked on a worker thread");
+        }
+    }
+
+    fn start_workers(&mut self) {
+        for thread in (self.workers.len() + 1)..=self.worker_count {
+            let shared = self.shared.clone();
+            let generation = shared.lock().generation;
+
+            let worker = thread::Builder::new()
+                .name(format!("ecs-worker-{}", thread))
+                .spawn(move || run_worker(&shared, thread, generation))
+                .unwrap_or_else(|e| panic!("Failed to start worker thread: {}", e));
+
+            self.workers.push(worker);
+        }
+    }
+}
+
+// Waits for the workers to finish the running job when dropped, which also happens if WorkerPool::run unwinds
+struct RunningJob<'a> {
+    shared: &'a Shared,
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        while state.running_workers > 0 {
+            state = self.shared.job_finished.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.job = None;
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shared.lock().is_shutdown = true;
+        self.shared.job_started.notify_all();
+
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap_or_default();
+        }
+    }
+}
+
+fn run_worker(shared: &Shared, thread: usize, mut generation: u64) {
+    loop {
+        let job = {
+            let mut state = shared.lock();
+            while !state.is_shutdown && state.generation == generation {
+                state = shared.job_started.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+
+            if state.is_shutdown {
+                return;
+            }
+
+            generation = state.generation;
+            state.job.unwrap_or_else(|| panic!("Internal error: worker was started without a job"))
+        };
+
+        let is_ok = panic::catch_unwind(AssertUnwindSafe(|| job(thread))).is_ok();
+
+        let mut state = shared.lock();
+        state.has_panicked |= !is_ok;
+        state.running_workers -= 1;
+
+        if state.running_workers == 0 {
+            shared.job_finished.notify_all();
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Result};
+use std::any::TypeId;
+use std::sync::Arc;
+
+use crate::ecs::component::{Component, ComponentManager};
+use crate::ecs::resource::Resources;
+use crate::ecs::snapshot::{SnapshotReader, SnapshotRegistry, PREFAB_KEYWORD};
+use crate::ecs::ComponentActions;
+
+pub(in crate::ecs) trait PrefabComponent: Send + Sync {
+    fn instantiate(&self) -> Box<dyn ComponentActions + Send>;
+}
+
+impl<T: Component + Clone> PrefabComponent for T {
+    fn instantiate(&self) -> Box<dyn ComponentActions + Send> {
+        Box::new(self.clone())
+    }
+}
+
+// A named bundle of components which can be spawned any number of times. Component values are shared between clones of a
+//  prefab, so overriding components for a single spawn is as cheap as prefab.clone().with(component).
+#[derive(Clone)]
+pub struct Prefab {
+    name: String,
+    components: Vec<(TypeId, Arc<dyn PrefabComponent>)>,
+}
+
+impl Prefab {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            components: Vec::new(),
+        }
+    }
+
+    // Replaces the prefab's existing T component, if it has one
+    pub fn with<T: Component + Clone>(mut self, component: T) -> Self {
+        self.insert(TypeId::of::<T>(), Arc::new(component));
+
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has<T: Component>(&self) -> bool {
+        self.components.iter().any(|(type_id, _)| *type_id == TypeId::of::<T>())
+    }
+
+    pub(in crate::ecs) fn instantiate(&self) -> impl Iterator<Item = (TypeId, Box<dyn ComponentActions + Send>)> + '_ {
+        self.components.iter().map(|(type_id, component)| (*type_id, component.instantiate()))
+    }
+
+    fn insert(&mut self, type_id: TypeId, component: Arc<dyn PrefabComponent>) {
+        match self.components.iter_mut().find(|(t, _)| *t == type_id) {
+            Some((_, existing)) => *existing = component,
+            None => self.components.push((type_id, component)),
+        }
+    }
+}
+
+// Prefab data uses the same component lines as snapshots, with a "prefab <name>" line starting each prefab, e.g.
+//  prefab Crate
+//    Transform 0 0 0 1 0 0 0 1 1 1
+//    LevelEntity
+// Every component is deserialized once while parsing, so that broken data is rejected up front rather than when spawning, and
+//  each spawn gets a clone of it.
+pub(in crate::ecs) fn parse_prefabs(data: &str, registry: &SnapshotRegistry, components: &ComponentManager, resources: &Resources) -> Result<Vec<Prefab>> {
+    let mut prefabs: Vec<Prefab> = Vec::new();
+
+    for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if tokens[0] == PREFAB_KEYWORD {
+            match tokens.as_slice() {
+                [_, name] => prefabs.push(Prefab::new(name)),
+                _ => return Err(anyhow!("Expected \"{} <name>\" but found {:?}", PREFAB_KEYWORD, line)),
+            }
+
+            continue;
+        }
+
+        let (type_id, deserialize) = registry.get_prefab_deserializer(tokens[0])
+            .ok_or_else(|| anyhow!("Prefab component {} has not been registered", tokens[0]))?;
+
+        let prefab = prefabs.last_mut()
+            .ok_or_else(|| anyhow!("Prefab component {} doesn't belong to any prefab", tokens[0]))?;
+
+        let mut reader = SnapshotReader::new(&[], &tokens[1..], components, resources);
+        let component = deserialize(&mut reader).map_err(|e| anyhow!("Failed to load {} for prefab {}: {}", tokens[0], prefab.name, e))?;
+
+        if !reader.is_finished() {
+            return Err(anyhow!("Too many values for {} for prefab {}", tokens[0], prefab.name));
+        }
+
+        prefab.insert(type_id, component);
+    }
+
+    Ok(prefabs)
+}
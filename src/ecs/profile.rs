@@ -0,0 +1,239 @@
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::ecs::inspect::SystemInfo;
+use crate::ecs::system::{SystemId, SystemStage};
+
+// Threads are numbered within each parallel batch, with the thread that invokes the systems always being thread 0
+#[derive(Debug, Clone)]
+pub struct SystemSample {
+    pub system: SystemInfo,
+    pub start: Duration,
+    pub duration: Duration,
+    pub thread: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlushSample {
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+// Start times are relative to when the FrameStats resource was created
+#[derive(Debug, Clone)]
+pub struct FrameSample {
+    pub start: Duration,
+    pub duration: Duration,
+    pub systems: Vec<SystemSample>,
+    pub flushes: Vec<FlushSample>,
+}
+
+impl FrameSample {
+    pub fn get_flush_time(&self) -> Duration {
+        self.flushes.iter().map(|f| f.duration).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemStats {
+    pub id: SystemId,
+    pub name: String,
+    pub stage: SystemStage,
+    // A system can run more than once per frame, e.g. in the Physics stage with a FixedTimestep
+    pub runs: usize,
+    pub total: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+    pub last_entity_count: usize,
+}
+
+// Resource which keeps timings for the most recent frames. Systems are only profiled while this resource exists, so profiling can be
+//  turned on and off by inserting and removing it, or from the start with ECSBuilder::with_profiling.
+pub struct FrameStats {
+    epoch: Instant,
+    max_frames: usize,
+    frames: VecDeque<FrameSample>,
+}
+
+const TRACE_PROCESS_ID: usize = 0;
+
+impl FrameStats {
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            epoch: Instant::now(),
+            max_frames,
+            frames: VecDeque::with_capacity(max_frames),
+        }
+    }
+
+    // Oldest first
+    pub fn get_frames(&self) -> impl Iterator<Item = &FrameSample> {
+        self.frames.iter()
+    }
+
+    pub fn get_mean_frame_time(&self) -> Duration {
+        self.mean(|frame| frame.duration)
+    }
+
+    pub fn get_mean_flush_time(&self) -> Duration {
+        self.mean(|frame| frame.get_flush_time())
+    }
+
+    // Ordered by total time, so the systems which dominate frame time come first
+    pub fn get_system_stats(&self) -> Vec<SystemStats> {
+        let mut stats: Vec<SystemStats> = Vec::new();
+        let mut indexes: HashMap<SystemId, usize> = HashMap::new();
+
+        for sample in self.frames.iter().flat_map(|frame| frame.systems.iter()) {
+            let index = *indexes.entry(sample.system.id).or_insert_with(|| {
+                stats.push(SystemStats {
+                    id: sample.system.id,
+                    name: sample.system.name.clone(),
+                    stage: sample.system.stage,
+                    runs: 0,
+                    total: Duration::ZERO,
+                    mean: Duration::ZERO,
+                    max: Duration::ZERO,
+                    last_entity_count: 0,
+                });
+
+                stats.len() - 1
+            });
+
+            let system_stats = &mut stats[index];
+            system_stats.runs += 1;
+            system_stats.total += sample.duration;
+            system_stats.max = system_stats.max.max(sample.duration);
+            system_stats.last_entity_count = sample.system.entity_counts.iter().sum();
+        }
+
+        stats.iter_mut().for_each(|s| s.mean = s.total / s.runs as u32);
+        stats.sort_by_key(|s| Reverse(s.total));
+
+        stats
+    }
+
+    // Chrome's trace event format, which can be opened with chrome://tracing or https://ui.perfetto.dev
+    pub fn to_chrome_trace(&self) -> String {
+        let mut events: Vec<String> = Vec::new();
+
+        for frame in self.frames.iter() {
+            events.push(trace_event("Frame", "frame", frame.start, frame.duration, 0, None));
+
+            for sample in frame.systems.iter() {
+                let entity_count = sample.system.entity_counts.iter().sum();
+                events.push(trace_event(&sample.system.name, "system", sample.start, sample.duration, sample.thread, Some(entity_count)));
+            }
+
+            for flush in frame.flushes.iter() {
+                events.push(trace_event("Flush commands", "flush", flush.start, flush.duration, 0, None));
+            }
+        }
+
+        format!("{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    }
+
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_chrome_trace())?;
+
+        Ok(())
+    }
+
+    fn mean(&self, get_duration: impl Fn(&FrameSample) -> Duration) -> Duration {
+        if self.frames.is_empty() {
+            return Duration::ZERO;
+        }
+
+        self.frames.iter().map(get_duration).sum::<Duration>() / self.frames.len() as u32
+    }
+
+    fn push_frame(&mut self, frame: FrameSample) {
+        if self.max_frames == 0 {
+            return;
+        }
+
+        if self.frames.len() == self.max_frames {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(frame);
+    }
+}
+
+fn trace_event(name: &str, category: &str, start: Duration, duration: Duration, thread: usize, entity_count: Option<usize>) -> String {
+    let args = entity_count.map(|count| format!(",\"args\":{{\"entities\":{}}}", count)).unwrap_or_default();
+
+    format!(
+        "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":{},\"tid\":{}{}}}",
+        escape_json(name), category, start.as_secs_f64() * 1_000_000.0, duration.as_secs_f64() * 1_000_000.0, TRACE_PROCESS_ID, thread, args,
+    )
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(in crate::ecs) struct SystemTiming {
+    pub(in crate::ecs) start: Instant,
+    pub(in crate::ecs) end: Instant,
+    pub(in crate::ecs) thread: usize,
+}
+
+// Collects samples for the frame in progress, which are added to FrameStats once the frame is done
+pub(in crate::ecs) struct FrameRecorder {
+    epoch: Instant,
+    start: Instant,
+    systems: Vec<SystemSample>,
+    flushes: Vec<FlushSample>,
+}
+
+impl FrameRecorder {
+    pub(in crate::ecs) fn start(stats: &FrameStats) -> Self {
+        Self {
+            epoch: stats.epoch,
+            start: Instant::now(),
+            systems: Vec::new(),
+            flushes: Vec::new(),
+        }
+    }
+
+    pub(in crate::ecs) fn record_system(&mut self, system: SystemInfo, timing: SystemTiming) {
+        self.systems.push(SystemSample {
+            system,
+            start: timing.start.saturating_duration_since(self.epoch),
+            duration: timing.end.saturating_duration_since(timing.start),
+            thread: timing.thread,
+        });
+    }
+
+    pub(in crate::ecs) fn record_flush(&mut self, start: Instant) {
+        self.flushes.push(FlushSample {
+            start: start.saturating_duration_since(self.epoch),
+            duration: start.elapsed(),
+        });
+    }
+
+    pub(in crate::ecs) fn finish(self, stats: &mut FrameStats) {
+        stats.push_frame(FrameSample {
+            start: self.start.saturating_duration_since(self.epoch),
+            duration: self.start.elapsed(),
+            systems: self.systems,
+            flushes: self.flushes,
+        });
+    }
+}
@@ -0,0 +1,338 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use crate::ecs::component::{Component, ComponentArray, ComponentManager};
+use crate::ecs::entity::Entity;
+
+pub trait QueryData {
+    type Item<'a>;
+    type State<'a>: Copy;
+
+    fn required_types(types: &mut Vec<TypeId>);
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_>;
+
+    fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>>;
+}
+
+impl QueryData for Entity {
+    type Item<'a> = Entity;
+    type State<'a> = ();
+
+    fn required_types(_types: &mut Vec<TypeId>) {}
+
+    fn prepare(_components: &ComponentManager) -> Self::State<'_> {}
+
+    fn fetch<'a>(_state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+        Some(*entity)
+    }
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'a> = RwLockReadGuard<'a, T>;
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+        state?.get_component(entity)
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'a> = RwLockWriteGuard<'a, T>;
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+        state?.get_mut_component(entity)
+    }
+}
+
+impl<T: Component> QueryData for Option<&T> {
+    type Item<'a> = Option<RwLockReadGuard<'a, T>>;
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(_types: &mut Vec<TypeId>) {}
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+        Some(state.and_then(|comp_arr| comp_arr.get_component(entity)))
+    }
+}
+
+impl<T: Component> QueryData for Option<&mut T> {
+    type Item<'a> = Option<RwLockWriteGuard<'a, T>>;
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(_types: &mut Vec<TypeId>) {}
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+        Some(state.and_then(|comp_arr| comp_arr.get_mut_component(entity)))
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type Item<'a> = ($($name::Item<'a>,)+);
+            type State<'a> = ($($name::State<'a>,)+);
+
+            fn required_types(types: &mut Vec<TypeId>) {
+                $($name::required_types(types);)+
+            }
+
+            fn prepare(components: &ComponentManager) -> Self::State<'_> {
+                ($($name::prepare(components),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch<'a>(state: Self::State<'a>, entity: &Entity) -> Option<Self::Item<'a>> {
+                let ($($name,)+) = state;
+
+                Some(($($name::fetch($name, entity)?,)+))
+            }
+        }
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+
+pub trait QueryFilter {
+    type State<'a>: Copy;
+
+    fn required_types(types: &mut Vec<TypeId>);
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_>;
+
+    fn matches(state: Self::State<'_>, entity: &Entity) -> bool;
+}
+
+pub struct With<T: Component>(PhantomData<T>);
+
+pub struct Without<T: Component>(PhantomData<T>);
+
+// Matches components added since the querying system last ran, or during the current frame outside of systems
+pub struct Added<T: Component>(PhantomData<T>);
+
+// Matches components added or mutably accessed since the querying system last ran, or during the current frame outside of systems
+pub struct Changed<T: Component>(PhantomData<T>);
+
+impl QueryFilter for () {
+    type State<'a> = ();
+
+    fn required_types(_types: &mut Vec<TypeId>) {}
+
+    fn prepare(_components: &ComponentManager) -> Self::State<'_> {}
+
+    fn matches(_state: Self::State<'_>, _entity: &Entity) -> bool {
+        true
+    }
+}
+
+impl<T: Component> QueryFilter for With<T> {
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn matches(state: Self::State<'_>, entity: &Entity) -> bool {
+        state.is_some_and(|comp_arr| comp_arr.has_component(entity))
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(_types: &mut Vec<TypeId>) {}
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn matches(state: Self::State<'_>, entity: &Entity) -> bool {
+        !state.is_some_and(|comp_arr| comp_arr.has_component(entity))
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn matches(state: Self::State<'_>, entity: &Entity) -> bool {
+        state.is_some_and(|comp_arr| comp_arr.is_added(entity))
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State<'a> = Option<&'a ComponentArray<T>>;
+
+    fn required_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn prepare(components: &ComponentManager) -> Self::State<'_> {
+        components.get_array::<T>()
+    }
+
+    fn matches(state: Self::State<'_>, entity: &Entity) -> bool {
+        state.is_some_and(|comp_arr| comp_arr.is_changed(entity))
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            type State<'a> = ($($name::State<'a>,)+);
+
+            fn required_types(types: &mut Vec<TypeId>) {
+                $($name::required_types(types);)+
+            }
+
+            fn prepare(components: &ComponentManager) -> Self::State<'_> {
+                ($($name::prepare(components),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn matches(state: Self::State<'_>, entity: &Entity) -> bool {
+                let ($($name,)+) = state;
+
+                $($name::matches($name, entity))&&+
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(A);
+impl_query_filter_tuple!(A, B);
+impl_query_filter_tuple!(A, B, C);
+impl_query_filter_tuple!(A, B, C, D);
+
+pub struct Query<'a, Q: QueryData, F: QueryFilter = ()> {
+    data_state: Q::State<'a>,
+    filter_state: F::State<'a>,
+    driver: &'a [Entity],
+}
+
+impl<'a, Q: QueryData, F: QueryFilter> Query<'a, Q, F> {
+    pub(in crate::ecs) fn new(components: &'a ComponentManager) -> Self {
+        let mut required_types = Vec::new();
+        Q::required_types(&mut required_types);
+        F::required_types(&mut required_types);
+
+        if required_types.is_empty() {
+            panic!("A query must require at least one component, either through &T, &mut T, or With<T>");
+        }
+
+        // Drive iteration from the smallest required component array, since every match must be in all of them anyway
+        let driver = required_types.iter()
+            .map(|type_id| components.get_entities_with(type_id))
+            .min_by_key(|entities| entities.len())
+            .unwrap_or_default();
+
+        Self {
+            data_state: Q::prepare(components),
+            filter_state: F::prepare(components),
+            driver,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Q::Item<'a>)> + '_ {
+        let (data_state, filter_state) = (self.data_state, self.filter_state);
+
+        self.driver.iter()
+            .filter(move |e| F::matches(filter_state, e))
+            .filter_map(move |e| Q::fetch(data_state, e).map(|item| (*e, item)))
+    }
+
+    pub fn iter_over<'b, I: Iterator<Item = &'b Entity> + 'b>(&self, entities: I) -> impl Iterator<Item = (Entity, Q::Item<'a>)> + 'b
+    where
+        'a: 'b,
+        Q: 'b,
+        F: 'b,
+    {
+        let (data_state, filter_state) = (self.data_state, self.filter_state);
+
+        entities
+            .filter(move |e| F::matches(filter_state, e))
+            .filter_map(move |e| Q::fetch(data_state, e).map(|item| (*e, item)))
+    }
+
+    pub fn get(&self, entity: &Entity) -> Option<Q::Item<'a>> {
+        if F::matches(self.filter_state, entity) {
+            Q::fetch(self.data_state, entity)
+        } else {
+            None
+        }
+    }
+
+    pub fn single(&self) -> Option<Q::Item<'a>> {
+        let mut matches = self.iter();
+
+        let (_, item) = matches.next()?;
+
+        if matches.next().is_some() {
+            return None;
+        }
+
+        Some(item)
+    }
+
+    pub fn single_entity(&self) -> Option<Entity> {
+        let mut matches = self.iter();
+
+        let (entity, _) = matches.next()?;
+
+        if matches.next().is_some() {
+            return None;
+        }
+
+        Some(entity)
+    }
+
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
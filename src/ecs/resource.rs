@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Result};
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+pub struct Resources {
+    // Each value is a RwLock<T> keyed by the TypeId of T
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+const DEFAULT_INITIAL_RESOURCE_CAPACITY: usize = 16;
+
+impl Resources {
+    pub(in crate::ecs) fn new() -> Self {
+        Self {
+            resources: HashMap::with_capacity(DEFAULT_INITIAL_RESOURCE_CAPACITY),
+        }
+    }
+
+    pub(in crate::ecs) fn insert<T: Send + Sync + 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(RwLock::new(resource)));
+    }
+
+    pub(in crate::ecs) fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.resources.remove(&TypeId::of::<T>())
+            .map(|r| r.downcast::<RwLock<T>>().unwrap_or_else(|_| panic!("Internal error: Failed to downcast resource")))
+            .map(|r| r.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.try_get().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.try_get_mut().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get<T: Send + Sync + 'static>(&self) -> Result<Option<RwLockReadGuard<'_, T>>> {
+        let resource = match self.get_lock::<T>() {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+
+        let resource = resource.try_read()
+            .map_err(|_| anyhow!("Resource {} is already mutably borrowed", type_name::<T>()))?;
+
+        Ok(Some(resource))
+    }
+
+    pub fn try_get_mut<T: Send + Sync + 'static>(&self) -> Result<Option<RwLockWriteGuard<'_, T>>> {
+        let resource = match self.get_lock::<T>() {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+
+        let resource = resource.try_write()
+            .map_err(|_| anyhow!("Resource {} is already borrowed", type_name::<T>()))?;
+
+        Ok(Some(resource))
+    }
+
+    fn get_lock<T: Send + Sync + 'static>(&self) -> Option<&RwLock<T>> {
+        self.resources.get(&TypeId::of::<T>())
+            .map(|r| r.downcast_ref::<RwLock<T>>().unwrap_or_else(|| panic!("Internal error: Failed to downcast resource")))
+    }
+}
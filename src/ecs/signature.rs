@@ -0,0 +1,71 @@
+use std::ops::{BitOr, BitOrAssign};
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+// Trailing zero words are always trimmed, so that equal sets of bits compare and hash equally regardless of how they were built
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(in crate::ecs) struct Signature {
+    words: Vec<u64>,
+}
+
+impl Signature {
+    pub(in crate::ecs) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(in crate::ecs) fn from_bit(bit: usize) -> Self {
+        let mut signature = Self::new();
+        signature.set(bit);
+        signature
+    }
+
+    pub(in crate::ecs) fn set(&mut self, bit: usize) {
+        let word = bit / BITS_PER_WORD;
+
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+
+        self.words[word] |= 1 << (bit % BITS_PER_WORD);
+    }
+
+    pub(in crate::ecs) fn remove_all(&mut self, other: &Signature) {
+        self.words.iter_mut().zip(other.words.iter()).for_each(|(w, o)| *w &= !o);
+
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+
+    pub(in crate::ecs) fn contains_all(&self, other: &Signature) -> bool {
+        other.words.len() <= self.words.len()
+            && self.words.iter().zip(other.words.iter()).all(|(w, o)| w & o == *o)
+    }
+
+    pub(in crate::ecs) fn intersects(&self, other: &Signature) -> bool {
+        self.words.iter().zip(other.words.iter()).any(|(w, o)| w & o != 0)
+    }
+
+    pub(in crate::ecs) fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl BitOrAssign<&Signature> for Signature {
+    fn bitor_assign(&mut self, rhs: &Signature) {
+        if rhs.words.len() > self.words.len() {
+            self.words.resize(rhs.words.len(), 0);
+        }
+
+        self.words.iter_mut().zip(rhs.words.iter()).for_each(|(w, o)| *w |= o);
+    }
+}
+
+impl BitOr<&Signature> for Signature {
+    type Output = Signature;
+
+    fn bitor(mut self, rhs: &Signature) -> Signature {
+        self |= rhs;
+        self
+    }
+}
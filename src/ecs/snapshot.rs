@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Result};
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Arc, RwLockReadGuard};
+
+use crate::ecs::component::{Component, ComponentManager};
+use crate::ecs::entity::Entity;
+use crate::ecs::prefab::PrefabComponent;
+use crate::ecs::resource::Resources;
+use crate::ecs::{ComponentActions, ECSCommands};
+
+const SNAPSHOT_HEADER: &str = "snapshot 1";
+const ENTITY_KEYWORD: &str = "entity";
+pub(in crate::ecs) const PREFAB_KEYWORD: &str = "prefab";
+const NONE_TOKEN: &str = "-";
+
+// Components opt in to snapshots by implementing this and being registered with ECSBuilder::with_serializable_component. Values are
+//  written as whitespace separated tokens, so a single value must never contain whitespace. Prefabs loaded from data deserialize
+//  each component once and spawn clones of it.
+pub trait SerializableComponent: Component + Clone {
+    fn serialize(&self, writer: &mut SnapshotWriter);
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self>;
+}
+
+type ComponentSaver = fn(&ComponentManager, &Entity, &mut SnapshotWriter) -> bool;
+type ComponentDeserializer = fn(&mut SnapshotReader) -> Result<Box<dyn ComponentActions + Send>>;
+pub(in crate::ecs) type PrefabComponentDeserializer = fn(&mut SnapshotReader) -> Result<Arc<dyn PrefabComponent>>;
+
+struct SerializableComponentType {
+    name: &'static str,
+    type_id: TypeId,
+    save: ComponentSaver,
+    deserialize: ComponentDeserializer,
+    deserialize_prefab: PrefabComponentDeserializer,
+}
+
+// Each parsed entity is a list of (component type index, values)
+type ParsedSnapshot<'a> = Vec<Vec<(usize, Vec<&'a str>)>>;
+
+pub(in crate::ecs) struct SnapshotRegistry {
+    component_types: Vec<SerializableComponentType>,
+}
+
+impl SnapshotRegistry {
+    pub(in crate::ecs) fn new() -> Self {
+        Self {
+            component_types: Vec::new(),
+        }
+    }
+
+    pub(in crate::ecs) fn register<T: SerializableComponent>(&mut self, name: &'static str) -> Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) || name == ENTITY_KEYWORD || name == PREFAB_KEYWORD {
+            return Err(anyhow!("{:?} is not a valid snapshot name for {}", name, type_name::<T>()));
+        }
+
+        if self.component_types.iter().any(|c| c.type_id == TypeId::of::<T>()) {
+            return Err(anyhow!("Component {} is already registered for snapshots", type_name::<T>()));
+        }
+
+        if self.component_types.iter().any(|c| c.name == name) {
+            return Err(anyhow!("The snapshot name {} is already in use", name));
+        }
+
+        self.component_types.push(SerializableComponentType {
+            name,
+            type_id: TypeId::of::<T>(),
+            save: |components, entity, writer| {
+                components.get_component::<T>(entity).map(|component| component.serialize(writer)).is_some()
+            },
+            deserialize: |reader| Ok(Box::new(T::deserialize(reader)?)),
+            deserialize_prefab: |reader| Ok(Arc::new(T::deserialize(reader)?)),
+        });
+
+        Ok(())
+    }
+
+    pub(in crate::ecs) fn get_prefab_deserializer(&self, name: &str) -> Option<(TypeId, PrefabComponentDeserializer)> {
+        self.component_types.iter().find(|c| c.name == name).map(|c| (c.type_id, c.deserialize_prefab))
+    }
+
+    // Only entities with at least one serializable component are saved, ordered by index so that the same world always saves the same way
+    pub(in crate::ecs) fn save(&self, components: &ComponentManager, resources: &Resources) -> String {
+        let mut entities: Vec<Entity> = self.component_types.iter()
+            .flat_map(|c| components.get_entities_with(&c.type_id).iter().copied())
+            .collect();
+
+        entities.sort_by_key(|e| e.index);
+        entities.dedup();
+
+        let snapshot_indexes: HashMap<Entity, usize> = entities.iter().enumerate().map(|(i, e)| (*e, i)).collect();
+
+        let mut snapshot = String::from(SNAPSHOT_HEADER);
+        snapshot.push('\n');
+
+        for (i, entity) in entities.iter().enumerate() {
+            snapshot.push_str(&format!("{} {}\n", ENTITY_KEYWORD, i));
+
+            for component_type in self.component_types.iter() {
+                let mut writer = SnapshotWriter::new(&snapshot_indexes, resources);
+
+                if (component_type.save)(components, entity, &mut writer) {
+                    snapshot.push_str("  ");
+                    snapshot.push_str(component_type.name);
+                    writer.tokens.iter().for_each(|token| {
+                        snapshot.push(' ');
+                        snapshot.push_str(token);
+                    });
+                    snapshot.push('\n');
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    // Validates the structure of the whole snapshot up front, so that nothing is created for a snapshot that's obviously broken
+    pub(in crate::ecs) fn parse<'a>(&self, snapshot: &'a str) -> Result<ParsedSnapshot<'a>> {
+        let mut lines = snapshot.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some(SNAPSHOT_HEADER) {
+            return Err(anyhow!("Snapshot must start with {:?}", SNAPSHOT_HEADER));
+        }
+
+        let mut parsed: ParsedSnapshot = Vec::new();
+
+        for line in lines {
+            let mut tokens = line.split_whitespace();
+            let name = tokens.next().unwrap_or_else(|| panic!("Internal error: expected a non-empty snapshot line"));
+
+            if name == ENTITY_KEYWORD {
+                let index = tokens.next().and_then(|t| t.parse::<usize>().ok());
+
+                if index != Some(parsed.len()) || tokens.next().is_some() {
+                    return Err(anyhow!("Expected \"{} {}\" but found {:?}", ENTITY_KEYWORD, parsed.len(), line));
+                }
+
+                parsed.push(Vec::new());
+                continue;
+            }
+
+            let component_type = self.component_types.iter().position(|c| c.name == name)
+                .ok_or_else(|| anyhow!("Snapshot component {} has not been registered", name))?;
+
+            parsed.last_mut()
+                .ok_or_else(|| anyhow!("Snapshot component {} doesn't belong to any entity", name))?
+                .push((component_type, tokens.collect()));
+        }
+
+        Ok(parsed)
+    }
+
+    pub(in crate::ecs) fn load(&self, parsed: &ParsedSnapshot, entities: &[Entity], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands) -> Result<()> {
+        for (entity, parsed_components) in entities.iter().zip(parsed.iter()) {
+            for (component_type, tokens) in parsed_components.iter() {
+                let component_type = &self.component_types[*component_type];
+                let mut reader = SnapshotReader::new(entities, tokens, components, resources);
+
+                let component = (component_type.deserialize)(&mut reader)
+                    .map_err(|e| anyhow!("Failed to load {} for snapshot entity {:?}: {}", component_type.name, entity, e))?;
+
+                commands.attach_boxed_component(entity, component_type.type_id, component);
+
+                if !reader.is_finished() {
+                    return Err(anyhow!("Too many values for {} for snapshot entity {:?}", component_type.name, entity));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct SnapshotWriter<'a> {
+    snapshot_indexes: &'a HashMap<Entity, usize>,
+    resources: &'a Resources,
+    tokens: Vec<String>,
+}
+
+impl<'a> SnapshotWriter<'a> {
+    fn new(snapshot_indexes: &'a HashMap<Entity, usize>, resources: &'a Resources) -> Self {
+        Self {
+            snapshot_indexes,
+            resources,
+            tokens: Vec::new(),
+        }
+    }
+
+    // For components which save a stable name in place of a value that's only meaningful while the game is running
+    pub fn get_resource<T: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'a, T>> {
+        self.resources.get::<T>()
+    }
+
+    pub fn write(&mut self, value: impl Display) {
+        self.tokens.push(value.to_string());
+    }
+
+    pub fn write_option(&mut self, value: Option<impl Display>) {
+        match value {
+            Some(value) => self.write(value),
+            None => self.write(NONE_TOKEN),
+        }
+    }
+
+    // Entities in the snapshot are written by their position in it and remapped on load. Any other entity (i.e. one without any
+    //  serializable components) is written as is, and can only be loaded while that entity is still alive.
+    pub fn write_entity(&mut self, entity: &Entity) {
+        match self.snapshot_indexes.get(entity) {
+            Some(i) => self.write(format!("@{}", i)),
+            None => self.write(format!("#{}:{}", entity.index, entity.generation)),
+        }
+    }
+
+    pub fn write_optional_entity(&mut self, entity: Option<&Entity>) {
+        match entity {
+            Some(entity) => self.write_entity(entity),
+            None => self.write(NONE_TOKEN),
+        }
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    loaded_entities: &'a [Entity],
+    tokens: &'a [&'a str],
+    next_token: usize,
+    components: &'a ComponentManager,
+    resources: &'a Resources,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub(in crate::ecs) fn new(loaded_entities: &'a [Entity], tokens: &'a [&'a str], components: &'a ComponentManager, resources: &'a Resources) -> Self {
+        Self {
+            loaded_entities,
+            tokens,
+            next_token: 0,
+            components,
+            resources,
+        }
+    }
+
+    pub fn get_resource<T: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'a, T>> {
+        self.resources.get::<T>()
+    }
+
+    pub fn read<T: FromStr>(&mut self) -> Result<T> {
+        let token = self.next()?;
+
+        token.parse().map_err(|_| anyhow!("Failed to parse {:?} as {}", token, type_name::<T>()))
+    }
+
+    pub fn read_option<T: FromStr>(&mut self) -> Result<Option<T>> {
+        if self.peek() == Some(NONE_TOKEN) {
+            self.next_token += 1;
+            return Ok(None);
+        }
+
+        self.read().map(Some)
+    }
+
+    pub fn read_entity(&mut self) -> Result<Entity> {
+        let token = self.next()?;
+
+        if let Some(i) = token.strip_prefix('@') {
+            let i: usize = i.parse().map_err(|_| anyhow!("Invalid snapshot entity reference {:?}", token))?;
+
+            return self.loaded_entities.get(i).copied()
+                .ok_or_else(|| anyhow!("Entity reference {:?} is outside of the snapshot", token));
+        }
+
+        let (index, generation) = token.strip_prefix('#').and_then(|t| t.split_once(':'))
+            .ok_or_else(|| anyhow!("Invalid entity reference {:?}", token))?;
+
+        let entity = Entity {
+            index: index.parse().map_err(|_| anyhow!("Invalid entity reference {:?}", token))?,
+            generation: generation.parse().map_err(|_| anyhow!("Invalid entity reference {:?}", token))?,
+        };
+
+        if !self.components.is_alive(&entity) {
+            return Err(anyhow!("Entity reference {:?} is outside of the snapshot and no longer exists", token));
+        }
+
+        Ok(entity)
+    }
+
+    pub fn read_optional_entity(&mut self) -> Result<Option<Entity>> {
+        if self.peek() == Some(NONE_TOKEN) {
+            self.next_token += 1;
+            return Ok(None);
+        }
+
+        self.read_entity().map(Some)
+    }
+
+    pub(in crate::ecs) fn is_finished(&self) -> bool {
+        self.next_token >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.next_token).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str> {
+        let token = self.peek().ok_or_else(|| anyhow!("Expected more values"))?;
+        self.next_token += 1;
+
+        Ok(token)
+    }
+}
@@ -1,47 +1,408 @@
+use anyhow::Result;
+use std::any::{type_name, TypeId};
+use std::cell::{Ref, RefCell};
 use std::collections::HashSet;
-use std::collections::hash_set::Iter;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
-use crate::ecs::{ECSCommands, Signature};
-use crate::ecs::component::ComponentManager;
+use crate::ecs::ECSCommands;
+use crate::ecs::error::EcsError;
+use crate::ecs::signature::Signature;
+use crate::ecs::component::{ComponentManager, SystemSignature};
 use crate::ecs::entity::Entity;
+use crate::ecs::inspect::SystemInfo;
+use crate::ecs::resource::Resources;
 
-pub type System = fn(entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands);
+pub trait System: Send + 'static {
+    // Receives one set of matching entities per signature the system was registered with, in registration order
+    fn run(&mut self, entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands);
+
+    fn name(&self) -> &str {
+        type_name::<Self>()
+    }
+
+    // Systems which don't declare their access are treated as exclusive, and never run alongside another system
+    fn access(&self) -> Option<&SystemAccess> {
+        None
+    }
+
+    // Names of systems in the same stage which this system must run before
+    fn runs_before(&self) -> &[String] {
+        &[]
+    }
+
+    // Names of systems in the same stage which this system must run after
+    fn runs_after(&self) -> &[String] {
+        &[]
+    }
+
+    fn named(self, name: &str) -> ConfiguredSystem<Self> where Self: Sized {
+        ConfiguredSystem::new(self).named(name)
+    }
+
+    fn with_access(self, access: SystemAccess) -> ConfiguredSystem<Self> where Self: Sized {
+        ConfiguredSystem::new(self).with_access(access)
+    }
+
+    fn before(self, name: &str) -> ConfiguredSystem<Self> where Self: Sized {
+        ConfiguredSystem::new(self).before(name)
+    }
+
+    fn after(self, name: &str) -> ConfiguredSystem<Self> where Self: Sized {
+        ConfiguredSystem::new(self).after(name)
+    }
+}
+
+impl<F> System for F
+where
+    F: FnMut(&[HashSet<Entity>], &ComponentManager, &Resources, &mut ECSCommands) + Send + 'static,
+{
+    fn run(&mut self, entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands) {
+        self(entities, components, resources, commands)
+    }
+}
+
+pub type SystemFn = fn(entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SystemStage {
+    PreUpdate,
+    Update,
+    Physics,
+    PostUpdate,
+    Render,
+}
+
+// Resource which drives the Physics stage at a fixed rate. Frame time is fed in with accumulate, and each frame the stage runs
+//  once per whole step accumulated, up to max_sub_steps. Time beyond that is dropped, so a slow frame can't snowball into
+//  ever more sub-steps
+pub struct FixedTimestep {
+    step: Duration,
+    max_sub_steps: u32,
+    accumulator: Duration,
+    alpha: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration, max_sub_steps: u32) -> Self {
+        Self {
+            step,
+            max_sub_steps,
+            accumulator: Duration::ZERO,
+            alpha: 0.0,
+        }
+    }
+
+    pub fn accumulate(&mut self, frame_time: Duration) {
+        self.accumulator += frame_time;
+    }
+
+    pub fn get_step(&self) -> Duration {
+        self.step
+    }
+
+    // How far the current frame is between the last fixed step and the next one, for interpolating rendered state
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub(in crate::ecs) fn take_steps(&mut self) -> u32 {
+        let mut steps = 0;
+
+        while self.accumulator >= self.step && steps < self.max_sub_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if self.accumulator >= self.step {
+            self.accumulator = Duration::ZERO;
+        }
+
+        self.alpha = self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+
+        steps
+    }
+}
+
+// The inherent methods shadow the provided ones on System, so chaining them configures a single wrapper
+pub struct ConfiguredSystem<S: System> {
+    system: S,
+    name: Option<String>,
+    access: Option<SystemAccess>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl<S: System> ConfiguredSystem<S> {
+    fn new(system: S) -> Self {
+        Self {
+            system,
+            name: None,
+            access: None,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+
+        self
+    }
+
+    pub fn with_access(mut self, access: SystemAccess) -> Self {
+        self.access = Some(access);
+
+        self
+    }
+
+    pub fn before(mut self, name: &str) -> Self {
+        self.before.push(String::from(name));
+
+        self
+    }
+
+    pub fn after(mut self, name: &str) -> Self {
+        self.after.push(String::from(name));
+
+        self
+    }
+}
+
+impl<S: System> System for ConfiguredSystem<S> {
+    fn run(&mut self, entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands) {
+        self.system.run(entities, components, resources, commands)
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_else(|| self.system.name())
+    }
+
+    fn access(&self) -> Option<&SystemAccess> {
+        self.access.as_ref().or_else(|| self.system.access())
+    }
+
+    fn runs_before(&self) -> &[String] {
+        &self.before
+    }
+
+    fn runs_after(&self) -> &[String] {
+        &self.after
+    }
+}
+
+// The components and resources a system borrows. Systems in the same batch don't see each other's commands until the batch is
+//  flushed, so systems whose commands could invalidate entities used by other systems should stay exclusive
+#[derive(Debug, Clone, Default)]
+pub struct SystemAccess {
+    reads: HashSet<TypeId>,
+    writes: HashSet<TypeId>,
+}
+
+impl SystemAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reads<T: 'static>(mut self) -> Self {
+        self.reads.insert(TypeId::of::<T>());
+
+        self
+    }
+
+    pub fn writes<T: 'static>(mut self) -> Self {
+        self.writes.insert(TypeId::of::<T>());
+
+        self
+    }
+
+    fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        self.writes.iter().any(|t| other.reads.contains(t) || other.writes.contains(t))
+            || other.writes.iter().any(|t| self.reads.contains(t))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId(usize);
+
+static SYSTEM_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl SystemId {
+    pub(in crate::ecs) fn next() -> Self {
+        Self(SYSTEM_ID_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
 
 pub(in crate::ecs) struct SystemManager {
-    pub(in crate::ecs) system: System,
-    system_signatures: HashSet<Signature>,
-    pub(in crate::ecs) precedence: i16,
-    entities: HashSet<Entity>,
+    pub(in crate::ecs) id: SystemId,
+    system: Box<dyn System>,
+    system_signatures: Vec<SystemSignature>,
+    pub(in crate::ecs) stage: SystemStage,
+    entities: Vec<HashSet<Entity>>,
+    last_run_tick: Option<u32>,
 }
 
 impl SystemManager {
-    pub(in crate::ecs) fn new(system: System, system_signatures: HashSet<Signature>, precedence: i16, initial_capacity: usize) -> Self {
+    pub(in crate::ecs) fn new(id: SystemId, system: Box<dyn System>, system_signatures: Vec<SystemSignature>, stage: SystemStage, initial_capacity: usize) -> Self {
         Self {
+            id,
             system,
+            entities: system_signatures.iter().map(|_| HashSet::with_capacity(initial_capacity)).collect(),
             system_signatures,
-            precedence,
-            entities: HashSet::with_capacity(initial_capacity),
+            stage,
+            last_run_tick: None,
         }
     }
 
-    pub(in crate::ecs) fn handle_entity_updated(&mut self, entity: &Entity, signature: Signature) {
-        if self.system_signatures.iter().any(|s| signatures_match(signature, *s)) {
-            self.entities.insert(entity.clone());
-        } else {
-            self.entities.remove(entity);
-        }
+    pub(in crate::ecs) fn get_name(&self) -> &str {
+        self.system.name()
+    }
+
+    pub(in crate::ecs) fn handle_entity_updated(&mut self, entity: &Entity, signature: &Signature) {
+        self.system_signatures.iter().zip(self.entities.iter_mut()).for_each(|(s, entities)| {
+            if s.matches(signature) {
+                entities.insert(*entity);
+            } else {
+                entities.remove(entity);
+            }
+        });
     }
 
     pub(in crate::ecs) fn handle_entity_removed(&mut self, entity: &Entity) {
-        self.entities.remove(entity);
+        self.entities.iter_mut().for_each(|entities| {
+            entities.remove(entity);
+        });
+    }
+
+    pub(in crate::ecs) fn handle_entities_removed(&mut self, removed: &HashSet<Entity>) {
+        self.entities.iter_mut().for_each(|entities| {
+            entities.retain(|e| !removed.contains(e));
+        });
+    }
+
+    pub(in crate::ecs) fn invoke_system(&mut self, components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands) {
+        let (system, entities) = (&mut self.system, &self.entities);
+        self.last_run_tick = Some(components.run_as_system(self.last_run_tick, || system.run(entities, components, resources, commands)));
+    }
+
+    pub(in crate::ecs) fn inspect(&self) -> SystemInfo {
+        SystemInfo {
+            id: self.id,
+            name: String::from(self.system.name()),
+            stage: self.stage,
+            entity_counts: self.entities.iter().map(|entities| entities.len()).collect(),
+        }
+    }
+}
+
+// Splits managers (sorted by stage) into batches of systems which can safely run at the same time. Batches never span stages.
+//  Within a stage, systems are ordered by their before/after constraints and then by registration order, and each system is
+//  placed in the first batch after every earlier system it conflicts with or must run after
+pub(in crate::ecs) fn build_schedule(managers: &[RefCell<SystemManager>]) -> Result<Vec<Vec<usize>>, EcsError> {
+    let managers: Vec<Ref<SystemManager>> = managers.iter().map(|m| m.borrow()).collect();
+    let names: Vec<&str> = managers.iter().map(|m| m.system.name()).collect();
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); managers.len()];
+
+    for (i, manager) in managers.iter().enumerate() {
+        check_constraint_names(manager.system.as_ref(), &names)?;
+
+        for name in manager.system.runs_after() {
+            for j in indexes_named(&managers, name) {
+                add_ordering(&managers, &mut predecessors, j, i)?;
+            }
+        }
+
+        for name in manager.system.runs_before() {
+            for j in indexes_named(&managers, name) {
+                add_ordering(&managers, &mut predecessors, i, j)?;
+            }
+        }
+    }
+
+    let mut schedule: Vec<Vec<usize>> = Vec::new();
+    let mut batch_indexes: Vec<usize> = vec![0; managers.len()];
+    let mut stage_start = 0;
+
+    while stage_start < managers.len() {
+        let stage = managers[stage_start].stage;
+        let stage_end = stage_start + managers[stage_start..].iter().take_while(|m| m.stage == stage).count();
+
+        let order = sort_stage(&managers, &predecessors, stage_start..stage_end)?;
+        let first_batch = schedule.len();
+
+        for (k, &i) in order.iter().enumerate() {
+            let batch_index = order[..k].iter()
+                .filter(|&&j| predecessors[i].contains(&j) || conflicts(&managers[j], &managers[i]))
+                .map(|&j| batch_indexes[j] + 1)
+                .max()
+                .unwrap_or(first_batch);
+
+            if batch_index == schedule.len() {
+                schedule.push(Vec::new());
+            }
+
+            schedule[batch_index].push(i);
+            batch_indexes[i] = batch_index;
+        }
+
+        stage_start = stage_end;
     }
 
-    pub(in crate::ecs) fn invoke_system(&self, components: &mut ComponentManager, commands: &mut ECSCommands) {
-        (self.system)(self.entities.iter(), components, commands);
+    Ok(schedule)
+}
+
+// Constraints can only name systems which are already registered, so that a misspelt name is an error rather than being ignored
+pub(in crate::ecs) fn check_constraint_names(system: &dyn System, registered_names: &[&str]) -> Result<(), EcsError> {
+    let unknown_name = system.runs_after().iter().chain(system.runs_before().iter())
+        .find(|name| !registered_names.contains(&name.as_str()));
+
+    match unknown_name {
+        Some(name) => Err(EcsError::InvalidSchedule(format!("System {} is ordered against {}, but no system with that name is registered", system.name(), name))),
+        None => Ok(()),
     }
 }
 
-#[inline]
-const fn signatures_match(entity_signature: Signature, system_signature: Signature) -> bool {
-    entity_signature & system_signature == system_signature
+fn indexes_named<'a>(managers: &'a [Ref<SystemManager>], name: &'a str) -> impl Iterator<Item = usize> + 'a {
+    managers.iter().enumerate()
+        .filter(move |(_, m)| m.system.name() == name)
+        .map(|(i, _)| i)
+}
+
+fn add_ordering(managers: &[Ref<SystemManager>], predecessors: &mut [Vec<usize>], first: usize, then: usize) -> Result<(), EcsError> {
+    let (first_stage, then_stage) = (managers[first].stage, managers[then].stage);
+
+    if first_stage == then_stage {
+        predecessors[then].push(first);
+    } else if first_stage > then_stage {
+        return Err(EcsError::InvalidSchedule(format!("System {} must run before {}, but its stage {:?} comes after {:?}", managers[first].system.name(), managers[then].system.name(), first_stage, then_stage)));
+    }
+
+    Ok(())
+}
+
+fn sort_stage(managers: &[Ref<SystemManager>], predecessors: &[Vec<usize>], stage: Range<usize>) -> Result<Vec<usize>, EcsError> {
+    let mut remaining: Vec<usize> = stage.collect();
+    let mut order: Vec<usize> = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        // Always take the earliest registered system that's ready, so unconstrained systems keep their registration order
+        let next = remaining.iter()
+            .position(|&i| predecessors[i].iter().all(|p| order.contains(p)))
+            .ok_or_else(|| {
+                let names: Vec<&str> = remaining.iter().map(|&i| managers[i].system.name()).collect();
+                EcsError::InvalidSchedule(format!("Ordering constraints between systems [{}] form a cycle", names.join(", ")))
+            })?;
+
+        order.push(remaining.remove(next));
+    }
+
+    Ok(order)
+}
+
+fn conflicts(a: &SystemManager, b: &SystemManager) -> bool {
+    match (a.system.access(), b.system.access()) {
+        (Some(a), Some(b)) => a.conflicts_with(b),
+        _ => true,
+    }
 }
@@ -1,22 +1,30 @@
 use anyhow::Result;
 use ecs::ComponentActions;
-use math::{get_proj_matrix, vec2, vec3, Quat, Vec2, Vec3, QUAT_IDENTITY, VEC_2_ZERO, VEC_3_Y_AXIS, VEC_3_ZERO, VEC_3_Z_AXIS};
+use math::{get_proj_matrix, vec2, vec3, Mat4, Quat, Vec2, Vec3, MAT_4_IDENTITY, QUAT_IDENTITY, VEC_2_ZERO, VEC_3_Y_AXIS, VEC_3_ZERO, VEC_3_Z_AXIS};
 use physics::{generate_ray, get_ray_intersection, PotentialRigidBodyCollision};
 use render_engine::GuiState;
 use core::mesh::create_quad_mesh;
 use core::{TextureBinding, IDENTITY_SCALE_VEC, WHITE};
 use std::cmp::Ordering;
-use std::collections::hash_set::Iter;
 use std::collections::HashSet;
 use std::f32;
 use rand::Rng;
 use std::time::Duration;
 
-use crate::core::{Camera, Color, ColorMaterial, TimeDelta, Timer, Transform, Viewport2D};
-use crate::core::mesh::{create_cube_mesh, create_plane_mesh, Mesh, MeshBinding};
-use crate::ecs::component::{Component, ComponentManager};
+use crate::core::{Camera, Color, ColorMaterial, GlobalTransform, RenderAssets, RenderTextureId, TimeDelta, Timer, Transform, Viewport2D};
+use crate::core::mesh::{create_cube_mesh, create_plane_mesh, Mesh, MeshBinding, RenderMeshId};
+use crate::ecs::app::{App, Clock};
+use crate::ecs::component::{Component, ComponentEventKind, ComponentManager};
 use crate::ecs::entity::Entity;
-use crate::ecs::system::System;
+use crate::ecs::error::ErrorPolicy;
+use crate::ecs::event::{EventReader, Events};
+use crate::ecs::hierarchy::{Children, Parent};
+use crate::ecs::prefab::Prefab;
+use crate::ecs::profile::FrameStats;
+use crate::ecs::query::{Changed, With, Without};
+use crate::ecs::resource::Resources;
+use crate::ecs::snapshot::{SerializableComponent, SnapshotReader, SnapshotWriter};
+use crate::ecs::system::{FixedTimestep, System, SystemAccess, SystemFn, SystemStage};
 use crate::ecs::{ECSBuilder, ECSCommands, ECS};
 use crate::maze::create_maze_vector;
 use crate::physics::{apply_ang_vel, get_deepest_rigid_body_collision, get_edge_collision, get_point_collision, BoundingSphere, Particle, ParticleCable, ParticleRod, ParticleCollision, ParticleCollisionDetector, PhysicsMeshProperties, QuadTree, RigidBody, RigidBodyCollision};
@@ -35,45 +43,57 @@ const FAR_PLANE: f32 = 1000.0;
 
 const CUBE_SIZE: f32 = 10.0;
 
+// When enabled, timings for the last PROFILED_FRAME_COUNT frames are written to TRACE_FILE on exit in Chrome's trace format
+const PROFILE_SYSTEMS: bool = false;
+const PROFILED_FRAME_COUNT: usize = 600;
+const TRACE_FILE: &str = "trace.json";
+
 fn main() {
     pretty_env_logger::init();
 
     let mut ecs = init_ecs();
+
+    if PROFILE_SYSTEMS {
+        ecs.insert_resource(FrameStats::new(PROFILED_FRAME_COUNT));
+    }
+
     create_scene(&mut ecs);
 
-    while ecs.invoke_systems() {}
+    let mut app = App::new(ecs);
+    app.run();
+
+    if PROFILE_SYSTEMS {
+        app.get_ecs().get_resource::<FrameStats>().unwrap().write_chrome_trace(TRACE_FILE).unwrap();
+    }
 }
 
 fn init_ecs() -> ECS {
     ECSBuilder::with_initial_entity_capacity(1_024)
+        .with_error_policy(ErrorPolicy::Log)
         .with_component::<Viewport2D>()
-        .with_component::<Transform>()
-        .with_component::<Mesh>()
-        .with_component::<MeshBinding>()
-        .with_component::<TextureBinding>()
+        .with_serializable_component::<Transform>("Transform")
+        .with_component::<GlobalTransform>()
+        .with_debug_output::<Transform>()
+        .with_debug_output::<GlobalTransform>()
+        .with_serializable_component::<Mesh>("Mesh")
+        .with_serializable_component::<MeshBinding>("MeshBinding")
+        .with_serializable_component::<TextureBinding>("TextureBinding")
         .with_component::<ColorMaterial>()
-        .with_component::<VulkanRenderEngine>()
-        .with_component::<TimeDelta>()
         .with_component::<Particle>()
         .with_component::<ParticleCable>()
         .with_component::<ParticleRod>()
-        .with_component::<ParticleCollision>()
         .with_component::<ParticleCollisionDetector>()
         .with_component::<PhysicsMeshProperties>()
-        .with_component::<PotentialRigidBodyCollision>()
         .with_component::<RigidBodyCollision>()
-        .with_component::<QuadTree<BoundingSphere>>()
-        .with_component::<RigidBody>()
-        .with_component::<Timer>()
+        .with_serializable_component::<RigidBody>("RigidBody")
+        .with_serializable_component::<Timer>("Timer")
         .with_component::<CubeMeshOwner>()
         .with_component::<PlaneMeshOwner>()
         .with_component::<QuadMeshOwner>()
         .with_component::<MousePickable>()
-        .with_component::<CursorManager>()
-        .with_component::<Player>()
-        .with_component::<LevelLoader>()
-        .with_component::<LevelEntity>()
-        .with_component::<Baddie>()
+        .with_serializable_component::<Player>("Player")
+        .with_serializable_component::<LevelEntity>("LevelEntity")
+        .with_serializable_component::<Baddie>("Baddie")
         .with_component::<DeadBaddie>()
         .with_component::<Wall>()
         .with_component::<BaddieTextureOwner>()
@@ -82,9 +102,11 @@ fn init_ecs() -> ECS {
         .with_component::<SpriteAnimation>()
         .with_component::<GunAnimationTimer>()
         .with_component::<GunReloadTimer>()
-        .with_component::<DigitsTextureOwner>()
         .with_component::<Ladder>()
         .with_component::<LadderTextureOwner>()
+        .with_event::<LoadLevel>()
+        .with_event::<ParticleCollision>()
+        .with_event::<PotentialRigidBodyCollision>()
         .build()
 }
 
@@ -107,57 +129,65 @@ fn init_render_engine() -> Result<VulkanRenderEngine> {
 
 fn create_scene(ecs: &mut ECS) {
     let mut render_engine = init_render_engine().unwrap_or_else(|e| panic!("{}", e));
+    let render_assets = populate_scene(ecs, Some(&mut render_engine));
+    ecs.insert_resource(render_engine);
+    ecs.insert_resource(render_assets);
+
+    register_systems(ecs);
+    register_render_systems(ecs);
+}
+
+// Nothing is created with a render engine, so meshes and textures are left without render ids and only systems which don't need a
+//  window are registered, e.g. so that gameplay can be run under App in tests
+fn create_headless_scene(ecs: &mut ECS) {
+    populate_scene(ecs, None);
+
+    register_systems(ecs);
+}
+
+// Returns the names of the meshes and textures which were created with the render engine
+fn populate_scene(ecs: &mut ECS, mut render_engine: Option<&mut VulkanRenderEngine>) -> RenderAssets {
+    let mut render_assets = RenderAssets::default();
 
     let cube_mesh: Mesh = create_cube_mesh();
-    let cube_mesh_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_mesh(cube_mesh.vertices.clone(), cube_mesh.vertex_indices.clone()))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let cube_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/wall.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let cube_mesh_id = create_render_mesh(render_engine.as_deref_mut(), &mut render_assets, "cube", &cube_mesh);
+    let cube_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/wall.png");
     let cube_mesh_entity = ecs.create_entity();
-    let cube_mesh_binding = MeshBinding::new_provisional(Some(cube_mesh_id), Some(cube_mesh_entity));
-    let cube_texture_binding = TextureBinding::new_provisional(Some(cube_texture_id), Some(cube_mesh_entity));
+    let cube_mesh_binding = MeshBinding::new_provisional(cube_mesh_id, Some(cube_mesh_entity));
+    let cube_texture_binding = TextureBinding::new_provisional(cube_texture_id, Some(cube_mesh_entity));
     ecs.attach_provisional_component(&cube_mesh_entity, cube_mesh);
     ecs.attach_provisional_component(&cube_mesh_entity, cube_mesh_binding);
     ecs.attach_provisional_component(&cube_mesh_entity, cube_texture_binding);
     ecs.attach_provisional_component(&cube_mesh_entity, CubeMeshOwner {});
 
     let plane_mesh: Mesh = create_plane_mesh();
-    let plane_mesh_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_mesh(plane_mesh.vertices.clone(), plane_mesh.vertex_indices.clone()))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let plane_mesh_id = create_render_mesh(render_engine.as_deref_mut(), &mut render_assets, "plane", &plane_mesh);
     let plane_mesh_entity = ecs.create_entity();
-    let plane_mesh_binding = MeshBinding::new_provisional(Some(plane_mesh_id), Some(plane_mesh_entity));
+    let plane_mesh_binding = MeshBinding::new_provisional(plane_mesh_id, Some(plane_mesh_entity));
     ecs.attach_provisional_component(&plane_mesh_entity, plane_mesh);
     ecs.attach_provisional_component(&plane_mesh_entity, plane_mesh_binding);
     ecs.attach_provisional_component(&cube_mesh_entity, PlaneMeshOwner {});
 
     let quad_mesh: Mesh = create_quad_mesh();
-    let quad_mesh_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_mesh(quad_mesh.vertices.clone(), quad_mesh.vertex_indices.clone()))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let quad_mesh_id = create_render_mesh(render_engine.as_deref_mut(), &mut render_assets, "quad", &quad_mesh);
     let quad_mesh_entity = ecs.create_entity();
-    let quad_mesh_binding = MeshBinding::new_provisional(Some(quad_mesh_id), Some(quad_mesh_entity));
+    let quad_mesh_binding = MeshBinding::new_provisional(quad_mesh_id, Some(quad_mesh_entity));
     ecs.attach_provisional_component(&quad_mesh_entity, quad_mesh);
     ecs.attach_provisional_component(&quad_mesh_entity, quad_mesh_binding);
     ecs.attach_provisional_component(&quad_mesh_entity, QuadMeshOwner {});
 
     let baddie_texture_entity = ecs.create_entity();
-    let baddie_animation = create_baddie_sprite_animation(&mut render_engine);
+    let baddie_animation = create_baddie_sprite_animation(render_engine.as_deref_mut(), &mut render_assets);
     ecs.attach_provisional_component(&baddie_texture_entity, baddie_animation.frames[0]);
     ecs.attach_provisional_component(&baddie_texture_entity, BaddieTextureOwner {});
-    ecs.attach_provisional_component(&baddie_texture_entity, baddie_animation);
+    ecs.attach_provisional_component(&baddie_texture_entity, baddie_animation.clone());
 
-    let digits_texture_owner = create_digits_texture_owner(&mut render_engine);
-    let digits_texture_owner_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&digits_texture_owner_entity, digits_texture_owner);
+    let digits_texture_owner = create_digits_texture_owner(render_engine.as_deref_mut(), &mut render_assets);
+    ecs.insert_resource(digits_texture_owner);
 
-    let ladder_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/ladder.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let ladder_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/ladder.png");
     let ladder_texture_entity = ecs.create_entity();
-    let ladder_texture_binding = TextureBinding::new_provisional(Some(ladder_texture_id), Some(ladder_texture_entity));
+    let ladder_texture_binding = TextureBinding::new_provisional(ladder_texture_id, Some(ladder_texture_entity));
     ecs.attach_provisional_component(&ladder_texture_entity, ladder_texture_binding);
     ecs.attach_provisional_component(&ladder_texture_entity, quad_mesh_binding.clone());
     ecs.attach_provisional_component(&ladder_texture_entity, LadderTextureOwner {});
@@ -167,22 +197,20 @@ fn create_scene(ecs: &mut ECS) {
     ////////////////////
 
     // Crosshair
-    let crosshair_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/crosshair.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let crosshair_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/crosshair.png");
     let crosshair_element = GuiElement {
         id: String::from("crosshair"),
         position: vec2(0.0, 0.0),
         dimensions: vec2(1.0, 1.0),
     };
     let crosshair_entity = ecs.create_entity();
-    let crosshair_texture_binding = TextureBinding::new_provisional(Some(crosshair_texture_id), Some(crosshair_entity));
+    let crosshair_texture_binding = TextureBinding::new_provisional(crosshair_texture_id, Some(crosshair_entity));
     ecs.attach_provisional_component(&crosshair_entity, crosshair_element);
     ecs.attach_provisional_component(&crosshair_entity, crosshair_texture_binding);
 
     // Gun
     let gun_texture_entity = ecs.create_entity();
-    let gun_animation = create_gun_sprite_animation(&mut render_engine);
+    let gun_animation = create_gun_sprite_animation(render_engine.as_deref_mut(), &mut render_assets);
     ecs.attach_provisional_component(&gun_texture_entity, gun_animation.frames[0]);
     ecs.attach_provisional_component(&gun_texture_entity, GunTextureOwner {});
     ecs.attach_provisional_component(&gun_texture_entity, gun_animation);
@@ -192,20 +220,16 @@ fn create_scene(ecs: &mut ECS) {
     ecs.attach_provisional_component(&ammo_0_entity, GuiElement { id: String::from("ammo_counter_0"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
     let ammo_1_entity = ecs.create_entity();
     ecs.attach_provisional_component(&ammo_1_entity, GuiElement { id: String::from("ammo_counter_1"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
-    let ammo_label_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/bullet.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let ammo_label_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/bullet.png");
     let ammo_label_entity = ecs.create_entity();
-    let ammo_label_texture_binding = TextureBinding::new_provisional(Some(ammo_label_texture_id), Some(ammo_label_entity));
+    let ammo_label_texture_binding = TextureBinding::new_provisional(ammo_label_texture_id, Some(ammo_label_entity));
     ecs.attach_provisional_component(&ammo_label_entity, GuiElement { id: String::from("ammo_label"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
     ecs.attach_provisional_component(&ammo_label_entity, ammo_label_texture_binding);
 
     // Health
-    let health_label_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/heart.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let health_label_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/heart.png");
     let health_label_entity = ecs.create_entity();
-    let health_label_texture_binding = TextureBinding::new_provisional(Some(health_label_texture_id), Some(health_label_entity));
+    let health_label_texture_binding = TextureBinding::new_provisional(health_label_texture_id, Some(health_label_entity));
     ecs.attach_provisional_component(&health_label_entity, GuiElement { id: String::from("health_label"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
     ecs.attach_provisional_component(&health_label_entity, health_label_texture_binding);
     let health_0_entity = ecs.create_entity();
@@ -216,11 +240,9 @@ fn create_scene(ecs: &mut ECS) {
     ecs.attach_provisional_component(&health_2_entity, GuiElement { id: String::from("health_counter_2"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
 
     // Level
-    let level_label_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/level.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
+    let level_label_texture_id = create_render_texture(render_engine.as_deref_mut(), &mut render_assets, "res/level.png");
     let level_label_entity = ecs.create_entity();
-    let level_label_texture_binding = TextureBinding::new_provisional(Some(level_label_texture_id), Some(level_label_entity));
+    let level_label_texture_binding = TextureBinding::new_provisional(level_label_texture_id, Some(level_label_entity));
     ecs.attach_provisional_component(&level_label_entity, GuiElement { id: String::from("level_label"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
     ecs.attach_provisional_component(&level_label_entity, level_label_texture_binding);
     let level_0_entity = ecs.create_entity();
@@ -228,90 +250,116 @@ fn create_scene(ecs: &mut ECS) {
     let level_1_entity = ecs.create_entity();
     ecs.attach_provisional_component(&level_1_entity, GuiElement { id: String::from("level_counter_1"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
 
-    let vulkan_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&vulkan_entity, render_engine);
+    ecs.insert_resource(TimeDelta::default());
 
-    let time_delta = TimeDelta::default();
-    let time_delta_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&time_delta_entity, time_delta);
+    const PHYSICS_STEP_SECS: f32 = 1.0 / 60.0;
+    const MAX_PHYSICS_SUB_STEPS: u32 = 5;
+    ecs.insert_resource(FixedTimestep::new(Duration::from_secs_f32(PHYSICS_STEP_SECS), MAX_PHYSICS_SUB_STEPS));
 
     let particle_collision_detector = ParticleCollisionDetector::new(0.1);
     let particle_collision_detector_entity = ecs.create_entity();
     ecs.attach_provisional_component(&particle_collision_detector_entity, particle_collision_detector);
 
     let quad_tree: QuadTree<BoundingSphere> = QuadTree::new(VEC_3_ZERO, 125.0, 512, 10, 96, 16).unwrap();
-    let quad_tree_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&quad_tree_entity, quad_tree);
-
-    let cursor_manager = CursorManager { is_locked: false, just_locked: false, cursor_delta: VEC_2_ZERO };
-    let cursor_manager_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&cursor_manager_entity, cursor_manager);
-
-    let level_loader = LevelLoader { should_load: true, next_level_id: 0 };
-    let level_loader_entity = ecs.create_entity();
-    ecs.attach_provisional_component(&level_loader_entity, level_loader);
-
-    ecs.register_system(SHUTDOWN_ECS, HashSet::from([ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap()]), -999);
-    ecs.register_system(TIME_SINCE_LAST_FRAME, HashSet::from([ecs.get_system_signature_1::<TimeDelta>().unwrap()]), -500);
-    ecs.register_system(LOAD_LEVEL, HashSet::from([ecs.get_system_signature_1::<LevelLoader>().unwrap(), ecs.get_system_signature_1::<LevelEntity>().unwrap(), ecs.get_system_signature_1::<CubeMeshOwner>().unwrap(), ecs.get_system_signature_1::<PlaneMeshOwner>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap(), ecs.get_system_signature_1::<GunTextureOwner>().unwrap(), ecs.get_system_signature_1::<LadderTextureOwner>().unwrap()]), -400);
-    ecs.register_system(MANAGE_CURSOR, HashSet::from([ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap(), ecs.get_system_signature_1::<CursorManager>().unwrap()]), -400);
-    ecs.register_system(UPDATE_SPRITE_ANIMATIONS, HashSet::from([ecs.get_system_signature_2::<SpriteAnimation, Timer>().unwrap(), ecs.get_system_signature_1::<GunReloadTimer>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap()]), -400);
-    ecs.register_system(SPAWN_BADDIES, HashSet::from([ecs.get_system_signature_1::<QuadMeshOwner>().unwrap(), ecs.get_system_signature_1::<BaddieTextureOwner>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap(), ecs.get_system_signature_2::<Timer, Player>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_2::<Wall, Transform>().unwrap(), ecs.get_system_signature_1::<Baddie>().unwrap()]), -400);
-    ecs.register_system(MOVE_CAMERA, HashSet::from([ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap(), ecs.get_system_signature_1::<CursorManager>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap()]), -400);
-    ecs.register_system(APPLY_PLAYER_WALL_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_1::<Wall>().unwrap()]), -400);
-    ecs.register_system(UPDATE_BADDIE_IS_ACTIVE, HashSet::from([ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_1::<Baddie>().unwrap(), ecs.get_system_signature_2::<Wall, Transform>().unwrap()]), -400);
-    ecs.register_system(MOVE_BADDIE, HashSet::from([ecs.get_system_signature_1::<Baddie>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap()]), -400);
-    ecs.register_system(UPDATE_LADDER, HashSet::from([ecs.get_system_signature_1::<Ladder>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap()]), -400);
-    ecs.register_system(UPDATE_DEAD_BADDIES, HashSet::from([ecs.get_system_signature_1::<DeadBaddie>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap()]), -400);
-    ecs.register_system(DAMAGE_PLAYER, HashSet::from([ecs.get_system_signature_1::<Baddie>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_1::<LevelLoader>().unwrap()]), -400);
-    ecs.register_system(SHOOT_BADDIES, HashSet::from([ecs.get_system_signature_1::<Baddie>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_2::<Wall, Transform>().unwrap(), ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap(), ecs.get_system_signature_1::<CursorManager>().unwrap(), ecs.get_system_signature_1::<BaddieTextureOwner>().unwrap(), ecs.get_system_signature_1::<Player>().unwrap(), ecs.get_system_signature_1::<GunAnimationTimer>().unwrap(), ecs.get_system_signature_1::<GunReloadTimer>().unwrap()]), -400);
-    ecs.register_system(DESPAWN_BADDIES, HashSet::from([ecs.get_system_signature_1::<Baddie>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap()]), -400);
-    ecs.register_system(UPDATE_PARTICLES, HashSet::from([ecs.get_system_signature_2::<Transform, Particle>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap()]), -200);
-    ecs.register_system(UPDATE_RIGID_BODIES, HashSet::from([ecs.get_system_signature_2::<Transform, RigidBody>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap()]), -200);
-    ecs.register_system(UPDATE_QUAD_TREE, HashSet::from([ecs.get_system_signature_1::<QuadTree<BoundingSphere>>().unwrap(), ecs.get_system_signature_2::<Transform, RigidBody>().unwrap()]), -150);
-    ecs.register_system(DETECT_PARTICLE_CABLE_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<ParticleCable>().unwrap()]), -100);
-    ecs.register_system(DETECT_PARTICLE_ROD_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<ParticleRod>().unwrap()]), -100);
-    ecs.register_system(DETECT_POTENTIAL_RIGID_BODY_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<QuadTree<BoundingSphere>>().unwrap()]), -100);
-    ecs.register_system(DETECT_RIGID_BODY_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<PotentialRigidBodyCollision>().unwrap(), ecs.get_system_signature_1::<RigidBodyCollision>().unwrap()]), -99);
-    ecs.register_system(RESOLVE_PARTICLE_COLLISIONS, HashSet::from([ecs.get_system_signature_1::<TimeDelta>().unwrap(), ecs.get_system_signature_1::<ParticleCollision>().unwrap()]), -50);
-    ecs.register_system(DETECT_LOAD_NEXT_LEVEL, HashSet::from([ecs.get_system_signature_1::<LevelLoader>().unwrap(), ecs.get_system_signature_1::<Viewport2D>().unwrap(), ecs.get_system_signature_1::<Ladder>().unwrap()]), -50);
-    ecs.register_system(UPDATE_GUI_ELEMENTS, HashSet::from([ecs.get_system_signature_1::<GuiElement>().unwrap(), ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap(), ecs.get_system_signature_1::<GunReloadTimer>().unwrap(), ecs.get_system_signature_1::<GunAnimationTimer>().unwrap(), ecs.get_system_signature_1::<DigitsTextureOwner>().unwrap(), ecs.get_system_signature_1::<GunTextureOwner>().unwrap()]), 2);
-    ecs.register_system(SYNC_RENDER_STATE, HashSet::from([ecs.get_system_signature_0().unwrap()]), 2);
-    ecs.register_system(RESET_TRANSFORM_FLAGS, HashSet::from([ecs.get_system_signature_1::<Transform>().unwrap()]), 3);
-    ecs.register_system(UPDATE_TIMERS, HashSet::from([ecs.get_system_signature_1::<Timer>().unwrap(), ecs.get_system_signature_1::<TimeDelta>().unwrap()]), 5);
-    ecs.register_system(SHUTDOWN_RENDER_ENGINE, HashSet::from([ecs.get_system_signature_1::<VulkanRenderEngine>().unwrap()]), 999);
+    ecs.insert_resource(quad_tree);
+
+    ecs.insert_resource(CursorManager { is_locked: false, just_locked: false, cursor_delta: VEC_2_ZERO });
+
+    const BADDIE_ANIMATION_SPEED: f32 = 0.15;
+
+    // Blocks make up the floor, ceiling and walls of each level, and each spawned entity gets its own Transform
+    ecs.insert_resource(LevelPrefabs {
+        block: Prefab::new("Block")
+            .with(cube_mesh_binding)
+            .with(cube_texture_binding)
+            .with(LevelEntity {}),
+        baddie: Prefab::new("Baddie")
+            .with(quad_mesh_binding)
+            .with(baddie_animation.frames[0])
+            .with(baddie_animation)
+            .with(Baddie { is_active: false })
+            .with(LevelEntity {})
+            .with(Timer::new(0.0, 1.0, Duration::from_secs_f32(BADDIE_ANIMATION_SPEED))),
+    });
+
+    ecs.insert_resource(LevelLoader { next_level_id: 0 });
+    ecs.send_event(LoadLevel {});
+
+    render_assets
 }
 
-fn create_baddie_sprite_animation(render_engine: &mut VulkanRenderEngine) -> SpriteAnimation {
-    let baddie_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_2 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_2.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_3 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_3.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_4 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_4.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_5 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_5.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_6 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_6.png")))
+fn register_systems(ecs: &mut ECS) {
+    ecs.register_system(SHUTDOWN_ECS.named("SHUTDOWN_ECS"), vec![], SystemStage::PreUpdate);
+    ecs.register_system(TIME_SINCE_LAST_FRAME.named("TIME_SINCE_LAST_FRAME").after("SHUTDOWN_ECS"), vec![], SystemStage::PreUpdate);
+    ecs.register_system(load_level().named("LOAD_LEVEL"), vec![], SystemStage::Update);
+    ecs.register_system(MANAGE_CURSOR.named("MANAGE_CURSOR").with_access(SystemAccess::new().writes::<VulkanRenderEngine>().writes::<CursorManager>()), vec![], SystemStage::Update);
+    ecs.register_system(UPDATE_SPRITE_ANIMATIONS.named("UPDATE_SPRITE_ANIMATIONS"), vec![ecs.get_system_signature_2::<SpriteAnimation, Timer>().unwrap()], SystemStage::Update);
+    ecs.register_system(SPAWN_BADDIES.named("SPAWN_BADDIES"), vec![], SystemStage::Update);
+    ecs.register_system(MOVE_CAMERA.named("MOVE_CAMERA").with_access(SystemAccess::new().reads::<VulkanRenderEngine>().reads::<TimeDelta>().reads::<CursorManager>().writes::<Viewport2D>().writes::<Player>()), vec![], SystemStage::Update);
+    ecs.register_system(APPLY_PLAYER_WALL_COLLISIONS.named("APPLY_PLAYER_WALL_COLLISIONS").with_access(SystemAccess::new().reads::<Wall>().reads::<Transform>().writes::<Viewport2D>()), vec![ecs.get_system_signature_2::<Wall, Transform>().unwrap()], SystemStage::Update);
+    ecs.register_system(UPDATE_BADDIE_IS_ACTIVE.named("UPDATE_BADDIE_IS_ACTIVE").with_access(SystemAccess::new().reads::<Viewport2D>().reads::<MeshBinding>().reads::<Mesh>().reads::<Transform>().writes::<Baddie>().writes::<Timer>()), vec![ecs.get_system_signature_3::<Baddie, Transform, Timer>().unwrap().without(ecs.get_system_signature_1::<DeadBaddie>().unwrap())], SystemStage::Update);
+    ecs.register_system(MOVE_BADDIE.named("MOVE_BADDIE").with_access(SystemAccess::new().reads::<Viewport2D>().reads::<TimeDelta>().reads::<Baddie>().writes::<Transform>()), vec![ecs.get_system_signature_2::<Baddie, Transform>().unwrap()], SystemStage::Update);
+    ecs.register_system(UPDATE_LADDER.named("UPDATE_LADDER").with_access(SystemAccess::new().reads::<Viewport2D>().writes::<Transform>()), vec![ecs.get_system_signature_2::<Ladder, Transform>().unwrap()], SystemStage::Update);
+    ecs.register_system(UPDATE_DEAD_BADDIES.named("UPDATE_DEAD_BADDIES"), vec![ecs.get_system_signature_3::<DeadBaddie, Transform, Timer>().unwrap()], SystemStage::Update);
+    ecs.register_system(DAMAGE_PLAYER.named("DAMAGE_PLAYER"), vec![ecs.get_system_signature_2::<Baddie, Transform>().unwrap()], SystemStage::Update);
+    ecs.register_system(SHOOT_BADDIES.named("SHOOT_BADDIES"), vec![ecs.get_system_signature_2::<MeshBinding, Transform>().unwrap().with_any(ecs.get_system_signature_2::<Baddie, Wall>().unwrap())], SystemStage::Update);
+    ecs.register_system(DESPAWN_BADDIES.named("DESPAWN_BADDIES"), vec![ecs.get_system_signature_2::<Baddie, Transform>().unwrap()], SystemStage::Update);
+    ecs.register_system(UPDATE_PARTICLES.named("UPDATE_PARTICLES"), vec![ecs.get_system_signature_2::<Transform, Particle>().unwrap()], SystemStage::Physics);
+    ecs.register_system(UPDATE_RIGID_BODIES.named("UPDATE_RIGID_BODIES"), vec![ecs.get_system_signature_2::<Transform, RigidBody>().unwrap()], SystemStage::Physics);
+    ecs.register_system(UPDATE_QUAD_TREE.named("UPDATE_QUAD_TREE").after("UPDATE_RIGID_BODIES"), vec![ecs.get_system_signature_2::<Transform, RigidBody>().unwrap()], SystemStage::Physics);
+    ecs.register_system(DETECT_PARTICLE_CABLE_COLLISIONS.named("DETECT_PARTICLE_CABLE_COLLISIONS"), vec![ecs.get_system_signature_1::<ParticleCable>().unwrap()], SystemStage::Physics);
+    ecs.register_system(DETECT_PARTICLE_ROD_COLLISIONS.named("DETECT_PARTICLE_ROD_COLLISIONS"), vec![ecs.get_system_signature_1::<ParticleRod>().unwrap()], SystemStage::Physics);
+    ecs.register_system(DETECT_POTENTIAL_RIGID_BODY_COLLISIONS.named("DETECT_POTENTIAL_RIGID_BODY_COLLISIONS").after("UPDATE_QUAD_TREE"), vec![], SystemStage::Physics);
+    ecs.register_system(detect_rigid_body_collisions().named("DETECT_RIGID_BODY_COLLISIONS").after("DETECT_POTENTIAL_RIGID_BODY_COLLISIONS"), vec![ecs.get_system_signature_1::<RigidBodyCollision>().unwrap()], SystemStage::Physics);
+    ecs.register_system(resolve_particle_collisions().named("RESOLVE_PARTICLE_COLLISIONS").after("DETECT_PARTICLE_CABLE_COLLISIONS").after("DETECT_PARTICLE_ROD_COLLISIONS"), vec![], SystemStage::Physics);
+    ecs.register_system(DETECT_LOAD_NEXT_LEVEL.named("DETECT_LOAD_NEXT_LEVEL"), vec![ecs.get_system_signature_2::<Ladder, Transform>().unwrap()], SystemStage::PostUpdate);
+    ecs.register_system(PROPAGATE_TRANSFORMS.named("PROPAGATE_TRANSFORMS").with_access(SystemAccess::new().reads::<Parent>().reads::<Children>().reads::<Transform>().writes::<GlobalTransform>()), vec![], SystemStage::Render);
+    ecs.register_system(UPDATE_TIMERS.named("UPDATE_TIMERS"), vec![ecs.get_system_signature_1::<Timer>().unwrap()], SystemStage::Render);
+}
+
+// Systems which only run with a render engine
+fn register_render_systems(ecs: &mut ECS) {
+    ecs.register_system(UPDATE_GUI_ELEMENTS.named("UPDATE_GUI_ELEMENTS").before("PROPAGATE_TRANSFORMS"), vec![ecs.get_system_signature_1::<GuiElement>().unwrap()], SystemStage::Render);
+    ecs.register_system(SYNC_RENDER_STATE.named("SYNC_RENDER_STATE").after("PROPAGATE_TRANSFORMS").before("UPDATE_TIMERS"), vec![], SystemStage::Render);
+    ecs.register_system(SHUTDOWN_RENDER_ENGINE.named("SHUTDOWN_RENDER_ENGINE").after("UPDATE_TIMERS"), vec![], SystemStage::Render);
+}
+
+// Names are recorded in RenderAssets so that snapshots can refer to render meshes and textures. Nothing is created without a render
+//  engine, i.e. in a headless scene.
+fn create_render_mesh(render_engine: Option<&mut VulkanRenderEngine>, render_assets: &mut RenderAssets, name: &str, mesh: &Mesh) -> Option<RenderMeshId> {
+    let id = render_engine?.get_device_mut()
+        .and_then(|d| d.create_mesh(mesh.vertices.clone(), mesh.vertex_indices.clone()))
         .unwrap_or_else(|e| panic!("{}", e));
-    let baddie_texture_id_7 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/baddie_7.png")))
+    render_assets.insert_mesh(name, id);
+
+    Some(id)
+}
+
+fn create_render_texture(render_engine: Option<&mut VulkanRenderEngine>, render_assets: &mut RenderAssets, file_path: &str) -> Option<RenderTextureId> {
+    let id = render_engine?.get_device_mut()
+        .and_then(|d| d.create_texture(String::from(file_path)))
         .unwrap_or_else(|e| panic!("{}", e));
+    render_assets.insert_texture(file_path, id);
+
+    Some(id)
+}
 
-    let baddie_texture_binding = TextureBinding::new_provisional(Some(baddie_texture_id), None);
-    let baddie_texture_binding_2 = TextureBinding::new_provisional(Some(baddie_texture_id_2), None);
-    let baddie_texture_binding_3 = TextureBinding::new_provisional(Some(baddie_texture_id_3), None);
-    let baddie_texture_binding_4 = TextureBinding::new_provisional(Some(baddie_texture_id_4), None);
-    let baddie_texture_binding_5 = TextureBinding::new_provisional(Some(baddie_texture_id_5), None);
-    let baddie_texture_binding_6 = TextureBinding::new_provisional(Some(baddie_texture_id_6), None);
-    let baddie_texture_binding_7 = TextureBinding::new_provisional(Some(baddie_texture_id_7), None);
+fn create_baddie_sprite_animation(mut render_engine: Option<&mut VulkanRenderEngine>, render_assets: &mut RenderAssets) -> SpriteAnimation {
+    let baddie_texture_id = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie.png");
+    let baddie_texture_id_2 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_2.png");
+    let baddie_texture_id_3 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_3.png");
+    let baddie_texture_id_4 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_4.png");
+    let baddie_texture_id_5 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_5.png");
+    let baddie_texture_id_6 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_6.png");
+    let baddie_texture_id_7 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/baddie_7.png");
+
+    let baddie_texture_binding = TextureBinding::new_provisional(baddie_texture_id, None);
+    let baddie_texture_binding_2 = TextureBinding::new_provisional(baddie_texture_id_2, None);
+    let baddie_texture_binding_3 = TextureBinding::new_provisional(baddie_texture_id_3, None);
+    let baddie_texture_binding_4 = TextureBinding::new_provisional(baddie_texture_id_4, None);
+    let baddie_texture_binding_5 = TextureBinding::new_provisional(baddie_texture_id_5, None);
+    let baddie_texture_binding_6 = TextureBinding::new_provisional(baddie_texture_id_6, None);
+    let baddie_texture_binding_7 = TextureBinding::new_provisional(baddie_texture_id_7, None);
 
     SpriteAnimation {
         base: Some(baddie_texture_binding),
@@ -319,32 +367,20 @@ fn create_baddie_sprite_animation(render_engine: &mut VulkanRenderEngine) -> Spr
     }
 }
 
-fn create_gun_sprite_animation(render_engine: &mut VulkanRenderEngine) -> SpriteAnimation {
-    let gun_texture_id = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let gun_texture_id_2 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun_2.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let gun_texture_id_3 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun_3.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let gun_texture_id_4 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun_4.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let gun_texture_id_5 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun_5.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let gun_texture_id_6 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/gun_6.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-
-    let gun_texture_binding = TextureBinding::new_provisional(Some(gun_texture_id), None);
-    let gun_texture_binding_2 = TextureBinding::new_provisional(Some(gun_texture_id_2), None);
-    let gun_texture_binding_3 = TextureBinding::new_provisional(Some(gun_texture_id_3), None);
-    let gun_texture_binding_4 = TextureBinding::new_provisional(Some(gun_texture_id_4), None);
-    let gun_texture_binding_5 = TextureBinding::new_provisional(Some(gun_texture_id_5), None);
-    let gun_texture_binding_6 = TextureBinding::new_provisional(Some(gun_texture_id_6), None);
+fn create_gun_sprite_animation(mut render_engine: Option<&mut VulkanRenderEngine>, render_assets: &mut RenderAssets) -> SpriteAnimation {
+    let gun_texture_id = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun.png");
+    let gun_texture_id_2 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun_2.png");
+    let gun_texture_id_3 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun_3.png");
+    let gun_texture_id_4 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun_4.png");
+    let gun_texture_id_5 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun_5.png");
+    let gun_texture_id_6 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/gun_6.png");
+
+    let gun_texture_binding = TextureBinding::new_provisional(gun_texture_id, None);
+    let gun_texture_binding_2 = TextureBinding::new_provisional(gun_texture_id_2, None);
+    let gun_texture_binding_3 = TextureBinding::new_provisional(gun_texture_id_3, None);
+    let gun_texture_binding_4 = TextureBinding::new_provisional(gun_texture_id_4, None);
+    let gun_texture_binding_5 = TextureBinding::new_provisional(gun_texture_id_5, None);
+    let gun_texture_binding_6 = TextureBinding::new_provisional(gun_texture_id_6, None);
 
     SpriteAnimation {
         base: Some(gun_texture_binding),
@@ -352,48 +388,28 @@ fn create_gun_sprite_animation(render_engine: &mut VulkanRenderEngine) -> Sprite
     }
 }
 
-fn create_digits_texture_owner(render_engine: &mut VulkanRenderEngine) -> DigitsTextureOwner {
-    let texture_id_0 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/0.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_1 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/1.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_2 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/2.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_3 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/3.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_4 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/4.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_5 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/5.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_6 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/6.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_7 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/7.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_8 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/8.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-    let texture_id_9 = render_engine.get_device_mut()
-        .and_then(|d| d.create_texture(String::from("res/9.png")))
-        .unwrap_or_else(|e| panic!("{}", e));
-
-    let texture_binding_0 = TextureBinding::new_provisional(Some(texture_id_0), None);
-    let texture_binding_1 = TextureBinding::new_provisional(Some(texture_id_1), None);
-    let texture_binding_2 = TextureBinding::new_provisional(Some(texture_id_2), None);
-    let texture_binding_3 = TextureBinding::new_provisional(Some(texture_id_3), None);
-    let texture_binding_4 = TextureBinding::new_provisional(Some(texture_id_4), None);
-    let texture_binding_5 = TextureBinding::new_provisional(Some(texture_id_5), None);
-    let texture_binding_6 = TextureBinding::new_provisional(Some(texture_id_6), None);
-    let texture_binding_7 = TextureBinding::new_provisional(Some(texture_id_7), None);
-    let texture_binding_8 = TextureBinding::new_provisional(Some(texture_id_8), None);
-    let texture_binding_9 = TextureBinding::new_provisional(Some(texture_id_9), None);
+fn create_digits_texture_owner(mut render_engine: Option<&mut VulkanRenderEngine>, render_assets: &mut RenderAssets) -> DigitsTextureOwner {
+    let texture_id_0 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/0.png");
+    let texture_id_1 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/1.png");
+    let texture_id_2 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/2.png");
+    let texture_id_3 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/3.png");
+    let texture_id_4 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/4.png");
+    let texture_id_5 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/5.png");
+    let texture_id_6 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/6.png");
+    let texture_id_7 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/7.png");
+    let texture_id_8 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/8.png");
+    let texture_id_9 = create_render_texture(render_engine.as_deref_mut(), render_assets, "res/9.png");
+
+    let texture_binding_0 = TextureBinding::new_provisional(texture_id_0, None);
+    let texture_binding_1 = TextureBinding::new_provisional(texture_id_1, None);
+    let texture_binding_2 = TextureBinding::new_provisional(texture_id_2, None);
+    let texture_binding_3 = TextureBinding::new_provisional(texture_id_3, None);
+    let texture_binding_4 = TextureBinding::new_provisional(texture_id_4, None);
+    let texture_binding_5 = TextureBinding::new_provisional(texture_id_5, None);
+    let texture_binding_6 = TextureBinding::new_provisional(texture_id_6, None);
+    let texture_binding_7 = TextureBinding::new_provisional(texture_id_7, None);
+    let texture_binding_8 = TextureBinding::new_provisional(texture_id_8, None);
+    let texture_binding_9 = TextureBinding::new_provisional(texture_id_9, None);
 
     DigitsTextureOwner {
         digits: vec![texture_binding_0, texture_binding_1, texture_binding_2, texture_binding_3, texture_binding_4, texture_binding_5, texture_binding_6, texture_binding_7, texture_binding_8, texture_binding_9],
@@ -401,55 +417,64 @@ fn create_digits_texture_owner(render_engine: &mut VulkanRenderEngine) -> Digits
 }
 
 // Built-in
-const SHUTDOWN_ECS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    entites.for_each(|e| {
-        let render_engine = components.get_component::<VulkanRenderEngine>(e).unwrap();
-
+const SHUTDOWN_ECS: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    if let Some(render_engine) = resources.get::<VulkanRenderEngine>() {
         if render_engine.get_window().map_or(true, |w| w.is_closing()) {
             commands.shutdown();
         }
-    });
+    }
 };
 
 // Built-in
-const TIME_SINCE_LAST_FRAME: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    entites.for_each(|e| {
-        let time_delta = components.get_mut_component::<TimeDelta>(e).unwrap();
+const TIME_SINCE_LAST_FRAME: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    if let Some(mut time_delta) = resources.get_mut::<TimeDelta>() {
+        let now = resources.get::<Clock>().map_or_else(std::time::SystemTime::now, |clock| clock.now());
 
         if time_delta.is_started {
-            let now = std::time::SystemTime::now();
             time_delta.since_last_frame = now.duration_since(time_delta.timestamp).unwrap();
             time_delta.timestamp = now;
+
+            if let Some(mut fixed_timestep) = resources.get_mut::<FixedTimestep>() {
+                fixed_timestep.accumulate(time_delta.since_last_frame);
+            }
         } else {
             time_delta.is_started = true;
-            time_delta.timestamp = std::time::SystemTime::now();
+            time_delta.timestamp = now;
         }
-    });
+    }
 };
 
 // Built-in
-const RESET_TRANSFORM_FLAGS: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    entites.for_each(|e| {
-        let transform = components.get_mut_component::<Transform>(e).unwrap();
-
-        transform.reset_changed_flags();
-    });
+const PROPAGATE_TRANSFORMS: SystemFn = |_: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+    // Roots start from the identity matrix, whether or not they have a Transform themselves
+    let roots: Vec<Entity> = components.query_filtered::<Entity, (With<Children>, Without<Parent>)>().iter()
+        .chain(components.query_filtered::<Entity, (With<GlobalTransform>, Without<Children>, Without<Parent>)>().iter())
+        .map(|(e, _)| e)
+        .collect();
+
+    roots.iter().for_each(|root| propagate_transform(root, &MAT_4_IDENTITY, components));
 };
 
-const SPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let quad_mesh_binding = entites.clone()
-        .filter(|e| components.get_component::<QuadMeshOwner>(e).is_some())
-        .map(|e| components.get_component::<MeshBinding>(e).unwrap())
-        .next()
-        .unwrap();
-    let baddie_animation = entites.clone()
-        .filter(|e| components.get_component::<BaddieTextureOwner>(e).is_some())
-        .map(|e| components.get_component::<SpriteAnimation>(e).unwrap())
-        .next()
-        .unwrap();
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let player = entites.clone().find_map(|e| components.get_component::<Player>(e)).unwrap();
-    let spawn_timer = entites.clone().find_map(|e| components.get_mut_component::<Timer>(e)).unwrap();
+fn propagate_transform(entity: &Entity, parent_world_mat: &Mat4, components: &ComponentManager) {
+    // An entity without a Transform of its own just passes its parent's world matrix through to its children
+    let world_mat = match components.get_component::<Transform>(entity) {
+        Some(transform) => *parent_world_mat * *transform.to_world_mat(),
+        None => *parent_world_mat,
+    };
+
+    if let Some(mut global_transform) = components.get_mut_component::<GlobalTransform>(entity) {
+        global_transform.set_world_mat(world_mat);
+    }
+
+    if let Some(children) = components.get_component::<Children>(entity) {
+        children.get_entities().iter().for_each(|child| propagate_transform(child, &world_mat, components));
+    }
+}
+
+const SPAWN_BADDIES: SystemFn = |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let (player, mut spawn_timer) = components.query::<(&Player, &mut Timer)>().single().unwrap();
 
     if spawn_timer.remaining_duration.is_none() {
         let mut rng = rand::rng();
@@ -474,11 +499,9 @@ const SPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMana
             let x_in_bounds = spawn_x > -player.level_width / 2.0 && spawn_x < player.level_width / 2.0;
             let z_in_bounds = spawn_z > -player.level_height / 2.0 && spawn_z < player.level_height / 2.0;
 
-            if x_in_bounds && z_in_bounds && !entites.clone()
-                .filter(|e| components.get_component::<Wall>(e).is_some_and(|w| w.is_lowest_wall))
-                .any(|e| {
-                    let wall_transform = components.get_component::<Transform>(e).unwrap();
-
+            if x_in_bounds && z_in_bounds && !components.query::<(&Wall, &Transform)>().iter()
+                .filter(|(_, (wall, _))| wall.is_lowest_wall)
+                .any(|(_, (_, wall_transform))| {
                     let min_x = wall_transform.get_pos().x - wall_transform.get_scl().x / 2.0;
                     let max_x = wall_transform.get_pos().x + wall_transform.get_scl().x / 2.0;
 
@@ -488,25 +511,16 @@ const SPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMana
                     spawn_x >= min_x && spawn_x <= max_x && spawn_z >= min_z && spawn_z <= max_z
                 })
             {
-                let current_baddie_count = entites.clone()
-                    .filter(|e| components.get_component::<Baddie>(e).is_some())
-                    .count();
+                let current_baddie_count = components.query::<&Baddie>().count();
 
                 if current_baddie_count < player.baddie_cap {
                     const BADDIE_SIZE: f32 = 16.0;
-                    const BADDIE_ANIMATION_SPEED: f32 = 0.15;
 
                     let rot_ang = rng.random_range(0.0..(std::f32::consts::PI * 2.0));
 
                     let baddie_transform = Transform::new(vec3(spawn_x, CUBE_SIZE / 2.0 + BADDIE_SIZE / 2.0, spawn_z), Quat::from_axis_spin(&VEC_3_Y_AXIS, rot_ang).unwrap(), IDENTITY_SCALE_VEC * BADDIE_SIZE);
-                    let baddie_entity = commands.create_entity();
+                    let baddie_entity = commands.spawn_prefab(&resources.get::<LevelPrefabs>().unwrap().baddie);
                     commands.attach_provisional_component(&baddie_entity, baddie_transform);
-                    commands.attach_provisional_component(&baddie_entity, quad_mesh_binding.clone());
-                    commands.attach_provisional_component(&baddie_entity, baddie_animation.frames[0].clone());
-                    commands.attach_provisional_component(&baddie_entity, baddie_animation.clone());
-                    commands.attach_provisional_component(&baddie_entity, Baddie { is_active: false });
-                    commands.attach_provisional_component(&baddie_entity, LevelEntity {});
-                    commands.attach_provisional_component(&baddie_entity, Timer::new(0.0, 1.0, Duration::from_secs_f32(BADDIE_ANIMATION_SPEED)));
                 }
             }
         }
@@ -515,193 +529,163 @@ const SPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMana
     }
 };
 
-const UPDATE_BADDIE_IS_ACTIVE: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-
-    for e in entites.clone() {
-        if let Some(baddie) = components.get_mut_component::<Baddie>(e) {
-            let baddie_transform = components.get_component::<Transform>(e).unwrap();
+const UPDATE_BADDIE_IS_ACTIVE: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
 
-            let distance_to_camera = (cam.pos - *baddie_transform.get_pos()).len();
+    for (_, (mut baddie, baddie_transform, mut animation_timer)) in components.query::<(&mut Baddie, &Transform, &mut Timer)>().iter_over(entities[0].iter()) {
+        let distance_to_camera = (cam.pos - *baddie_transform.get_pos()).len();
 
-            const MAX_ACTIVE_DISTANCE_TO_PLAYER: f32 = 175.0;
+        const MAX_ACTIVE_DISTANCE_TO_PLAYER: f32 = 175.0;
 
-            if distance_to_camera > MAX_ACTIVE_DISTANCE_TO_PLAYER {
-                baddie.is_active = false;
-            } else {
-                let line_of_sight_blocked = entites.clone()
-                    .any(|e| {
-                        if components.get_component::<Wall>(e).is_some() {
-                            let wall_transform = components.get_mut_component::<Transform>(e).unwrap();
-                            let wall_mesh = components.get_component::<Mesh>(
-                                components.get_component::<MeshBinding>(e).unwrap().mesh_wrapper.as_ref().unwrap()
-                            ).unwrap();
-        
-                            if let Some(dist) = check_ray_intersects(baddie_transform.get_pos(), &(cam.pos - *baddie_transform.get_pos()).normalized().unwrap(), wall_mesh, wall_transform, false) {
-                                return dist < (cam.pos - *baddie_transform.get_pos()).len();
-                            }
-                        }
+        if distance_to_camera > MAX_ACTIVE_DISTANCE_TO_PLAYER {
+            baddie.is_active = false;
+        } else {
+            let line_of_sight_blocked = components.query_filtered::<(&Transform, &MeshBinding), With<Wall>>().iter()
+                .any(|(_, (wall_transform, wall_mesh_binding))| {
+                    let wall_mesh = components.get_component::<Mesh>(wall_mesh_binding.mesh_wrapper.as_ref().unwrap()).unwrap();
 
-                        false
-                    });
+                    if let Some(dist) = check_ray_intersects(baddie_transform.get_pos(), &(cam.pos - *baddie_transform.get_pos()).normalized().unwrap(), &wall_mesh, &wall_transform, false) {
+                        return dist < (cam.pos - *baddie_transform.get_pos()).len();
+                    }
 
-                baddie.is_active = !line_of_sight_blocked;
-            }
+                    false
+                });
 
-            let animation_timer = components.get_mut_component::<Timer>(e).unwrap();
+            baddie.is_active = !line_of_sight_blocked;
+        }
 
-            if !baddie.is_active {
-                animation_timer.stop();
-            } else if animation_timer.remaining_duration.is_none() {
-                animation_timer.reset();
-            }
+        if !baddie.is_active {
+            animation_timer.stop();
+        } else if animation_timer.remaining_duration.is_none() {
+            animation_timer.reset();
         }
     }
 };
 
-const MOVE_BADDIE: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
+const MOVE_BADDIE: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let time_delta = resources.get::<TimeDelta>().unwrap();
     let delta_sec = time_delta.since_last_frame.as_secs_f32();
 
     const BADDIE_SPEED: f32 = 45.0;
 
-    for e in entites {
-        if let Some(baddie) = components.get_component::<Baddie>(e) {
-            let transform =  components.get_mut_component::<Transform>(e).unwrap();
-
-            if baddie.is_active {
-                let towards_player = (vec3(cam.pos.x, 0.0, cam.pos.z) - vec3(transform.get_pos().x, 0.0, transform.get_pos().z)).normalized().unwrap();
+    for (_, (baddie, mut transform)) in components.query::<(&Baddie, &mut Transform)>().iter_over(entities[0].iter()) {
+        let transform = &mut *transform;
 
-                transform.set_pos(*transform.get_pos() + towards_player * BADDIE_SPEED * delta_sec);
+        if baddie.is_active {
+            let towards_player = (vec3(cam.pos.x, 0.0, cam.pos.z) - vec3(transform.get_pos().x, 0.0, transform.get_pos().z)).normalized().unwrap();
 
-                let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
+            transform.set_pos(*transform.get_pos() + towards_player * BADDIE_SPEED * delta_sec);
 
-                if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
-                    angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
-                }
+            let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
 
-                transform.set_rot(Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap());
+            if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
+                angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
             }
+
+            transform.set_rot(Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap());
         }
     }
 };
 
-const UPDATE_LADDER: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-
-    for e in entites {
-        if components.get_component::<Ladder>(e).is_some() {
-            let transform = components.get_mut_component::<Transform>(e).unwrap();
-
-            let towards_player = (vec3(cam.pos.x, 0.0, cam.pos.z) - vec3(transform.get_pos().x, 0.0, transform.get_pos().z)).normalized().unwrap();
+const UPDATE_LADDER: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
 
-            let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
+    for (_, mut transform) in components.query_filtered::<&mut Transform, With<Ladder>>().iter_over(entities[0].iter()) {
+        let towards_player = (vec3(cam.pos.x, 0.0, cam.pos.z) - vec3(transform.get_pos().x, 0.0, transform.get_pos().z)).normalized().unwrap();
 
-            if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
-                angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
-            }
+        let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
 
-            transform.set_rot(Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap());
+        if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
+            angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
         }
+
+        transform.set_rot(Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap());
     }
 };
 
-const UPDATE_DEAD_BADDIES: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
+const UPDATE_DEAD_BADDIES: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let time_delta = resources.get::<TimeDelta>().unwrap();
     let delta_sec = time_delta.since_last_frame.as_secs_f32();
 
     const DEAD_BADDIE_SPIN_SPEED: f32 = 50.0;
     const DEAD_BADDIE_GRAVITY: f32 = -30.0;
 
-    for e in entites {
-        if let Some(baddie) = components.get_mut_component::<DeadBaddie>(e) {
-            let transform = components.get_mut_component::<Transform>(e).unwrap();
-            let timer = components.get_component::<Timer>(e).unwrap();
+    for (e, (mut baddie, mut transform, timer)) in components.query::<(&mut DeadBaddie, &mut Transform, &Timer)>().iter_over(entities[0].iter()) {
+        let transform = &mut *transform;
 
-            if let Some(remainder) = timer.remaining_duration {
-                baddie.vel.y += DEAD_BADDIE_GRAVITY * delta_sec;
+        if let Some(remainder) = timer.remaining_duration {
+            baddie.vel.y += DEAD_BADDIE_GRAVITY * delta_sec;
 
-                transform.set_pos(*transform.get_pos() + baddie.vel * delta_sec);
+            transform.set_pos(*transform.get_pos() + baddie.vel * delta_sec);
 
-                let spin_amt = (timer.initial_duration.as_secs_f32() - remainder.as_secs_f32()) * DEAD_BADDIE_SPIN_SPEED;
+            let spin_amt = (timer.initial_duration.as_secs_f32() - remainder.as_secs_f32()) * DEAD_BADDIE_SPIN_SPEED;
 
-                let towards_player = (cam.pos - *transform.get_pos()).normalized().unwrap();
+            let towards_player = (cam.pos - *transform.get_pos()).normalized().unwrap();
 
-                let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
+            let mut angle_to_cam = towards_player.angle_rads_from(&VEC_3_Z_AXIS).unwrap();
 
-                if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
-                    angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
-                }
+            if VEC_3_Z_AXIS.cross(&towards_player).y < 0.0 {
+                angle_to_cam = 2.0 * std::f32::consts::PI - angle_to_cam;
+            }
 
-                let to_player_rot = Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap();
-                let spin_rot = Quat::from_axis_spin(&towards_player, spin_amt).unwrap();
+            let to_player_rot = Quat::from_axis_spin(&VEC_3_Y_AXIS, angle_to_cam).unwrap();
+            let spin_rot = Quat::from_axis_spin(&towards_player, spin_amt).unwrap();
 
-                transform.set_rot(spin_rot * to_player_rot);
-            } else {
-                commands.destroy_entity(e);
-            }
+            transform.set_rot(spin_rot * to_player_rot);
+        } else {
+            commands.destroy_entity(&e);
         }
     }
 };
 
-const DAMAGE_PLAYER: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let player = entites.clone().find_map(|e| components.get_mut_component::<Player>(e)).unwrap();
-    let level_loader = entites.clone().find_map(|e| components.get_mut_component::<LevelLoader>(e)).unwrap();
+const DAMAGE_PLAYER: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let mut player = components.query::<&mut Player>().single().unwrap();
+    let mut level_loader = resources.get_mut::<LevelLoader>().unwrap();
 
     const HEALTH_PER_BADDIE: u32 = 20;
     const DAMAGE_DISTANCE: f32 = 8.0;
 
-    for e in entites {
-        if components.get_component::<Baddie>(e).is_some() {
-            let transform =  components.get_mut_component::<Transform>(e).unwrap();
-
-            let dist_to_player = (*transform.get_pos() - cam.pos).len();
+    for (e, transform) in components.query_filtered::<&Transform, With<Baddie>>().iter_over(entities[0].iter()) {
+        let dist_to_player = (*transform.get_pos() - cam.pos).len();
 
-            if dist_to_player <= DAMAGE_DISTANCE {
-                commands.destroy_entity(e);
+        if dist_to_player <= DAMAGE_DISTANCE {
+            commands.destroy_entity(&e);
 
-                player.curr_health -= HEALTH_PER_BADDIE;
+            player.curr_health -= HEALTH_PER_BADDIE;
 
-                if player.curr_health <= 0 {
-                    // ur bad
-                    level_loader.should_load = true;
-                    level_loader.next_level_id = 0;
-                }
+            if player.curr_health <= 0 {
+                // ur bad
+                level_loader.next_level_id = 0;
+                commands.send_event(LoadLevel {});
             }
         }
     }
 };
 
-const SHOOT_BADDIES: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let render_engine = entites.clone().find_map(|e| components.get_mut_component::<VulkanRenderEngine>(e)).unwrap();
-    let cursor_manager = entites.clone().find_map(|e| components.get_mut_component::<CursorManager>(e)).unwrap();
-    let baddie_texture_binding = entites.clone()
-        .filter(|e: &&Entity| components.get_component::<BaddieTextureOwner>(e).is_some())
-        .map(|e| components.get_component::<TextureBinding>(e).unwrap())
-        .next()
-        .unwrap();
-    let player = entites.clone().find_map(|e| components.get_mut_component::<Player>(e)).unwrap();
-    let gun_animation_timer = entites.clone()
-        .filter(|e| components.get_component::<GunAnimationTimer>(e).is_some())
-        .map(|e| components.get_mut_component::<Timer>(e).unwrap())
-        .next()
-        .unwrap();
-    let gun_reload_timer = entites.clone()
-        .filter(|e| components.get_component::<GunReloadTimer>(e).is_some())
-        .map(|e| components.get_mut_component::<Timer>(e).unwrap())
-        .next()
-        .unwrap();
+const SHOOT_BADDIES: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let render_engine = resources.get::<VulkanRenderEngine>();
+    let cursor_manager = resources.get::<CursorManager>().unwrap();
+    let baddie_texture_binding = components.query_filtered::<&TextureBinding, With<BaddieTextureOwner>>().single().unwrap();
+    let mut player = components.query::<&mut Player>().single().unwrap();
+    let mut gun_animation_timer = components.query_filtered::<&mut Timer, With<GunAnimationTimer>>().single().unwrap();
+    let mut gun_reload_timer = components.query_filtered::<&mut Timer, With<GunReloadTimer>>().single().unwrap();
 
     if player.is_reloading && gun_reload_timer.remaining_duration.is_none() {
         player.is_reloading = false;
         player.ammo_count = player.max_ammo;
     }
 
-    if let Ok(window) = render_engine.get_window() {
+    if let Some(window) = render_engine.as_ref().and_then(|r| r.get_window().ok()) {
         if cursor_manager.is_locked && gun_reload_timer.remaining_duration.is_none() {
             if window.is_button_pressed(VirtualButton::Left) && player.ammo_count > 0 {
                 player.ammo_count -= 1;
@@ -713,25 +697,19 @@ const SHOOT_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMana
                 let mut closest_baddie: Option<Entity> = None;
                 let mut closest_obstacle = f32::MAX;
 
-                for e in entites.clone() {
-                    let is_baddie = components.get_component::<Baddie>(e).is_some();
-
-                    if is_baddie || components.get_component::<Wall>(e).is_some() {
-                        let mesh = components.get_component::<Mesh>(
-                            components.get_component::<MeshBinding>(e).unwrap().mesh_wrapper.as_ref().unwrap()
-                        ).unwrap();
-                        let transform = components.get_mut_component::<Transform>(e).unwrap();
-
-                        if let Some(dist) = check_ray_intersects(&cam.pos, &ray_dir, mesh, transform, is_baddie) {
-                            if dist < closest_obstacle {
-                                closest_baddie = if is_baddie {
-                                    Some(*e)
-                                } else {
-                                    None
-                                };
-
-                                closest_obstacle = dist;
-                            }
+                for (e, (mesh_binding, transform, baddie)) in components.query::<(&MeshBinding, &Transform, Option<&Baddie>)>().iter_over(entities[0].iter()) {
+                    let is_baddie = baddie.is_some();
+                    let mesh = components.get_component::<Mesh>(mesh_binding.mesh_wrapper.as_ref().unwrap()).unwrap();
+
+                    if let Some(dist) = check_ray_intersects(&cam.pos, &ray_dir, &mesh, &transform, is_baddie) {
+                        if dist < closest_obstacle {
+                            closest_baddie = if is_baddie {
+                                Some(e)
+                            } else {
+                                None
+                            };
+
+                            closest_obstacle = dist;
                         }
                     }
                 }
@@ -776,7 +754,7 @@ const SHOOT_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMana
     }
 };
 
-fn check_ray_intersects(ray_origin: &Vec3, ray_dir: &Vec3, mesh: &Mesh, transform: &mut Transform, is_baddie: bool) -> Option<f32> {
+fn check_ray_intersects(ray_origin: &Vec3, ray_dir: &Vec3, mesh: &Mesh, transform: &Transform, is_baddie: bool) -> Option<f32> {
     const BADDIE_COLLISION_Y_THRESHOLD: f32 = 0.25;
 
     let inverse_world_matrix = transform.to_world_mat().inverted().unwrap();
@@ -790,9 +768,10 @@ fn check_ray_intersects(ray_origin: &Vec3, ray_dir: &Vec3, mesh: &Mesh, transfor
         .map(|p| (*ray_origin - p).len())
 }
 
-const DESPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
+const DESPAWN_BADDIES: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
+    let time_delta = resources.get::<TimeDelta>().unwrap();
     let delta_sec = time_delta.since_last_frame.as_secs_f32();
 
     let mut rng = rand::rng();
@@ -800,191 +779,160 @@ const DESPAWN_BADDIES: System = |entites: Iter<Entity>, components: &ComponentMa
     const DESPAWN_CHANCE: f32 = 0.03;
     const MIN_DESPAWN_DISTANCE: f32 = 150.0;
 
-    for e in entites {
-        if let Some(baddie) = components.get_component::<Baddie>(e) {
-            let baddie_transform = components.get_component::<Transform>(e).unwrap();
-
-            let dist_to_player= (cam.pos - *baddie_transform.get_pos()).len();
+    for (e, (baddie, baddie_transform)) in components.query::<(&Baddie, &Transform)>().iter_over(entities[0].iter()) {
+        let dist_to_player= (cam.pos - *baddie_transform.get_pos()).len();
 
-            if !baddie.is_active && dist_to_player >= MIN_DESPAWN_DISTANCE && rng.random_range(0.0..1.0) < DESPAWN_CHANCE * delta_sec {
-                commands.destroy_entity(e);
-            }
+        if !baddie.is_active && dist_to_player >= MIN_DESPAWN_DISTANCE && rng.random_range(0.0..1.0) < DESPAWN_CHANCE * delta_sec {
+            commands.destroy_entity(&e);
         }
     }
 };
 
-const LOAD_LEVEL: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let level_loader = entites.clone().find_map(|e| components.get_mut_component::<LevelLoader>(e)).unwrap();
-
-    if level_loader.should_load {
-        let cube_mesh_binding = entites.clone()
-            .filter(|e| components.get_component::<CubeMeshOwner>(e).is_some())
-            .map(|e| components.get_component::<MeshBinding>(e).unwrap())
-            .next()
-            .unwrap();
-        let cube_texture_binding = entites.clone()
-            .filter(|e| components.get_component::<CubeMeshOwner>(e).is_some())
-            .map(|e| components.get_component::<TextureBinding>(e).unwrap())
-            .next()
-            .unwrap();
-        let ladder_texture_binding = entites.clone()
-            .filter(|e| components.get_component::<LadderTextureOwner>(e).is_some())
-            .map(|e| components.get_component::<TextureBinding>(e).unwrap())
-            .next()
-            .unwrap();
-        let ladder_mesh_binding = entites.clone()
-            .filter(|e| components.get_component::<LadderTextureOwner>(e).is_some())
-            .map(|e| components.get_component::<MeshBinding>(e).unwrap())
-            .next()
-            .unwrap();
-        let existing_health = entites.clone().find_map(|e| components.get_component::<Player>(e)).map(|p| p.curr_health);
-        let gun_animation = entites.clone()
-            .filter(|e| components.get_component::<GunTextureOwner>(e).is_some())
-            .map(|e| components.get_component::<SpriteAnimation>(e).unwrap())
-            .next()
-            .unwrap();
-
-        for e in entites.clone() {
-            if components.get_component::<LevelEntity>(e).is_some() {
-                commands.destroy_entity(e);
-            }
-        }
+fn load_level() -> impl System {
+    let mut load_requests = EventReader::<LoadLevel>::new();
 
-        // 'd' - open space, 'w' - wall, 'p' - player, 's' - starting door (unused, place wall), 'e' - exit (ladder)
-        let maze_data = create_maze_vector((4 + level_loader.next_level_id).pow(2));
-        let level_dim_x: usize = maze_data.len();
-        let level_dim_z: usize = maze_data[0].len();
+    move |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+        let mut level_loader = resources.get_mut::<LevelLoader>().unwrap();
 
-        let (player_x, player_z) = get_player_indexes(&maze_data);
-        let player_x_pos = CUBE_SIZE * (player_x as f32 - (level_dim_x as f32 - 1.0) / 2.0);
-        let player_z_pos = CUBE_SIZE * (player_z as f32 - (level_dim_z as f32 - 1.0) / 2.0);
+        // Any number of requests in the same frame only load a single level
+        if resources.get::<Events<LoadLevel>>().unwrap().read(&mut load_requests).count() > 0 {
+            let level_prefabs = resources.get::<LevelPrefabs>().unwrap();
+            let (ladder_mesh_binding, ladder_texture_binding) = components.query_filtered::<(&MeshBinding, &TextureBinding), With<LadderTextureOwner>>().single().unwrap();
+            let existing_health = components.query::<&Player>().single().map(|p| p.curr_health);
+            let gun_animation = components.query_filtered::<&SpriteAnimation, With<GunTextureOwner>>().single().unwrap();
 
-        let cam_pos = vec3(player_x_pos, 0.0, player_z_pos);
-        let cam_forward = -VEC_3_Z_AXIS;
+            commands.destroy_entities_with::<LevelEntity>();
 
-        let cam = Camera::new(cam_pos, cam_forward, VEC_3_Y_AXIS, 70.0_f32.to_radians());
-        let viewport = Viewport2D::new(cam, VEC_2_ZERO, vec2(1.0, 1.0));
-        let viewport_entity = commands.create_entity();
-        commands.attach_provisional_component(&viewport_entity, viewport);
-        commands.attach_provisional_component(&viewport_entity, LevelEntity {});
+            // 'd' - open space, 'w' - wall, 'p' - player, 's' - starting door (unused, place wall), 'e' - exit (ladder)
+            let maze_data = create_maze_vector((4 + level_loader.next_level_id).pow(2));
+            let level_dim_x: usize = maze_data.len();
+            let level_dim_z: usize = maze_data[0].len();
 
-        const STACK_HEIGHT: u32 = 3;
+            let (player_x, player_z) = get_player_indexes(&maze_data);
+            let player_x_pos = CUBE_SIZE * (player_x as f32 - (level_dim_x as f32 - 1.0) / 2.0);
+            let player_z_pos = CUBE_SIZE * (player_z as f32 - (level_dim_z as f32 - 1.0) / 2.0);
 
-        for i in (-1 as i32)..((level_dim_x + 1) as i32) {
-            for j in (-1 as i32)..((level_dim_z + 1) as i32) {
-                let x_pos = CUBE_SIZE * (i as f32 - (level_dim_x as f32 - 1.0) / 2.0);
-                let z_pos = CUBE_SIZE * (j as f32 - (level_dim_z as f32 - 1.0) / 2.0);
+            let cam_pos = vec3(player_x_pos, 0.0, player_z_pos);
+            let cam_forward = -VEC_3_Z_AXIS;
 
-                let cube_pos = vec3(x_pos, 0.0, z_pos);
+            let cam = Camera::new(cam_pos, cam_forward, VEC_3_Y_AXIS, 70.0_f32.to_radians());
+            let viewport = Viewport2D::new(cam, VEC_2_ZERO, vec2(1.0, 1.0));
+            let viewport_entity = commands.create_entity();
+            commands.attach_provisional_component(&viewport_entity, viewport);
+            commands.attach_provisional_component(&viewport_entity, LevelEntity {});
 
-                let cube_transform = Transform::new(cube_pos, QUAT_IDENTITY, IDENTITY_SCALE_VEC * CUBE_SIZE);
-                let cube_entity = commands.create_entity();
-                commands.attach_provisional_component(&cube_entity, cube_transform);
-                commands.attach_provisional_component(&cube_entity, cube_texture_binding.clone());
-                commands.attach_provisional_component(&cube_entity, cube_mesh_binding.clone());
-                commands.attach_provisional_component(&cube_entity, LevelEntity {});
+            const STACK_HEIGHT: u32 = 3;
 
-                let ceiling_pos = vec3(x_pos, (STACK_HEIGHT + 1) as f32 * CUBE_SIZE, z_pos);
+            for i in (-1 as i32)..((level_dim_x + 1) as i32) {
+                for j in (-1 as i32)..((level_dim_z + 1) as i32) {
+                    let x_pos = CUBE_SIZE * (i as f32 - (level_dim_x as f32 - 1.0) / 2.0);
+                    let z_pos = CUBE_SIZE * (j as f32 - (level_dim_z as f32 - 1.0) / 2.0);
 
-                let ceiling_transform = Transform::new(ceiling_pos, QUAT_IDENTITY, IDENTITY_SCALE_VEC * CUBE_SIZE);
-                let ceiling_entity = commands.create_entity();
-                commands.attach_provisional_component(&ceiling_entity, ceiling_transform);
-                commands.attach_provisional_component(&ceiling_entity, cube_texture_binding.clone());
-                commands.attach_provisional_component(&ceiling_entity, cube_mesh_binding.clone());
-                commands.attach_provisional_component(&ceiling_entity, LevelEntity {});
+                    let cube_pos = vec3(x_pos, 0.0, z_pos);
 
-                let always_wall = i == -1 || i == level_dim_x as i32 || j == -1 || j == level_dim_z as i32;
-                if always_wall || is_wall(&maze_data, i, j) {
-                    create_walls(commands, cube_texture_binding, cube_mesh_binding, x_pos, z_pos, CUBE_SIZE, STACK_HEIGHT);
+                    let cube_transform = Transform::new(cube_pos, QUAT_IDENTITY, IDENTITY_SCALE_VEC * CUBE_SIZE);
+                    let cube_entity = commands.spawn_prefab(&level_prefabs.block);
+                    commands.attach_provisional_component(&cube_entity, cube_transform);
+
+                    let ceiling_pos = vec3(x_pos, (STACK_HEIGHT + 1) as f32 * CUBE_SIZE, z_pos);
+
+                    let ceiling_transform = Transform::new(ceiling_pos, QUAT_IDENTITY, IDENTITY_SCALE_VEC * CUBE_SIZE);
+                    let ceiling_entity = commands.spawn_prefab(&level_prefabs.block);
+                    commands.attach_provisional_component(&ceiling_entity, ceiling_transform);
+
+                    let always_wall = i == -1 || i == level_dim_x as i32 || j == -1 || j == level_dim_z as i32;
+                    if always_wall || is_wall(&maze_data, i, j) {
+                        create_walls(commands, &level_prefabs.block, x_pos, z_pos, CUBE_SIZE, STACK_HEIGHT);
+                    }
                 }
             }
-        }
 
-        const MAX_HEALTH: u32 = 100;
-        const MAX_AMMO: usize = 12;
-
-        let spawn_chance = 0.025 + 0.008 * (level_loader.next_level_id as f32);
-        let baddie_cap = 20 + 10 * (level_loader.next_level_id);
-
-        let player = if level_loader.next_level_id == 0 {
-            Player {
-                y_vel: 0.0,
-                is_jumping: false,
-                curr_health: MAX_HEALTH,
-                level_width: level_dim_x as f32 * CUBE_SIZE,
-                level_height: level_dim_z  as f32 * CUBE_SIZE,
-                spawn_chance,
-                baddie_cap,
-                ammo_count: MAX_AMMO,
-                max_ammo: MAX_AMMO,
-                is_reloading: false,
-                curr_level: level_loader.next_level_id + 1,
-            }
-        } else {
-            Player {
-                y_vel: 0.0,
-                is_jumping: false,
-                curr_health: existing_health.unwrap(),
-                level_width: level_dim_x as f32 * CUBE_SIZE,
-                level_height: level_dim_z as f32 * CUBE_SIZE,
-                spawn_chance,
-                baddie_cap,
-                ammo_count: MAX_AMMO,
-                max_ammo: MAX_AMMO,
-                is_reloading: false,
-                curr_level: level_loader.next_level_id + 1,
-            }
-        };
-
-        let spawn_timer = Timer::for_initial_duration(Duration::from_millis(100));
-
-        let player_entity = commands.create_entity();
-        commands.attach_provisional_component(&player_entity, player);
-        commands.attach_provisional_component(&player_entity, LevelEntity {});
-        commands.attach_provisional_component(&player_entity, GuiElement { id: String::from("gun"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
-        commands.attach_provisional_component(&player_entity, spawn_timer);
-        commands.attach_provisional_component(&player_entity, gun_animation.clone());
-        commands.attach_provisional_component(&player_entity, gun_animation.base.unwrap().clone());
-
-        const GUN_ANIMATION_SPEED: f32 = 0.3;
-        let mut gun_animation_timer = Timer::new(0.0, 1.0, Duration::from_secs_f32(GUN_ANIMATION_SPEED));
-        gun_animation_timer.stop();
-
-        let gun_animation_timer_entity = commands.create_entity();
-        commands.attach_provisional_component(&gun_animation_timer_entity, LevelEntity {});
-        commands.attach_provisional_component(&gun_animation_timer_entity, GunAnimationTimer {});
-        commands.attach_provisional_component(&gun_animation_timer_entity, gun_animation_timer);
-
-        const GUN_RELOAD_SPEED: f32 = 2.0;
-        let mut gun_reload_timer = Timer::for_initial_duration(Duration::from_secs_f32(GUN_RELOAD_SPEED));
-        gun_reload_timer.stop();
-
-        let gun_reload_timer_entity = commands.create_entity();
-        commands.attach_provisional_component(&gun_reload_timer_entity, LevelEntity {});
-        commands.attach_provisional_component(&gun_reload_timer_entity, GunReloadTimer {});
-        commands.attach_provisional_component(&gun_reload_timer_entity, gun_reload_timer);
-
-        level_loader.next_level_id += 1;
-        level_loader.should_load = false;
-
-        let (ladder_x_index, ladder_z_index) = get_ladder_indexes(&maze_data);
-
-        let ladder_x_pos = CUBE_SIZE * (ladder_x_index as f32 - (level_dim_x as f32 - 1.0) / 2.0);
-        let ladder_z_pos = CUBE_SIZE * (ladder_z_index as f32 - (level_dim_z as f32 - 1.0) / 2.0);
-
-        const LADDER_WIDTH: f32 = 5.0;
-        const LADDER_HEIGHT: f32 = LADDER_WIDTH * 4.6;
-        const LADDER_Y_POS: f32 = CUBE_SIZE / 2.0 + LADDER_HEIGHT / 2.0;
-
-        let ladder_transform = Transform::new(vec3(ladder_x_pos, LADDER_Y_POS, ladder_z_pos), QUAT_IDENTITY, vec3(LADDER_WIDTH, LADDER_HEIGHT, 1.0));
-        let ladder_entity = commands.create_entity();
-        commands.attach_provisional_component(&ladder_entity, ladder_transform);
-        commands.attach_provisional_component(&ladder_entity, ladder_texture_binding.clone());
-        commands.attach_provisional_component(&ladder_entity, ladder_mesh_binding.clone());
-        commands.attach_provisional_component(&ladder_entity, LevelEntity {});
-        commands.attach_provisional_component(&ladder_entity, Ladder {});
+            const MAX_HEALTH: u32 = 100;
+            const MAX_AMMO: usize = 12;
+
+            let spawn_chance = 0.025 + 0.008 * (level_loader.next_level_id as f32);
+            let baddie_cap = 20 + 10 * (level_loader.next_level_id);
+
+            let player = if level_loader.next_level_id == 0 {
+                Player {
+                    y_vel: 0.0,
+                    is_jumping: false,
+                    curr_health: MAX_HEALTH,
+                    level_width: level_dim_x as f32 * CUBE_SIZE,
+                    level_height: level_dim_z  as f32 * CUBE_SIZE,
+                    spawn_chance,
+                    baddie_cap,
+                    ammo_count: MAX_AMMO,
+                    max_ammo: MAX_AMMO,
+                    is_reloading: false,
+                    curr_level: level_loader.next_level_id + 1,
+                }
+            } else {
+                Player {
+                    y_vel: 0.0,
+                    is_jumping: false,
+                    curr_health: existing_health.unwrap(),
+                    level_width: level_dim_x as f32 * CUBE_SIZE,
+                    level_height: level_dim_z as f32 * CUBE_SIZE,
+                    spawn_chance,
+                    baddie_cap,
+                    ammo_count: MAX_AMMO,
+                    max_ammo: MAX_AMMO,
+                    is_reloading: false,
+                    curr_level: level_loader.next_level_id + 1,
+                }
+            };
+
+            let spawn_timer = Timer::for_initial_duration(Duration::from_millis(100));
+
+            let player_entity = commands.create_entity();
+            commands.attach_provisional_component(&player_entity, player);
+            commands.attach_provisional_component(&player_entity, LevelEntity {});
+            commands.attach_provisional_component(&player_entity, GuiElement { id: String::from("gun"), position: VEC_2_ZERO, dimensions: vec2(1.0, 1.0) });
+            commands.attach_provisional_component(&player_entity, spawn_timer);
+            commands.attach_provisional_component(&player_entity, gun_animation.clone());
+            commands.attach_provisional_component(&player_entity, gun_animation.base.unwrap().clone());
+
+            const GUN_ANIMATION_SPEED: f32 = 0.3;
+            let mut gun_animation_timer = Timer::new(0.0, 1.0, Duration::from_secs_f32(GUN_ANIMATION_SPEED));
+            gun_animation_timer.stop();
+
+            // The gun timers are children of the player, so they're despawned along with it when the next level loads
+            let gun_animation_timer_entity = commands.create_entity();
+            commands.attach_provisional_component(&gun_animation_timer_entity, Parent::new_provisional(player_entity));
+            commands.attach_provisional_component(&gun_animation_timer_entity, GunAnimationTimer {});
+            commands.attach_provisional_component(&gun_animation_timer_entity, gun_animation_timer);
+
+            const GUN_RELOAD_SPEED: f32 = 2.0;
+            let mut gun_reload_timer = Timer::for_initial_duration(Duration::from_secs_f32(GUN_RELOAD_SPEED));
+            gun_reload_timer.stop();
+
+            let gun_reload_timer_entity = commands.create_entity();
+            commands.attach_provisional_component(&gun_reload_timer_entity, Parent::new_provisional(player_entity));
+            commands.attach_provisional_component(&gun_reload_timer_entity, GunReloadTimer {});
+            commands.attach_provisional_component(&gun_reload_timer_entity, gun_reload_timer);
+
+            level_loader.next_level_id += 1;
+
+            let (ladder_x_index, ladder_z_index) = get_ladder_indexes(&maze_data);
+
+            let ladder_x_pos = CUBE_SIZE * (ladder_x_index as f32 - (level_dim_x as f32 - 1.0) / 2.0);
+            let ladder_z_pos = CUBE_SIZE * (ladder_z_index as f32 - (level_dim_z as f32 - 1.0) / 2.0);
+
+            const LADDER_WIDTH: f32 = 5.0;
+            const LADDER_HEIGHT: f32 = LADDER_WIDTH * 4.6;
+            const LADDER_Y_POS: f32 = CUBE_SIZE / 2.0 + LADDER_HEIGHT / 2.0;
+
+            let ladder_transform = Transform::new(vec3(ladder_x_pos, LADDER_Y_POS, ladder_z_pos), QUAT_IDENTITY, vec3(LADDER_WIDTH, LADDER_HEIGHT, 1.0));
+            let ladder_entity = commands.create_entity();
+            commands.attach_provisional_component(&ladder_entity, ladder_transform);
+            commands.attach_provisional_component(&ladder_entity, ladder_texture_binding.clone());
+            commands.attach_provisional_component(&ladder_entity, ladder_mesh_binding.clone());
+            commands.attach_provisional_component(&ladder_entity, LevelEntity {});
+            commands.attach_provisional_component(&ladder_entity, Ladder {});
+        }
     }
-};
+}
 
 fn get_player_indexes(maze_data: &Vec<Vec<char>>) -> (usize, usize) {
     for x in 0..maze_data.len() {
@@ -1014,49 +962,42 @@ fn get_ladder_indexes(maze_data: &Vec<Vec<char>>) -> (usize, usize) {
     panic!("No ladder found");
 }
 
-fn create_walls(commands: &mut ECSCommands, texture_binding: &TextureBinding, mesh_binding: &MeshBinding, x: f32, z: f32, cube_size: f32, stack_height: u32) {
+fn create_walls(commands: &mut ECSCommands, block: &Prefab, x: f32, z: f32, cube_size: f32, stack_height: u32) {
     for i in 0..stack_height {
         let wall_transform = Transform::new(vec3(x, (i + 1) as f32 * cube_size, z), QUAT_IDENTITY, IDENTITY_SCALE_VEC * cube_size);
 
-        let wall_entity = commands.create_entity();
+        let wall_entity = commands.spawn_prefab(block);
         commands.attach_provisional_component(&wall_entity, wall_transform);
-        commands.attach_provisional_component(&wall_entity, texture_binding.clone());
-        commands.attach_provisional_component(&wall_entity, mesh_binding.clone());
-        commands.attach_provisional_component(&wall_entity, LevelEntity {});
         commands.attach_provisional_component(&wall_entity, Wall { is_lowest_wall: i == 0 });
     }
 }
 
-const DETECT_LOAD_NEXT_LEVEL: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let level_loader = entites.clone().find_map(|e| components.get_mut_component::<LevelLoader>(e)).unwrap();
-    let cam = &entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap().cam;
+const DETECT_LOAD_NEXT_LEVEL: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
+    let cam = &viewport.cam;
 
     const LOAD_DISTANCE: f32 = 10.0;
 
-    for e in entites {
-        if components.get_component::<Ladder>(e).is_some() {
-            let transform = components.get_component::<Transform>(e).unwrap();
-
-            let cam_xz = vec3(cam.pos.x, 0.0, cam.pos.z);
-            let ladder_xz = vec3(transform.get_pos().x, 0.0, transform.get_pos().z);
-            let xz_dist_to_player = (cam_xz - ladder_xz).len();
+    for (_, transform) in components.query_filtered::<&Transform, With<Ladder>>().iter_over(entities[0].iter()) {
+        let cam_xz = vec3(cam.pos.x, 0.0, cam.pos.z);
+        let ladder_xz = vec3(transform.get_pos().x, 0.0, transform.get_pos().z);
+        let xz_dist_to_player = (cam_xz - ladder_xz).len();
 
-            if xz_dist_to_player <= LOAD_DISTANCE {
-                level_loader.should_load = true;
-            }
+        if xz_dist_to_player <= LOAD_DISTANCE {
+            commands.send_event(LoadLevel {});
         }
     }
 };
 
-const MANAGE_CURSOR: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let render_engine = entites.clone().find_map(|e| components.get_mut_component::<VulkanRenderEngine>(e)).unwrap();
-    let cursor_manager = entites.clone().find_map(|e| components.get_mut_component::<CursorManager>(e)).unwrap();
+const MANAGE_CURSOR: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let mut render_engine = resources.get_mut::<VulkanRenderEngine>();
+    let mut cursor_manager = resources.get_mut::<CursorManager>().unwrap();
 
-    let esc_pressed = render_engine.is_key_pressed(VirtualKey::Escape);
+    let esc_pressed = render_engine.as_ref().is_some_and(|r| r.is_key_pressed(VirtualKey::Escape));
 
     const DEBUG_CURSOR: bool = false;
 
-    if let Ok(window) = render_engine.get_window_mut() {
+    if let Some(window) = render_engine.as_mut().and_then(|r| r.get_window_mut().ok()) {
         let rel_center_pos = vec2((window.get_width() / 2) as f32, (window.get_height() / 2) as f32);
         let window_screen_pos = window.get_screen_position();
         let cursor_screen_pos = window.get_mouse_screen_position().map(|p| *p);
@@ -1104,52 +1045,45 @@ const MANAGE_CURSOR: System = |entites: Iter<Entity>, components: &ComponentMana
     cursor_manager.cursor_delta.y = cursor_manager.cursor_delta.y as i32 as f32;
 };
 
-const UPDATE_SPRITE_ANIMATIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let gun_reload_timer = entites.clone()
-        .filter(|e| components.get_component::<GunReloadTimer>(e).is_some())
-        .map(|e| components.get_mut_component::<Timer>(e).unwrap())
-        .next()
-        .unwrap();
-    let player_entity = entites.clone().filter(|e| components.get_component::<Player>(e).is_some()).next().unwrap();
-
-    for e in entites {
-        if let Some(animation) = components.get_component::<SpriteAnimation>(e) {
-            let animation_timer = components.get_mut_component::<Timer>(e).unwrap();
-
-            if animation_timer.remaining_duration.is_none() {
-                // hacky af
-                if e != player_entity || gun_reload_timer.remaining_duration.is_none() {
-                    if let Some(base_texture) = animation.base {
-                        if components.get_component::<TextureBinding>(e).is_some() {
-                            commands.detach_component::<TextureBinding>(e);
-                        }
-                        commands.attach_component(e, base_texture);
-                    } else {
-                        animation_timer.reset();
+const UPDATE_SPRITE_ANIMATIONS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+    let gun_reload_timer = components.query_filtered::<&Timer, With<GunReloadTimer>>().single().unwrap();
+    let player_entity = components.query_filtered::<Entity, With<Player>>().single_entity().unwrap();
+
+    for (e, (animation, mut animation_timer)) in components.query::<(&SpriteAnimation, &mut Timer)>().iter_over(entities[0].iter()) {
+        if animation_timer.remaining_duration.is_none() {
+            // hacky af
+            if e != player_entity || gun_reload_timer.remaining_duration.is_none() {
+                if let Some(base_texture) = animation.base {
+                    if components.has_component::<TextureBinding>(&e) {
+                        commands.detach_component::<TextureBinding>(&e);
                     }
+                    commands.attach_component(&e, base_texture);
+                } else {
+                    animation_timer.reset();
                 }
             }
+        }
 
-            if animation_timer.remaining_duration.is_some() {
-                let curr_frame_index = (animation_timer.current_value.min(0.9999) * animation.frames.len() as f32) as usize;
-                let curr_frame = animation.frames[curr_frame_index];
+        if animation_timer.remaining_duration.is_some() {
+            let curr_frame_index = (animation_timer.current_value.min(0.9999) * animation.frames.len() as f32) as usize;
+            let curr_frame = animation.frames[curr_frame_index];
 
-                if components.get_component::<TextureBinding>(e).is_some() {
-                    commands.detach_component::<TextureBinding>(e);
-                }
-                commands.attach_component(e, curr_frame);
+            if components.has_component::<TextureBinding>(&e) {
+                commands.detach_component::<TextureBinding>(&e);
             }
+            commands.attach_component(&e, curr_frame);
         }
     }
 };
 
-const MOVE_CAMERA: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let render_engine = entites.clone().find_map(|e| components.get_component::<VulkanRenderEngine>(e)).unwrap();
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
+const MOVE_CAMERA: SystemFn = |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let render_engine = resources.get::<VulkanRenderEngine>();
+    let time_delta = resources.get::<TimeDelta>().unwrap();
     let delta_sec = time_delta.since_last_frame.as_secs_f32();
-    let cursor_manager = entites.clone().find_map(|e| components.get_mut_component::<CursorManager>(e)).unwrap();
-    let cam = &mut entites.clone().find_map(|e| components.get_mut_component::<Viewport2D>(e)).unwrap().cam;
-    let player = entites.clone().find_map(|e| components.get_mut_component::<Player>(e)).unwrap();
+    let cursor_manager = resources.get::<CursorManager>().unwrap();
+    let mut viewport = components.query::<&mut Viewport2D>().single().unwrap();
+    let cam = &mut viewport.cam;
+    let mut player = components.query::<&mut Player>().single().unwrap();
 
     const PLAYER_GRAVITY: f32 = -40.0;
     const MIN_PLAYER_HEIGHT: f32 = 15.0;
@@ -1158,7 +1092,7 @@ const MOVE_CAMERA: System = |entites: Iter<Entity>, components: &ComponentManage
 
     player.y_vel += PLAYER_GRAVITY * delta_sec;
 
-    if let Ok(window) = render_engine.get_window() {
+    if let Some(window) = render_engine.as_ref().and_then(|r| r.get_window().ok()) {
         if cursor_manager.is_locked {
             let mut move_dir = VEC_3_ZERO;
             let cam_right_norm = cam.dir.cross(&cam.up).normalized().unwrap();
@@ -1225,25 +1159,22 @@ const MOVE_CAMERA: System = |entites: Iter<Entity>, components: &ComponentManage
     }
 };
 
-const APPLY_PLAYER_WALL_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let cam = &mut entites.clone().find_map(|e| components.get_mut_component::<Viewport2D>(e)).unwrap().cam;
+const APPLY_PLAYER_WALL_COLLISIONS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, _: &mut ECSCommands| {
+    let mut viewport = components.query::<&mut Viewport2D>().single().unwrap();
+    let cam = &mut viewport.cam;
 
     const COLLISION_DIST: f32 = 5.0;
 
-    for e in entites {
-        if let Some(wall) = components.get_component::<Wall>(e) {
-            if wall.is_lowest_wall {
-                let wall_transform = components.get_mut_component::<Transform>(e).unwrap();
-
-                if let Some(collision) = get_wall_collision(&cam.pos, COLLISION_DIST, wall_transform) {
-                    cam.pos += collision;
-                }
+    for (_, (wall, wall_transform)) in components.query::<(&Wall, &Transform)>().iter_over(entities[0].iter()) {
+        if wall.is_lowest_wall {
+            if let Some(collision) = get_wall_collision(&cam.pos, COLLISION_DIST, &wall_transform) {
+                cam.pos += collision;
             }
         }
     }
 };
 
-fn get_wall_collision(point: &Vec3, collision_dist: f32, transform: &mut Transform) -> Option<Vec3> {
+fn get_wall_collision(point: &Vec3, collision_dist: f32, transform: &Transform) -> Option<Vec3> {
     let min_x = transform.get_pos().x - transform.get_scl().x / 2.0 - collision_dist;
     let max_x = transform.get_pos().x + transform.get_scl().x / 2.0 + collision_dist;
 
@@ -1276,176 +1207,151 @@ fn get_wall_collision(point: &Vec3, collision_dist: f32, transform: &mut Transfo
 }
 
 // Built-in
-const UPDATE_PARTICLES: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
-    let delta = time_delta.since_last_frame.as_secs_f32();
+const UPDATE_PARTICLES: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let delta = resources.get::<FixedTimestep>().unwrap().get_step().as_secs_f32();
 
-    for e in entites {
-        let transform = components.get_mut_component::<Transform>(e);
-        let particle = components.get_mut_component::<Particle>(e);
+    for (_, (mut transform, mut particle)) in components.query::<(&mut Transform, &mut Particle)>().iter_over(entities[0].iter()) {
+        let (transform, particle) = (&mut *transform, &mut *particle);
 
-        if transform.is_some() && particle.is_some() {
-            let transform = transform.unwrap();
-            let particle = particle.unwrap();
+        particle.acc = particle.force_accum / particle.mass;
+        particle.acc.y -= particle.gravity;
 
-            particle.acc = particle.force_accum / particle.mass;
-            particle.acc.y -= particle.gravity;
+        particle.vel += particle.acc * delta;
+        // Raising to the delta power makes the damping more realistic when frame times are inconsistent, especially when damping
+        //  is not terribly close to 0. However, this operation is expensive, so we wouldn't want to do it when we're applying this
+        //  to a huge number of particles, for example.
+        particle.vel *= particle.damping.powf(delta);
 
-            particle.vel += particle.acc * delta;
-            // Raising to the delta power makes the damping more realistic when frame times are inconsistent, especially when damping
-            //  is not terribly close to 0. However, this operation is expensive, so we wouldn't want to do it when we're applying this
-            //  to a huge number of particles, for example.
-            particle.vel *= particle.damping.powf(delta);
+        transform.set_pos(*transform.get_pos() + particle.vel * delta);
 
-            transform.set_pos(*transform.get_pos() + particle.vel * delta);
-
-            particle.force_accum = VEC_3_ZERO;
-        }
+        particle.force_accum = VEC_3_ZERO;
     }
 };
 
 // Built-in
-const UPDATE_RIGID_BODIES: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
-    let delta = time_delta.since_last_frame.as_secs_f32();
-
-    for e in entites {
-        let transform = components.get_mut_component::<Transform>(e);
-        let rigid_body = components.get_mut_component::<RigidBody>(e);
+const UPDATE_RIGID_BODIES: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let delta = resources.get::<FixedTimestep>().unwrap().get_step().as_secs_f32();
 
-        if transform.is_some() && rigid_body.is_some() {
-            let transform = transform.unwrap();
-            let rigid_body = rigid_body.unwrap();
+    for (_, (mut transform, mut rigid_body)) in components.query::<(&mut Transform, &mut RigidBody)>().iter_over(entities[0].iter()) {
+        let (transform, rigid_body) = (&mut *transform, &mut *rigid_body);
 
-            // Linear motion
-            if let Some(mass) = rigid_body.props.mass {
-                rigid_body.linear_acc = rigid_body.linear_force_accum / mass;
-                rigid_body.linear_acc.y -= rigid_body.gravity;
+        // Linear motion
+        if let Some(mass) = rigid_body.props.mass {
+            rigid_body.linear_acc = rigid_body.linear_force_accum / mass;
+            rigid_body.linear_acc.y -= rigid_body.gravity;
 
-                rigid_body.linear_vel += rigid_body.linear_acc * delta;
-                rigid_body.linear_vel *= rigid_body.linear_damping.powf(delta);
-
-                transform.set_pos(*transform.get_pos() + rigid_body.linear_vel * delta);
-            } else {
-                rigid_body.linear_acc = VEC_3_ZERO;
+            rigid_body.linear_vel += rigid_body.linear_acc * delta;
+            rigid_body.linear_vel *= rigid_body.linear_damping.powf(delta);
 
-                rigid_body.linear_vel = VEC_3_ZERO;
-            }
+            transform.set_pos(*transform.get_pos() + rigid_body.linear_vel * delta);
+        } else {
+            rigid_body.linear_acc = VEC_3_ZERO;
 
-            rigid_body.linear_force_accum = VEC_3_ZERO;
+            rigid_body.linear_vel = VEC_3_ZERO;
+        }
 
-            // Rotational motion
-            if let Some(inertia_tensor) = rigid_body.props.inertia_tensor {
-                let world_matrix = transform.to_world_mat().to_mat3();
-                let inverse_world_matrix = world_matrix.inverted().unwrap_or_else(|_| panic!("Internal error: failed to invert world matrix"));
-                let inverse_inertia_tensor_world = (world_matrix * inertia_tensor * inverse_world_matrix).inverted()
-                    .unwrap_or_else(|_| panic!("Internal error: failed to invert inertia tensor world transform"));
+        rigid_body.linear_force_accum = VEC_3_ZERO;
 
-                rigid_body.ang_acc = inverse_inertia_tensor_world * rigid_body.torque_accum;
+        // Rotational motion
+        if let Some(inertia_tensor) = rigid_body.props.inertia_tensor {
+            let world_matrix = transform.to_world_mat().to_mat3();
+            let inverse_world_matrix = world_matrix.inverted().unwrap_or_else(|_| panic!("Internal error: failed to invert world matrix"));
+            let inverse_inertia_tensor_world = (world_matrix * inertia_tensor * inverse_world_matrix).inverted()
+                .unwrap_or_else(|_| panic!("Internal error: failed to invert inertia tensor world transform"));
 
-                rigid_body.ang_vel += rigid_body.ang_acc * delta;
-                rigid_body.ang_vel *= rigid_body.ang_damping.powf(delta);
+            rigid_body.ang_acc = inverse_inertia_tensor_world * rigid_body.torque_accum;
 
-                transform.set_rot(apply_ang_vel(transform.get_rot(), &rigid_body.ang_vel, delta));
-            } else {
-                rigid_body.ang_acc = VEC_3_ZERO;
+            rigid_body.ang_vel += rigid_body.ang_acc * delta;
+            rigid_body.ang_vel *= rigid_body.ang_damping.powf(delta);
 
-                rigid_body.ang_vel = VEC_3_ZERO;
-            }
+            transform.set_rot(apply_ang_vel(transform.get_rot(), &rigid_body.ang_vel, delta));
+        } else {
+            rigid_body.ang_acc = VEC_3_ZERO;
 
-            rigid_body.torque_accum = VEC_3_ZERO;
+            rigid_body.ang_vel = VEC_3_ZERO;
         }
+
+        rigid_body.torque_accum = VEC_3_ZERO;
     }
 };
 
 // Built-in
-const UPDATE_QUAD_TREE: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let quad_tree = entites.clone().find_map(|e| components.get_mut_component::<QuadTree<BoundingSphere>>(e)).unwrap();
-
-    let entities_to_update = entites
-        .filter(|e| {
-            components.get_component::<Transform>(e).is_some()
-                && components.get_component::<RigidBody>(e).is_some()
-        })
-        .collect::<HashSet<_>>();
-
-    quad_tree.remove_not_in(&entities_to_update);
+const UPDATE_QUAD_TREE: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let mut quad_tree = resources.get_mut::<QuadTree<BoundingSphere>>().unwrap();
 
-    for e in entities_to_update {
-        let transform = components.get_component::<Transform>(e).unwrap();
-        let rigid_body = components.get_component::<RigidBody>(e).unwrap();
+    // Bodies stop being tracked as soon as they lose either component, including when they're destroyed
+    components.get_component_events::<RigidBody>().chain(components.get_component_events::<Transform>())
+        .filter(|event| event.kind == ComponentEventKind::Removed)
+        .for_each(|event| quad_tree.remove(&event.entity).unwrap_or_default());
 
-        if transform.is_pos_changed_since_last_frame() || transform.is_scl_changed_since_last_frame() {
-            quad_tree.remove(e).unwrap_or_default();
+    for (e, (transform, rigid_body)) in components.query_filtered::<(&Transform, &RigidBody), Changed<Transform>>().iter_over(entities[0].iter()) {
+        quad_tree.remove(&e).unwrap_or_default();
 
-            let bounding_sphere = BoundingSphere::from_transform(transform, rigid_body.props.bounding_radius);
+        let bounding_sphere = BoundingSphere::from_transform(&transform, rigid_body.props.bounding_radius);
 
-            quad_tree.insert(*e, bounding_sphere).unwrap_or_else(|e| panic!("Failed to insert bounding sphere into quad tree: {:?}", e));
-        }
+        quad_tree.insert(e, bounding_sphere).unwrap_or_else(|e| panic!("Failed to insert bounding sphere into quad tree: {:?}", e));
     }
 };
 
 // Built-in
-const DETECT_POTENTIAL_RIGID_BODY_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let quad_tree = entites.clone().find_map(|e| components.get_component::<QuadTree<BoundingSphere>>(e)).unwrap();
+const DETECT_POTENTIAL_RIGID_BODY_COLLISIONS: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let quad_tree = resources.get::<QuadTree<BoundingSphere>>().unwrap();
 
     let potential_collisions = quad_tree.get_potential_collisions();
 
     for c in potential_collisions {
-        let e = commands.create_entity();
-
-        commands.attach_provisional_component(&e, c);
+        commands.send_event(c);
     }
 };
 
 // Built-in
-const DETECT_RIGID_BODY_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let new_collisions = entites.clone()
-        .map(|e| components.get_component::<PotentialRigidBodyCollision>(e).map(|c| (e, c)))
-        .filter(|c| c.is_some())
-        .map(|c| c.unwrap())
-        .inspect(|(e, _)| commands.destroy_entity(e))
-        .map(|(_, c)| {
-            let transform_a = components.get_mut_component::<Transform>(&c.entity_a);
-            let transform_b = components.get_mut_component::<Transform>(&c.entity_b);
-
-            let mesh_binding_a = components.get_component::<MeshBinding>(&c.entity_a);
-            let mesh_binding_b = components.get_component::<MeshBinding>(&c.entity_b);
-
-            let mesh_a = mesh_binding_a.map(|binding| components.get_component::<Mesh>(&binding.mesh_wrapper.unwrap()))
-                .filter(|m| m.is_some()).map(|m| m.unwrap());
-            let mesh_b = mesh_binding_b.map(|binding| components.get_component::<Mesh>(&binding.mesh_wrapper.unwrap()))
-                .filter(|m| m.is_some()).map(|m| m.unwrap());
-
-            if transform_a.is_some() && transform_b.is_some() && mesh_a.is_some() && mesh_b.is_some() {
-                let transform_a = transform_a.unwrap();
-                let transform_b = transform_b.unwrap();
-                let mesh_a = mesh_a.unwrap();
-                let mesh_b = mesh_b.unwrap();
-
-                get_deepest_rigid_body_collision(
-                    (&c.entity_a, mesh_a),
-                    (&c.entity_b, mesh_b),
-                    transform_a,
-                    transform_b,
-                )
-            } else {
-                None
-            }
-        })
-        .filter(|c| c.is_some())
-        .map(|c| c.unwrap())
-        .collect::<HashSet<_>>();
+fn detect_rigid_body_collisions() -> impl System {
+    let mut potential_collision_reader = EventReader::<PotentialRigidBodyCollision>::new();
+
+    move |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+        let potential_collisions = resources.get::<Events<PotentialRigidBodyCollision>>().unwrap();
+
+        let new_collisions = potential_collisions.read(&mut potential_collision_reader)
+            .map(|c| {
+                let transform_a = components.get_component::<Transform>(&c.entity_a);
+                let transform_b = components.get_component::<Transform>(&c.entity_b);
 
-    const COLLISION_CACHE_TOLERANCE: f32 = -0.01;
+                let mesh_binding_a = components.get_component::<MeshBinding>(&c.entity_a);
+                let mesh_binding_b = components.get_component::<MeshBinding>(&c.entity_b);
+
+                let mesh_a = mesh_binding_a.map(|binding| components.get_component::<Mesh>(&binding.mesh_wrapper.unwrap()))
+                    .filter(|m| m.is_some()).map(|m| m.unwrap());
+                let mesh_b = mesh_binding_b.map(|binding| components.get_component::<Mesh>(&binding.mesh_wrapper.unwrap()))
+                    .filter(|m| m.is_some()).map(|m| m.unwrap());
 
-    for e in entites.clone() {
-        if let Some(collision) = components.get_component::<RigidBodyCollision>(e) {
-            if new_collisions.contains(collision) {
-                commands.destroy_entity(e);
+                if transform_a.is_some() && transform_b.is_some() && mesh_a.is_some() && mesh_b.is_some() {
+                    let transform_a = transform_a.unwrap();
+                    let transform_b = transform_b.unwrap();
+                    let mesh_a = mesh_a.unwrap();
+                    let mesh_b = mesh_b.unwrap();
+
+                    get_deepest_rigid_body_collision(
+                        (&c.entity_a, &mesh_a),
+                        (&c.entity_b, &mesh_b),
+                        &transform_a,
+                        &transform_b,
+                    )
+                } else {
+                    None
+                }
+            })
+            .filter(|c| c.is_some())
+            .map(|c| c.unwrap())
+            .collect::<HashSet<_>>();
+
+        const COLLISION_CACHE_TOLERANCE: f32 = -0.01;
+
+        for (e, collision) in components.query::<&RigidBodyCollision>().iter_over(entities[0].iter()) {
+            if new_collisions.contains(&*collision) {
+                commands.destroy_entity(&e);
             } else {
-                let transform_a = components.get_mut_component::<Transform>(&collision.rigid_body_a);
-                let transform_b = components.get_mut_component::<Transform>(&collision.rigid_body_b);
+                let transform_a = components.get_component::<Transform>(&collision.rigid_body_a);
+                let transform_b = components.get_component::<Transform>(&collision.rigid_body_b);
 
                 let mesh_binding_a = components.get_component::<MeshBinding>(&collision.rigid_body_a);
                 let mesh_binding_b = components.get_component::<MeshBinding>(&collision.rigid_body_b);
@@ -1479,13 +1385,13 @@ const DETECT_RIGID_BODY_COLLISIONS: System = |entites: Iter<Entity>, components:
                             transform_b.get_pos(),
                             COLLISION_CACHE_TOLERANCE,
                         ) {
-                            commands.detach_component::<RigidBodyCollision>(e);
+                            commands.detach_component::<RigidBodyCollision>(&e);
 
                             retained_collision.point_features = Some(point_features);
 
-                            commands.attach_component(e, retained_collision);
+                            commands.attach_component(&e, retained_collision);
                         } else {
-                            commands.destroy_entity(e);
+                            commands.destroy_entity(&e);
                         }
                     } else if let Some(edge_features) = collision.edge_features {
                         let vertex_a_0 = &mesh_a.vertices[edge_features.0.0 as usize];
@@ -1504,39 +1410,34 @@ const DETECT_RIGID_BODY_COLLISIONS: System = |entites: Iter<Entity>, components:
                             (&vertex_pos_b_0, &vertex_pos_b_1),
                             COLLISION_CACHE_TOLERANCE,
                         ) {
-                            commands.detach_component::<RigidBodyCollision>(e);
+                            commands.detach_component::<RigidBodyCollision>(&e);
 
                             retained_collision.edge_features = Some(edge_features);
 
-                            commands.attach_component(e, retained_collision);
+                            commands.attach_component(&e, retained_collision);
                         } else {
-                            commands.destroy_entity(e);
+                            commands.destroy_entity(&e);
                         }
                     } else {
                         panic!("Rigid body collision between entities {:?} and {:?} has no collision features", &collision.rigid_body_a, &collision.rigid_body_b);
                     }
                 } else {
-                    commands.destroy_entity(e);
+                    commands.destroy_entity(&e);
                 }
             }
         }
-    }
 
-    for c in new_collisions.into_iter() {
-        let collision_entity = commands.create_entity();
+        for c in new_collisions.into_iter() {
+            let collision_entity = commands.create_entity();
 
-        commands.attach_provisional_component(&collision_entity, c);
+            commands.attach_provisional_component(&collision_entity, c);
+        }
     }
-};
+}
 
 // Built-in
-const DETECT_PARTICLE_CABLE_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let cables = entites
-        .map(|e| (e, components.get_component::<ParticleCable>(e)))
-        .filter(|(_, c)| c.is_some())
-        .map(|(e, c)| (e, c.unwrap()));
-
-    for (e, c) in cables {
+const DETECT_PARTICLE_CABLE_COLLISIONS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+    for (e, c) in components.query::<&ParticleCable>().iter_over(entities[0].iter()) {
         let transform_a = components.get_component::<Transform>(&c.particle_a);
         let transform_b = components.get_component::<Transform>(&c.particle_b);
 
@@ -1557,24 +1458,18 @@ const DETECT_PARTICLE_CABLE_COLLISIONS: System = |entites: Iter<Entity>, compone
                         length - c.max_length,
                     );
 
-                    let collision_entity = commands.create_entity();
-                    commands.attach_provisional_component(&collision_entity, collision);
+                    commands.send_event(collision);
                 }
             }
         } else {
-            commands.destroy_entity(e);
+            commands.destroy_entity(&e);
         }
     }
 };
 
 // Built-in
-const DETECT_PARTICLE_ROD_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let rods = entites
-        .map(|e| (e, components.get_component::<ParticleRod>(e)))
-        .filter(|(_, r)| r.is_some())
-        .map(|(e, r)| (e, r.unwrap()));
-
-    for (e, r) in rods {
+const DETECT_PARTICLE_ROD_COLLISIONS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, _: &Resources, commands: &mut ECSCommands| {
+    for (e, r) in components.query::<&ParticleRod>().iter_over(entities[0].iter()) {
         let transform_a = components.get_component::<Transform>(&r.particle_a);
         let transform_b = components.get_component::<Transform>(&r.particle_b);
 
@@ -1601,54 +1496,41 @@ const DETECT_PARTICLE_ROD_COLLISIONS: System = |entites: Iter<Entity>, component
                     penetration,
                 );
 
-                let collision_entity = commands.create_entity();
-                commands.attach_provisional_component(&collision_entity, collision);
+                commands.send_event(collision);
             }
         } else {
-            commands.destroy_entity(e);
+            commands.destroy_entity(&e);
         }
     }
 };
 
 // Built-in
-const RESOLVE_PARTICLE_COLLISIONS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
-    let delta_sec = time_delta.since_last_frame.as_secs_f32();
-
-    let collisions = entites
-        .map(|e| (e, components.get_component::<ParticleCollision>(e)))
-        .filter(|(_, c)| c.is_some())
-        .map(|(e, c)| (e, c.unwrap()))
-        .into_iter();
-
-    for (e, c) in collisions.clone() {
-        if !is_particle_collision_valid(c, components) {
-            commands.destroy_entity(e);
+fn resolve_particle_collisions() -> impl System {
+    let mut collision_reader = EventReader::<ParticleCollision>::new();
+
+    move |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+        let delta_sec = resources.get::<FixedTimestep>().unwrap().get_step().as_secs_f32();
+        let collisions = resources.get::<Events<ParticleCollision>>().unwrap();
+
+        let mut collisions = collisions.read(&mut collision_reader)
+            .filter(|c| is_particle_collision_valid(c, components))
+            .collect::<Vec<_>>();
+
+        collisions.sort_unstable_by(|c0, c1|
+            calculate_separating_velocity(c0, components)
+                .partial_cmp(&calculate_separating_velocity(c1, components))
+                .unwrap_or(Ordering::Less)
+        );
+
+        // NOTE: Since we're not recalculating collisions here, we can't use multiple iterations. Otherwise, resolve_iterpenetration would move
+        //  the particles with every iteration for the same collision, even if they're no longer penetrating after the first iteration. So, just
+        //  sort the collisions by separation velocity, then resolve them each once.
+        for c in collisions.iter() {
+            resolve_velocity(c, components, delta_sec);
+            resolve_interpenetration(c, components);
         }
     }
-
-    let mut collisions = collisions
-        .filter(|(_, c)| is_particle_collision_valid(c, components))
-        .collect::<Vec<_>>();
-
-    collisions.sort_unstable_by(|(_, c0), (_, c1)|
-        calculate_separating_velocity(c0, components)
-            .partial_cmp(&calculate_separating_velocity(c1, components))
-            .unwrap_or(Ordering::Less)
-    );
-
-    // NOTE: Since we're not recalculating collisions here, we can't use multiple iterations. Otherwise, resolve_iterpenetration would move
-    //  the particles with every iteration for the same collision, even if they're no longer penetrating after the first iteration. So, just
-    //  sort the collisions by separation velocity, then resolve them each once.
-    for (_, c) in collisions.clone() {
-        resolve_velocity(&c, components, delta_sec);
-        resolve_interpenetration(&c, components);
-    }
-
-    for (e, _) in collisions {
-        commands.destroy_entity(e);
-    }
-};
+}
 
 fn is_particle_collision_valid(collision: &ParticleCollision, components: &ComponentManager) -> bool {
     components.get_component::<Particle>(&collision.particle_a).is_some()
@@ -1675,7 +1557,7 @@ fn resolve_velocity(collision: &ParticleCollision, components: &ComponentManager
     let sep_vel = calculate_separating_velocity(collision, components);
 
     if sep_vel < f32::EPSILON {
-        let particle_a = components.get_mut_component::<Particle>(&collision.particle_a)
+        let mut particle_a = components.get_mut_component::<Particle>(&collision.particle_a)
             .unwrap_or_else(|| panic!("Internal error: no Particle component for entity {:?}", &collision.particle_a));
         let particle_b = collision.particle_b.map(|b| components.get_mut_component::<Particle>(&b)
             .unwrap_or_else(|| panic!("Internal error: no Particle component for entity {:?}", &b)));
@@ -1697,7 +1579,7 @@ fn resolve_velocity(collision: &ParticleCollision, components: &ComponentManager
 
         particle_a.vel += mass_factor_a * delta_sep_vel * collision.normal;
 
-        if let Some(b) = particle_b {
+        if let Some(mut b) = particle_b {
             let mass_factor_b = particle_a.mass / (particle_a.mass + b.mass);
 
             b.vel += mass_factor_b * delta_sep_vel * -collision.normal;
@@ -1712,44 +1594,34 @@ fn resolve_interpenetration(collision: &ParticleCollision, components: &Componen
         let particle_b = collision.particle_b.map(|b| components.get_component::<Particle>(&b)
             .unwrap_or_else(|| panic!("Internal error: no Particle component for entity {:?}", &b)));
 
-        let transform_a = components.get_mut_component::<Transform>(&collision.particle_a)
+        let mut transform_a = components.get_mut_component::<Transform>(&collision.particle_a)
             .unwrap_or_else(|| panic!("Internal error: no Transform component for entity {:?}", &collision.particle_a));
         let transform_b = collision.particle_b.map(|b| components.get_mut_component::<Transform>(&b)
             .unwrap_or_else(|| panic!("Internal error: no Transform component for entity {:?}", &b)));
 
-        let mass_factor_a = particle_b.map(|b| b.mass / (particle_a.mass + b.mass)).unwrap_or(1.0);
+        let mass_factor_a = particle_b.as_ref().map(|b| b.mass / (particle_a.mass + b.mass)).unwrap_or(1.0);
 
-        transform_a.set_pos(*transform_a.get_pos() + mass_factor_a * collision.penetration * collision.normal);
+        let pos_a = *transform_a.get_pos();
+        transform_a.set_pos(pos_a + mass_factor_a * collision.penetration * collision.normal);
 
         if let Some(particle_b) = particle_b {
-            let transform_b = transform_b.unwrap();
+            let mut transform_b = transform_b.unwrap();
 
             let mass_factor_b = particle_a.mass / (particle_a.mass + particle_b.mass);
 
-            transform_b.set_pos(*transform_b.get_pos() + mass_factor_b * collision.penetration * -collision.normal);
+            let pos_b = *transform_b.get_pos();
+            transform_b.set_pos(pos_b + mass_factor_b * collision.penetration * -collision.normal);
         }
     }
 }
 
-const UPDATE_GUI_ELEMENTS: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
-    let render_engine = entites.clone().find_map(|e| components.get_component::<VulkanRenderEngine>(e)).unwrap();
-    let gun_animation_timer = entites.clone()
-        .filter(|e| components.get_component::<GunAnimationTimer>(e).is_some())
-        .map(|e| components.get_mut_component::<Timer>(e).unwrap())
-        .next()
-        .unwrap();
-    let gun_reload_timer = entites.clone()
-        .filter(|e| components.get_component::<GunReloadTimer>(e).is_some())
-        .map(|e| components.get_mut_component::<Timer>(e).unwrap())
-        .next()
-        .unwrap();
-    let digits_texture_owner = entites.clone().find_map(|e| components.get_component::<DigitsTextureOwner>(e)).unwrap();
-    let player = entites.clone().find_map(|e| components.get_component::<Player>(e)).unwrap();
-    let gun_animation = entites.clone()
-        .filter(|e| components.get_component::<GunTextureOwner>(e).is_some())
-        .map(|e| components.get_component::<SpriteAnimation>(e).unwrap())
-        .next()
-        .unwrap();
+const UPDATE_GUI_ELEMENTS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
+    let render_engine = resources.get::<VulkanRenderEngine>().unwrap();
+    let gun_animation_timer = components.query_filtered::<&Timer, With<GunAnimationTimer>>().single().unwrap();
+    let gun_reload_timer = components.query_filtered::<&Timer, With<GunReloadTimer>>().single().unwrap();
+    let digits_texture_owner = resources.get::<DigitsTextureOwner>().unwrap();
+    let player = components.query::<&Player>().single().unwrap();
+    let gun_animation = components.query_filtered::<&SpriteAnimation, With<GunTextureOwner>>().single().unwrap();
 
     const CROSSHAIR_SIZE: f32 = 0.08;
     const GUI_LABEL_SIZE: f32 = 0.1;
@@ -1764,206 +1636,193 @@ const UPDATE_GUI_ELEMENTS: System = |entites: Iter<Entity>, components: &Compone
 
         let curr_health = player.curr_health as usize;
 
-        for e in entites {
-            if let Some(gui_element) = components.get_mut_component::<GuiElement>(e) {
-                if gui_element.id == "crosshair" {
-                    gui_element.dimensions = vec2(CROSSHAIR_SIZE / aspect_ratio, CROSSHAIR_SIZE);
-                } else if gui_element.id == "gun" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
+        for (e, mut gui_element) in components.query::<&mut GuiElement>().iter_over(entities[0].iter()) {
+            if gui_element.id == "crosshair" {
+                gui_element.dimensions = vec2(CROSSHAIR_SIZE / aspect_ratio, CROSSHAIR_SIZE);
+            } else if gui_element.id == "gun" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
+
+                if gun_reload_timer.remaining_duration.is_none() {
+                    if gun_animation_timer.remaining_duration.is_some() {
+                        let curr_frame = (gun_animation_timer.current_value.min(0.9999) * gun_animation.frames.len() as f32) as usize;
+
+                        commands.attach_component(&e, gun_animation.frames[curr_frame]);
+                    } else {
+                        commands.attach_component(&e, gun_animation.base.unwrap());
                     }
 
-                    if gun_reload_timer.remaining_duration.is_none() {
-                        if gun_animation_timer.remaining_duration.is_some() {
-                            let curr_frame = (gun_animation_timer.current_value.min(0.9999) * gun_animation.frames.len() as f32) as usize;
+                    gui_element.dimensions = vec2(GUN_SIZE / aspect_ratio, GUN_SIZE);
+                    gui_element.position = vec2(GUN_OFFSET_X, 1.0 - gui_element.dimensions.y / 2.0 + 0.01);
+                }
+            } else if gui_element.id == "ammo_counter_0" {
+                let digit = player.ammo_count % 10;
 
-                            commands.attach_component(e, gun_animation.frames[curr_frame]);
-                        } else {
-                            commands.attach_component(e, gun_animation.base.unwrap());
-                        }
+                let texture_binding = digits_texture_owner.digits[digit];
+
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
+                commands.attach_component(&e, texture_binding);
+
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE * DIGIT_WIDTH_TO_HEIGHT / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(1.0 - gui_element.dimensions.x / 2.0 - 3.0 * x_padding - GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+            } else if gui_element.id == "ammo_counter_1" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
+
+                if player.ammo_count >= 10 {
+                    let digit = player.ammo_count / 10;
 
-                        gui_element.dimensions = vec2(GUN_SIZE / aspect_ratio, GUN_SIZE);
-                        gui_element.position = vec2(GUN_OFFSET_X, 1.0 - gui_element.dimensions.y / 2.0 + 0.01);
+                    if digit >= 10 {
+                        panic!("Only 2 digits ammo counter is supported");
                     }
-                } else if gui_element.id == "ammo_counter_0" {
-                    let digit = player.ammo_count % 10;
 
                     let texture_binding = digits_texture_owner.digits[digit];
 
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
-                    commands.attach_component(e, texture_binding);
+                    commands.attach_component(&e, texture_binding);
 
                     gui_element.dimensions = vec2(GUI_LABEL_SIZE * DIGIT_WIDTH_TO_HEIGHT / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(1.0 - gui_element.dimensions.x / 2.0 - 3.0 * x_padding - GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                } else if gui_element.id == "ammo_counter_1" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
-
-                    if player.ammo_count >= 10 {
-                        let digit = player.ammo_count / 10;
+                    gui_element.position = vec2(1.0 - gui_element.dimensions.x * 3.0 / 2.0 - 3.0 * x_padding - GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+                }
+            } else if gui_element.id == "ammo_label" {
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(1.0 - gui_element.dimensions.x / 2.0 - x_padding, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+            } else if gui_element.id == "health_label" {
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + x_padding, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+            } else if gui_element.id == "level_label" {
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + x_padding, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
+            } else if gui_element.id == "health_counter_0" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
 
-                        if digit >= 10 {
-                            panic!("Only 2 digits ammo counter is supported");
-                        }
+                let mut digit = curr_health / 100;
 
-                        let texture_binding = digits_texture_owner.digits[digit];
+                if digit >= 10 {
+                    panic!("Only 3 digit health is supported");
+                }
 
-                        commands.attach_component(e, texture_binding);
+                if digit <= 0 {
+                    digit = curr_health / 10;
+                }
 
-                        gui_element.dimensions = vec2(GUI_LABEL_SIZE * DIGIT_WIDTH_TO_HEIGHT / aspect_ratio, GUI_LABEL_SIZE);
-                        gui_element.position = vec2(1.0 - gui_element.dimensions.x * 3.0 / 2.0 - 3.0 * x_padding - GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                    }
-                } else if gui_element.id == "ammo_label" {
-                    gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(1.0 - gui_element.dimensions.x / 2.0 - x_padding, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                } else if gui_element.id == "health_label" {
-                    gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + x_padding, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                } else if gui_element.id == "level_label" {
-                    gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + x_padding, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
-                } else if gui_element.id == "health_counter_0" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
+                if digit <= 0 {
+                    digit = curr_health;
+                }
 
-                    let mut digit = curr_health / 100;
+                let texture_binding = digits_texture_owner.digits[digit];
 
-                    if digit >= 10 {
-                        panic!("Only 3 digit health is supported");
-                    }
+                commands.attach_component(&e, texture_binding);
 
-                    if digit <= 0 {
-                        digit = curr_health / 10;
-                    }
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+            } else if gui_element.id == "health_counter_1" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
 
-                    if digit <= 0 {
-                        digit = curr_health;
-                    }
+                if curr_health >= 10 {
+                    let digit = if curr_health >= 100 {
+                        (curr_health / 10) % 10
+                    } else {
+                        curr_health % 10
+                    };
 
                     let texture_binding = digits_texture_owner.digits[digit];
 
-                    commands.attach_component(e, texture_binding);
+                    commands.attach_component(&e, texture_binding);
 
                     gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                } else if gui_element.id == "health_counter_1" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
+                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 3.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+                }
+            } else if gui_element.id == "health_counter_2" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
 
-                    if curr_health >= 10 {
-                        let digit = if curr_health >= 100 {
-                            (curr_health / 10) % 10
-                        } else {
-                            curr_health % 10
-                        };
+                if curr_health >= 100 {
+                    let digit = curr_health % 100;
 
-                        let texture_binding = digits_texture_owner.digits[digit];
+                    let texture_binding = digits_texture_owner.digits[digit];
 
-                        commands.attach_component(e, texture_binding);
+                    commands.attach_component(&e, texture_binding);
 
-                        gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                        gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 3.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                    }
-                } else if gui_element.id == "health_counter_2" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
+                    gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 5.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
+                }
+            } else if gui_element.id == "level_counter_0" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
 
-                    if curr_health >= 100 {
-                        let digit = curr_health % 100;
+                let mut digit = player.curr_level / 10;
 
-                        let texture_binding = digits_texture_owner.digits[digit];
+                if digit >= 10 {
+                    panic!("Only 2 digit level counter is supported");
+                }
 
-                        commands.attach_component(e, texture_binding);
+                if digit <= 0 {
+                    digit = player.curr_level % 10;
+                }
 
-                        gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                        gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 5.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, 1.0 - gui_element.dimensions.y / 2.0 - Y_PADDING);
-                    }
-                } else if gui_element.id == "level_counter_0" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
+                let texture_binding = digits_texture_owner.digits[digit];
 
-                    let mut digit = player.curr_level / 10;
+                commands.attach_component(&e, texture_binding);
 
-                    if digit >= 10 {
-                        panic!("Only 2 digit level counter is supported");
-                    }
+                gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
+                gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
+            } else if gui_element.id == "level_counter_1" {
+                if components.has_component::<TextureBinding>(&e) {
+                    commands.detach_component::<TextureBinding>(&e);
+                }
 
-                    if digit <= 0 {
-                        digit = player.curr_level % 10;
-                    }
+                if player.curr_level >= 10 {
+                    let digit = player.curr_level % 10;
 
                     let texture_binding = digits_texture_owner.digits[digit];
 
-                    commands.attach_component(e, texture_binding);
+                    commands.attach_component(&e, texture_binding);
 
                     gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
-                } else if gui_element.id == "level_counter_1" {
-                    if components.get_component::<TextureBinding>(e).is_some() {
-                        commands.detach_component::<TextureBinding>(e);
-                    }
-
-                    if player.curr_level >= 10 {
-                        let digit = player.curr_level % 10;
-
-                        let texture_binding = digits_texture_owner.digits[digit];
-
-                        commands.attach_component(e, texture_binding);
-
-                        gui_element.dimensions = vec2(GUI_LABEL_SIZE / aspect_ratio, GUI_LABEL_SIZE);
-                        gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 3.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
-                    }
-                } else {
-                    panic!("Bad GUI element ID {:?}", gui_element.id);
+                    gui_element.position = vec2(-1.0 + gui_element.dimensions.x * 3.0 / 2.0 + 3.0 * x_padding + GUI_LABEL_SIZE / 2.0, -1.0 + gui_element.dimensions.y / 2.0 + Y_PADDING);
                 }
+            } else {
+                panic!("Bad GUI element ID {:?}", gui_element.id);
             }
         }
     }
 };
 
 // Built-in
-const SYNC_RENDER_STATE: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let render_engine = entites.clone().find_map(|e| components.get_mut_component::<VulkanRenderEngine>(e)).unwrap();
-    let viewport = entites.clone().find_map(|e| components.get_component::<Viewport2D>(e)).unwrap();
+const SYNC_RENDER_STATE: SystemFn = |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let mut render_engine = resources.get_mut::<VulkanRenderEngine>().unwrap();
+    let viewport = components.query::<&Viewport2D>().single().unwrap();
     let cam = &viewport.cam;
-    let quad_mesh_id = entites.clone()
-        .filter(|e| components.get_component::<QuadMeshOwner>(e).is_some())
-        .map(|e| components.get_component::<MeshBinding>(e).unwrap())
-        .next()
-        .unwrap().id.unwrap();
+    let quad_mesh_id = components.query_filtered::<&MeshBinding, With<QuadMeshOwner>>().single().unwrap().id.unwrap();
 
     const DIST_THRESHOLD: f32 = 150.0;
 
-    let entity_states = entites.clone().filter(|e|
-        components.get_component::<Transform>(e).is_some()
-        && components.get_component::<MeshBinding>(e).is_some()
-        && components.get_component::<TextureBinding>(e).is_some())
-    .filter(|e| {
-        let transform = components.get_mut_component::<Transform>(e).unwrap();
-
-        (*transform.get_pos() - cam.pos).len() <= DIST_THRESHOLD
-    })
-    .map(|e| EntityRenderState {
-        world: *components.get_mut_component::<Transform>(e).unwrap().to_world_mat(),
-        mesh_id: components.get_component::<MeshBinding>(e).unwrap().id.unwrap(),
-        texture_id: components.get_component::<TextureBinding>(e).unwrap().id.unwrap(),
-        color: WHITE,
-    }).collect();
-
-    let gui_states = entites.clone()
-        .filter(|e| components.get_component::<TextureBinding>(e).is_some() && components.get_component::<GuiElement>(e).is_some())
-        .map(|e| GuiState {
+    // Entities with a GlobalTransform are rendered wherever their hierarchy puts them
+    let entity_states = components.query::<(&Transform, Option<&GlobalTransform>, &MeshBinding, &TextureBinding)>().iter()
+        .filter(|(_, (transform, global_transform, _, _))| (global_transform.as_ref().map_or(*transform.get_pos(), |g| g.get_pos()) - cam.pos).len() <= DIST_THRESHOLD)
+        .map(|(_, (transform, global_transform, mesh_binding, texture_binding))| EntityRenderState {
+            world: global_transform.map_or_else(|| *transform.to_world_mat(), |g| *g.get_world_mat()),
+            mesh_id: mesh_binding.id.unwrap(),
+            texture_id: texture_binding.id.unwrap(),
+            color: WHITE,
+        }).collect();
+
+    let gui_states = components.query::<(&TextureBinding, &GuiElement)>().iter()
+        .map(|(_, (texture_binding, gui_element))| GuiState {
             mesh_id: quad_mesh_id,
-            texture_id: components.get_component::<TextureBinding>(e).unwrap().id.unwrap(),
-            position: components.get_component::<GuiElement>(e).unwrap().position,
-            dimensions: components.get_component::<GuiElement>(e).unwrap().dimensions,
+            texture_id: texture_binding.id.unwrap(),
+            position: gui_element.position,
+            dimensions: gui_element.dimensions,
         }).collect();
 
     let aspect_ratio = render_engine.get_window().and_then(|w| {
@@ -1982,24 +1841,20 @@ const SYNC_RENDER_STATE: System = |entites: Iter<Entity>, components: &Component
 };
 
 // Built-in
-const UPDATE_TIMERS: System = |entites: Iter<Entity>, components: &ComponentManager, _: &mut ECSCommands| {
-    let time_delta = entites.clone().find_map(|e| components.get_component::<TimeDelta>(e)).unwrap();
+const UPDATE_TIMERS: SystemFn = |entities: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+    let time_delta = resources.get::<TimeDelta>().unwrap();
 
-    for e in entites {
-        if let Some(timer) = components.get_mut_component::<Timer>(e) {
-            timer.update(&time_delta.since_last_frame);
-        }
+    for (_, mut timer) in components.query::<&mut Timer>().iter_over(entities[0].iter()) {
+        timer.update(&time_delta.since_last_frame);
     }
 };
 
 // Built-in
-const SHUTDOWN_RENDER_ENGINE: System = |entites: Iter<Entity>, components: &ComponentManager, commands: &mut ECSCommands| {
+const SHUTDOWN_RENDER_ENGINE: SystemFn = |_: &[HashSet<Entity>], _: &ComponentManager, resources: &Resources, commands: &mut ECSCommands| {
     if commands.is_shutting_down() {
-        entites.for_each(|e| {
-            let render_engine = components.get_mut_component::<VulkanRenderEngine>(e).unwrap();
-
+        if let Some(mut render_engine) = resources.get_mut::<VulkanRenderEngine>() {
             render_engine.join_render_thread().unwrap_or_else(|e| panic!("{}", e));
-        });
+        }
     }
 };
 
@@ -2039,9 +1894,8 @@ struct CursorManager {
     cursor_delta: Vec2,
 }
 
-impl Component for CursorManager {}
-impl ComponentActions for CursorManager {}
 
+#[derive(Clone)]
 struct Player {
     y_vel: f32,
     is_jumping: bool,
@@ -2059,19 +1913,65 @@ struct Player {
 impl Component for Player {}
 impl ComponentActions for Player {}
 
+impl SerializableComponent for Player {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write(self.y_vel);
+        writer.write(self.is_jumping);
+        writer.write(self.curr_health);
+        writer.write(self.level_width);
+        writer.write(self.level_height);
+        writer.write(self.spawn_chance);
+        writer.write(self.baddie_cap);
+        writer.write(self.ammo_count);
+        writer.write(self.max_ammo);
+        writer.write(self.is_reloading);
+        writer.write(self.curr_level);
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Self {
+            y_vel: reader.read()?,
+            is_jumping: reader.read()?,
+            curr_health: reader.read()?,
+            level_width: reader.read()?,
+            level_height: reader.read()?,
+            spawn_chance: reader.read()?,
+            baddie_cap: reader.read()?,
+            ammo_count: reader.read()?,
+            max_ammo: reader.read()?,
+            is_reloading: reader.read()?,
+            curr_level: reader.read()?,
+        })
+    }
+}
+
+struct LevelPrefabs {
+    block: Prefab,
+    baddie: Prefab,
+}
+
 struct LevelLoader {
-    should_load: bool,
     next_level_id: usize,
 }
 
-impl Component for LevelLoader {}
-impl ComponentActions for LevelLoader {}
+struct LoadLevel {}
 
+
+#[derive(Clone)]
 struct LevelEntity {}
 
 impl Component for LevelEntity {}
 impl ComponentActions for LevelEntity {}
 
+impl SerializableComponent for LevelEntity {
+    fn serialize(&self, _writer: &mut SnapshotWriter) {}
+
+    fn deserialize(_reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+#[derive(Clone)]
 struct Baddie {
     is_active: bool,
 }
@@ -2079,6 +1979,16 @@ struct Baddie {
 impl Component for Baddie {}
 impl ComponentActions for Baddie {}
 
+impl SerializableComponent for Baddie {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        writer.write(self.is_active);
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        Ok(Self { is_active: reader.read()? })
+    }
+}
+
 struct DeadBaddie {
     vel: Vec3,
 }
@@ -2121,7 +2031,6 @@ struct SpriteAnimation {
 impl Component for SpriteAnimation {}
 impl ComponentActions for SpriteAnimation {}
 
-// Yikes, need a parentage system
 struct GunAnimationTimer {}
 
 impl Component for GunAnimationTimer {}
@@ -2136,8 +2045,6 @@ struct DigitsTextureOwner {
     digits: Vec<TextureBinding>,
 }
 
-impl Component for DigitsTextureOwner {}
-impl ComponentActions for DigitsTextureOwner {}
 
 struct LadderTextureOwner {}
 
@@ -2148,3 +2055,72 @@ struct Ladder {}
 
 impl Component for Ladder {}
 impl ComponentActions for Ladder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD_POSITIONS: SystemFn = |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+        let viewport = components.query::<&Viewport2D>().single();
+        let particle_transform = components.query_filtered::<&Transform, With<Particle>>().single();
+
+        if let (Some(viewport), Some(particle_transform)) = (viewport, particle_transform) {
+            resources.get_mut::<Vec<(Vec3, Vec3)>>().unwrap().push((viewport.cam.pos, *particle_transform.get_pos()));
+        }
+    };
+
+    #[test]
+    fn headless_scene_runs_movement_and_physics_under_app() {
+        let mut ecs = init_ecs();
+        create_headless_scene(&mut ecs);
+
+        let particle = ecs.create_entity();
+        ecs.attach_provisional_component(&particle, Transform::new(VEC_3_ZERO, QUAT_IDENTITY, IDENTITY_SCALE_VEC));
+        ecs.attach_provisional_component(&particle, Particle::new(vec3(6.0, 0.0, 0.0), 1.0, 1.0, 0.0));
+        ecs.insert_resource(Vec::<(Vec3, Vec3)>::new());
+        ecs.register_system(RECORD_POSITIONS.named("RECORD_POSITIONS"), vec![], SystemStage::PostUpdate);
+
+        // Frames are exactly one physics step apart, so every frame after the first one runs the Physics stage once
+        let mut app = App::new(ecs).with_clock(Clock::manual(Duration::from_secs_f32(1.0 / 60.0)));
+        assert_eq!(app.run_frames(61), 61);
+
+        let positions = app.get_ecs().get_resource::<Vec<(Vec3, Vec3)>>().unwrap();
+        let (cam_pos, particle_pos) = positions.last().unwrap();
+
+        // Gravity keeps the player at the minimum height, and the particle has moved for a second
+        assert_eq!(cam_pos.y, 15.0);
+        assert!((particle_pos.x - 6.0).abs() < 0.01);
+        assert_eq!(particle_pos.y, 0.0);
+    }
+
+    #[test]
+    fn transforms_propagate_from_roots_without_a_transform() {
+        let mut ecs = init_ecs();
+        ecs.register_system(PROPAGATE_TRANSFORMS.named("PROPAGATE_TRANSFORMS"), vec![], SystemStage::Render);
+
+        let group = ecs.create_entity();
+        let child = ecs.create_entity();
+        ecs.attach_provisional_component(&child, Parent::new_provisional(group));
+        ecs.attach_provisional_component(&child, Transform::new(vec3(1.0, 2.0, 3.0), QUAT_IDENTITY, IDENTITY_SCALE_VEC));
+        ecs.attach_provisional_component(&child, GlobalTransform::default());
+        let grandchild = ecs.create_entity();
+        ecs.attach_provisional_component(&grandchild, Parent::new_provisional(child));
+        ecs.attach_provisional_component(&grandchild, Transform::new(vec3(10.0, 0.0, 0.0), QUAT_IDENTITY, IDENTITY_SCALE_VEC));
+        ecs.attach_provisional_component(&grandchild, GlobalTransform::default());
+        let loose = ecs.create_entity();
+        ecs.attach_provisional_component(&loose, Transform::new(vec3(4.0, 5.0, 6.0), QUAT_IDENTITY, IDENTITY_SCALE_VEC));
+        ecs.attach_provisional_component(&loose, GlobalTransform::default());
+        ecs.invoke_systems();
+
+        let entities: Vec<Entity> = [child, grandchild, loose].iter().map(|e| ecs.resolve(e).unwrap()).collect();
+        ecs.insert_resource(Vec::<Vec3>::new());
+        ecs.register_system(move |_: &[HashSet<Entity>], components: &ComponentManager, resources: &Resources, _: &mut ECSCommands| {
+            *resources.get_mut::<Vec<Vec3>>().unwrap() = entities.iter()
+                .map(|e| components.get_component::<GlobalTransform>(e).unwrap().get_pos())
+                .collect();
+        }, vec![], SystemStage::PostUpdate);
+        ecs.invoke_systems();
+
+        assert_eq!(*ecs.get_resource::<Vec<Vec3>>().unwrap(), vec![vec3(1.0, 2.0, 3.0), vec3(11.0, 2.0, 3.0), vec3(4.0, 5.0, 6.0)]);
+    }
+}
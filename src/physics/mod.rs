@@ -4,12 +4,14 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
-use crate::core::{Camera, Transform};
+use crate::core::{read_vec3, write_vec3, Camera, Transform};
 use crate::core::mesh::{Edge, Face, Mesh, Vertex};
 use crate::ecs::{ComponentActions, ProvisionalEntity};
 use crate::ecs::component::{Component, ComponentManager};
 use crate::ecs::entity::Entity;
-use crate::math::{get_proj_matrix, mat3, vec3, vec4, Mat3, Quat, Vec2, Vec3, VEC_3_X_AXIS, VEC_3_Y_AXIS, VEC_3_ZERO};
+use crate::ecs::error::EcsError;
+use crate::ecs::snapshot::{SerializableComponent, SnapshotReader, SnapshotWriter};
+use crate::math::{get_proj_matrix, mat3, vec3, vec4, Mat3, Quat, Vec2, Vec3, VEC_3_X_AXIS, VEC_3_Y_AXIS, VEC_3_ZERO, VEC_3_Z_AXIS};
 use crate::render_engine::Window;
 
 // Common
@@ -25,11 +27,11 @@ pub(in crate) fn apply_ang_vel(rot: &Quat, ang_vel: &Vec3, delta: f32) -> Quat {
     }
 }
 
-pub fn local_to_world_point(local_point: &Vec3, transform: &mut Transform) -> Vec3 {
+pub fn local_to_world_point(local_point: &Vec3, transform: &Transform) -> Vec3 {
     (*transform.to_world_mat() * local_point.to_vec4(1.0)).xyz()
 }
 
-pub fn local_to_world_force(local_force: &Vec3, transform: &mut Transform) -> Vec3 {
+pub fn local_to_world_force(local_force: &Vec3, transform: &Transform) -> Vec3 {
     transform.to_rot_mat().to_mat3() * *local_force
 }
 
@@ -81,8 +83,8 @@ impl ComponentActions for Particle {}
 pub struct ParticleCable {
     pub particle_a: Entity,
     pub particle_b: Entity,
-    pub particle_a_prov: ProvisionalEntity,
-    pub particle_b_prov: ProvisionalEntity,
+    pub particle_a_prov: Option<ProvisionalEntity>,
+    pub particle_b_prov: Option<ProvisionalEntity>,
     pub max_length: f32,
     pub restitution: f32,
 }
@@ -97,8 +99,8 @@ impl ParticleCable {
         Self {
             particle_a,
             particle_b,
-            particle_a_prov: ProvisionalEntity(0),
-            particle_b_prov: ProvisionalEntity(0),
+            particle_a_prov: None,
+            particle_b_prov: None,
             max_length,
             restitution,
         }
@@ -111,10 +113,10 @@ impl ParticleCable {
         restitution: f32,
     ) -> Self {
         Self {
-            particle_a: Entity(0),
-            particle_b: Entity(0),
-            particle_a_prov: particle_a,
-            particle_b_prov: particle_b,
+            particle_a: Entity { index: 0, generation: 0 },
+            particle_b: Entity { index: 0, generation: 0 },
+            particle_a_prov: Some(particle_a),
+            particle_b_prov: Some(particle_b),
             max_length,
             restitution,
         }
@@ -124,9 +126,15 @@ impl ParticleCable {
 impl Component for ParticleCable {}
 
 impl ComponentActions for ParticleCable {
-    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) {
-        self.particle_a = provisional_to_entities.get(&self.particle_a_prov).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &self.particle_a_prov)).clone();
-        self.particle_b = provisional_to_entities.get(&self.particle_b_prov).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &self.particle_b_prov)).clone();
+    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
+        if let Some(p) = self.particle_a_prov.take() {
+            self.particle_a = *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?;
+        }
+        if let Some(p) = self.particle_b_prov.take() {
+            self.particle_b = *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -136,8 +144,8 @@ impl ComponentActions for ParticleCable {
 pub struct ParticleRod {
     pub particle_a: Entity,
     pub particle_b: Entity,
-    pub particle_a_prov: ProvisionalEntity,
-    pub particle_b_prov: ProvisionalEntity,
+    pub particle_a_prov: Option<ProvisionalEntity>,
+    pub particle_b_prov: Option<ProvisionalEntity>,
     pub length: f32,
 }
 
@@ -150,8 +158,8 @@ impl ParticleRod {
         Self {
             particle_a,
             particle_b,
-            particle_a_prov: ProvisionalEntity(0),
-            particle_b_prov: ProvisionalEntity(0),
+            particle_a_prov: None,
+            particle_b_prov: None,
             length,
         }
     }
@@ -162,10 +170,10 @@ impl ParticleRod {
         length: f32,
     ) -> Self {
         Self {
-            particle_a: Entity(0),
-            particle_b: Entity(0),
-            particle_a_prov: particle_a,
-            particle_b_prov: particle_b,
+            particle_a: Entity { index: 0, generation: 0 },
+            particle_b: Entity { index: 0, generation: 0 },
+            particle_a_prov: Some(particle_a),
+            particle_b_prov: Some(particle_b),
             length,
         }
     }
@@ -174,9 +182,15 @@ impl ParticleRod {
 impl Component for ParticleRod {}
 
 impl ComponentActions for ParticleRod {
-    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) {
-        self.particle_a = provisional_to_entities.get(&self.particle_a_prov).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &self.particle_a_prov)).clone();
-        self.particle_b = provisional_to_entities.get(&self.particle_b_prov).unwrap_or_else(|| panic!("Failed to map provisional entity {:?}", &self.particle_b_prov)).clone();
+    fn update_provisional_entities(&mut self, provisional_to_entities: &HashMap<ProvisionalEntity, Entity>) -> Result<(), EcsError> {
+        if let Some(p) = self.particle_a_prov.take() {
+            self.particle_a = *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?;
+        }
+        if let Some(p) = self.particle_b_prov.take() {
+            self.particle_b = *provisional_to_entities.get(&p).ok_or(EcsError::ProvisionalEntityNotFound(p))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -209,8 +223,6 @@ impl ParticleCollision {
     }
 }
 
-impl Component for ParticleCollision {}
-impl ComponentActions for ParticleCollision {}
 
 // ParticleCollisionDetector
 
@@ -277,6 +289,69 @@ impl RigidBody {
 impl Component for RigidBody {}
 impl ComponentActions for RigidBody {}
 
+impl SerializableComponent for RigidBody {
+    fn serialize(&self, writer: &mut SnapshotWriter) {
+        write_vec3(writer, &self.linear_vel);
+        write_vec3(writer, &self.ang_vel);
+        write_vec3(writer, &self.linear_acc);
+        write_vec3(writer, &self.ang_acc);
+        writer.write(self.linear_damping);
+        writer.write(self.ang_damping);
+        writer.write(self.gravity);
+        write_vec3(writer, &self.linear_force_accum);
+        write_vec3(writer, &self.torque_accum);
+
+        writer.write(self.props.volume);
+        writer.write_option(self.props.mass);
+        write_vec3(writer, &self.props.center_of_mass_offset);
+        writer.write(self.props.bounding_radius);
+
+        // The inertia tensor is written column by column, and only exists alongside a mass
+        if let Some(inertia_tensor) = self.props.inertia_tensor {
+            [VEC_3_X_AXIS, VEC_3_Y_AXIS, VEC_3_Z_AXIS].iter().for_each(|axis| write_vec3(writer, &(inertia_tensor * *axis)));
+        }
+    }
+
+    fn deserialize(reader: &mut SnapshotReader) -> Result<Self> {
+        let linear_vel = read_vec3(reader)?;
+        let ang_vel = read_vec3(reader)?;
+        let linear_acc = read_vec3(reader)?;
+        let ang_acc = read_vec3(reader)?;
+        let linear_damping = reader.read()?;
+        let ang_damping = reader.read()?;
+        let gravity = reader.read()?;
+        let linear_force_accum = read_vec3(reader)?;
+        let torque_accum = read_vec3(reader)?;
+
+        let volume = reader.read()?;
+        let mass: Option<f32> = reader.read_option()?;
+        let center_of_mass_offset = read_vec3(reader)?;
+        let bounding_radius = reader.read()?;
+
+        let props = match mass {
+            Some(mass) => {
+                let inertia_tensor = Mat3::from_columns(&read_vec3(reader)?, &read_vec3(reader)?, &read_vec3(reader)?);
+
+                PhysicsMeshProperties::new(volume, mass, inertia_tensor, center_of_mass_offset, bounding_radius)
+            },
+            None => PhysicsMeshProperties::new_immovable(volume, center_of_mass_offset, bounding_radius),
+        };
+
+        Ok(Self {
+            linear_vel,
+            ang_vel,
+            linear_acc,
+            ang_acc,
+            linear_damping,
+            ang_damping,
+            gravity,
+            props,
+            linear_force_accum,
+            torque_accum,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PhysicsMeshProperties { // TODO: refactor this alongside the mesh/mesh binding changes
     pub volume: f32, // TODO: remove for now? it's not accurate without scale values
@@ -457,7 +532,7 @@ pub fn generate_ray(screen_coords: &Vec2, window: &impl Window, cam: &Camera, ne
     Ok(world_coords)
 }
 
-pub fn get_ray_intersection(ray_source: &Vec3, ray_dir: &Vec3, mesh: &Mesh, transform: &mut Transform) -> Option<Vec3> {
+pub fn get_ray_intersection(ray_source: &Vec3, ray_dir: &Vec3, mesh: &Mesh, transform: &Transform) -> Option<Vec3> {
     // https://courses.cs.washington.edu/courses/csep557/09sp/lectures/triangle_intersection.pdf
 
     let world_matrix = transform.to_world_mat();
@@ -562,8 +637,6 @@ impl PotentialRigidBodyCollision {
     }
 }
 
-impl Component for PotentialRigidBodyCollision {}
-impl ComponentActions for PotentialRigidBodyCollision {}
 
 #[derive(Clone, Debug)]
 struct QuadTreeNode<T: BoundingVolume> {
@@ -804,20 +877,6 @@ impl<T: BoundingVolume> QuadTree<T> {
         Ok(())
     }
 
-    pub fn remove_not_in(&mut self, entities: &HashSet<&Entity>) {
-        let to_remove = self.root_node.entities_in_quad
-            .iter()
-            .filter(|e| !entities.contains(e))
-            .map(|e| *e)
-            .collect::<HashSet<_>>();
-
-        for e in to_remove {
-            self.all_entities.remove(&e);
-
-            self.root_node.remove(&e);
-        }
-    }
-
     pub fn get_potential_collisions(&self) -> Vec<PotentialRigidBodyCollision> {
         let mut potential_collisions = Vec::new();
 
@@ -851,9 +910,6 @@ impl<T: BoundingVolume> QuadTree<T> {
     }
 }
 
-impl<T: BoundingVolume> Component for QuadTree<T> {}
-impl<T: BoundingVolume> ComponentActions for QuadTree<T> {}
-
 // Rigid body collision detection
 
 // (Index of point vertex, indices of face vertices)
@@ -947,8 +1003,8 @@ impl RigidBodyCollision {
     }
 
     pub(in crate) fn _refresh_cache(&mut self, components: &ComponentManager) -> bool {
-        let transform_a = components.get_mut_component::<Transform>(&self.rigid_body_a);
-        let transform_b = components.get_mut_component::<Transform>(&self.rigid_body_b);
+        let transform_a = components.get_component::<Transform>(&self.rigid_body_a);
+        let transform_b = components.get_component::<Transform>(&self.rigid_body_b);
 
         let rigid_body_a = components.get_component::<RigidBody>(&self.rigid_body_a);
         let rigid_body_b = components.get_component::<RigidBody>(&self.rigid_body_b);
@@ -967,9 +1023,9 @@ impl RigidBodyCollision {
             let inverse_mass_b = rigid_body_b.props.mass.map(|m| 1.0 / m);
 
             let inverse_inertia_tensor_world_a = rigid_body_a.props.inertia_tensor.as_ref().map(|i|
-                _get_inverse_inertia_tensor_world(transform_a, i));
+                _get_inverse_inertia_tensor_world(&transform_a, i));
             let inverse_inertia_tensor_world_b = rigid_body_b.props.inertia_tensor.as_ref().map(|i|
-                _get_inverse_inertia_tensor_world(transform_b, i));
+                _get_inverse_inertia_tensor_world(&transform_b, i));
 
             let collision_to_world_space = _get_x_based_collision_space_orthonormal_basis(&self.normal);
             let world_to_collision_space = collision_to_world_space.transposed();
@@ -992,8 +1048,8 @@ impl RigidBodyCollision {
             let collision_space_vel = _get_collision_space_collision_vel(
                 &collision_point_rel_a,
                 &collision_point_rel_b,
-                rigid_body_a,
-                rigid_body_b,
+                &rigid_body_a,
+                &rigid_body_b,
                 &world_to_collision_space,
             );
 
@@ -1047,7 +1103,7 @@ fn _get_inertia(
 }
 
 fn _get_inverse_inertia_tensor_world(
-    transform: &mut Transform,
+    transform: &Transform,
     inertia_tensor: &Mat3,
 ) -> Mat3 {
     let world_matrix = transform.to_world_mat().to_mat3();
@@ -1116,8 +1172,8 @@ impl ComponentActions for RigidBodyCollision {}
 pub fn get_deepest_rigid_body_collision(
     mesh_a: (&Entity, &Mesh),
     mesh_b: (&Entity, &Mesh),
-    transform_a: &mut Transform,
-    transform_b: &mut Transform,
+    transform_a: &Transform,
+    transform_b: &Transform,
 ) -> Option<RigidBodyCollision> {
     // TODO: optimize with GJK or another non-naive approach
     // TODO: also, this is probably not rigorous for non-convex polyhedra
@@ -1182,7 +1238,7 @@ fn get_shallowest_point_collision(
     vertex: &Vec3,
     vertex_index: (&Entity, u32),
     mesh: (&Entity, &Mesh),
-    mesh_transform: &mut Transform,
+    mesh_transform: &Transform,
 ) -> Option<RigidBodyCollision> {
     mesh.1.vertex_indices
         .chunks(3)
@@ -1263,7 +1319,7 @@ fn _get_shallowest_edge_collision(
     edge: (&Vec3, &Vec3),
     edge_indices: (&Entity, &Edge),
     mesh: (&Entity, &Mesh),
-    mesh_transform: &mut Transform,
+    mesh_transform: &Transform,
 ) -> Option<RigidBodyCollision> {
     mesh.1.edges
         .iter()
@@ -4,7 +4,7 @@ use vulkan_structs::GuiUniformBufferObject;
 use winit::platform::windows::EventLoopBuilderExtWindows;
 use core::panic;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
 use std::{thread, usize};
@@ -25,8 +25,6 @@ use winit::window::{Window as winit_Window, WindowAttributes};
 
 use crate::core::{Color, RenderTextureId};
 use crate::core::mesh::Vertex;
-use crate::ecs::ComponentActions;
-use crate::ecs::component::Component;
 use crate::math::{mat3, mat4, vec2, Mat3, Vec2, VEC_2_ZERO};
 use crate::render_engine::{Device, RenderMeshId, RenderEngine, RenderEngineInitProps, RenderState, VirtualButton, VirtualKey, VirtualElementState, Window};
 use crate::render_engine::vulkan::vulkan_resources::{
@@ -58,6 +56,7 @@ const MAX_FRAMES_IN_FLIGHT: usize = 3;
 // TODO: we'll want a way to resize the uniform buffer and/or overwrite it multiple times per frame, instead of capping the limit on total uniform desciptors like this
 const NUM_UNIFORM_DESCRIPTORS: usize = 8192;
 
+// The receivers are wrapped in mutexes only so that the engine can be shared between threads as an ECS resource
 pub struct VulkanRenderEngine {
     mesh_id_counter: usize,
     texture_id_counter: usize,
@@ -67,26 +66,23 @@ pub struct VulkanRenderEngine {
     keys_down: HashMap<VirtualKey, bool>,
     keys_pressed: HashMap<VirtualKey, bool>,
     keys_released: HashMap<VirtualKey, bool>,
-    keys_receiver: Receiver<(VirtualKey, VirtualElementState)>,
+    keys_receiver: Mutex<Receiver<(VirtualKey, VirtualElementState)>>,
     buttons_down: HashMap<VirtualButton, bool>,
     buttons_pressed: HashMap<VirtualButton, bool>,
     buttons_released: HashMap<VirtualButton, bool>,
-    buttons_receiver: Receiver<(VirtualButton, VirtualElementState)>,
+    buttons_receiver: Mutex<Receiver<(VirtualButton, VirtualElementState)>>,
     mouse_pos: Option<Vec2>,
-    mouse_pos_receiver: Receiver<Option<Vec2>>,
+    mouse_pos_receiver: Mutex<Receiver<Option<Vec2>>>,
     mouse_pos_sender: SyncSender<Vec2>,
     mouse_visible_sender: SyncSender<bool>,
     window_extent: vk::Extent2D,
-    window_extent_receiver: Receiver<vk::Extent2D>,
+    window_extent_receiver: Mutex<Receiver<vk::Extent2D>>,
     window_screen_position: Vec2,
-    window_screen_position_receiver: Receiver<Vec2>,
+    window_screen_position_receiver: Mutex<Receiver<Vec2>>,
     is_closing: Arc<AtomicBool>,
     render_thread_join_handle: Option<JoinHandle<()>>,
 }
 
-impl Component for VulkanRenderEngine {}
-impl ComponentActions for VulkanRenderEngine {}
-
 struct VulkanApplication {
     init_props: RenderEngineInitProps,
     state_receiver: Receiver<RenderState>,
@@ -553,19 +549,19 @@ impl RenderEngine<VulkanRenderEngine, VulkanRenderEngine> for VulkanRenderEngine
                 keys_down: create_empty_vk_map(),
                 keys_pressed: create_empty_vk_map(),
                 keys_released: create_empty_vk_map(),
-                keys_receiver,
+                keys_receiver: Mutex::new(keys_receiver),
                 buttons_down: create_empty_vb_map(),
                 buttons_pressed: create_empty_vb_map(),
                 buttons_released: create_empty_vb_map(),
-                buttons_receiver,
+                buttons_receiver: Mutex::new(buttons_receiver),
                 mouse_pos: None,
-                mouse_pos_receiver,
+                mouse_pos_receiver: Mutex::new(mouse_pos_receiver),
                 mouse_pos_sender: downstream_mouse_pos_sender,
                 mouse_visible_sender,
                 window_extent: vk::Extent2D { width, height },
-                window_extent_receiver,
+                window_extent_receiver: Mutex::new(window_extent_receiver),
                 window_screen_position: VEC_2_ZERO,
-                window_screen_position_receiver,
+                window_screen_position_receiver: Mutex::new(window_screen_position_receiver),
                 is_closing,
                 render_thread_join_handle: Some(join_handle),
             }
@@ -576,7 +572,7 @@ impl RenderEngine<VulkanRenderEngine, VulkanRenderEngine> for VulkanRenderEngine
         // Keys
         let mut new_keys_down = self.keys_down.clone();
 
-        while let Ok((vk, vk_state)) = self.keys_receiver.try_recv() {
+        while let Ok((vk, vk_state)) = self.keys_receiver.get_mut().unwrap_or_else(|e| e.into_inner()).try_recv() {
             match vk_state {
                 VirtualElementState::Pressed => new_keys_down.insert(vk, true),
                 VirtualElementState::Released => new_keys_down.insert(vk, false),
@@ -598,7 +594,7 @@ impl RenderEngine<VulkanRenderEngine, VulkanRenderEngine> for VulkanRenderEngine
         // Buttons
         let mut new_buttons_down = self.buttons_down.clone();
 
-        while let Ok((vb, vb_state)) = self.buttons_receiver.try_recv() {
+        while let Ok((vb, vb_state)) = self.buttons_receiver.get_mut().unwrap_or_else(|e| e.into_inner()).try_recv() {
             match vb_state {
                 VirtualElementState::Pressed => new_buttons_down.insert(vb, true),
                 VirtualElementState::Released => new_buttons_down.insert(vb, false),
@@ -618,17 +614,17 @@ impl RenderEngine<VulkanRenderEngine, VulkanRenderEngine> for VulkanRenderEngine
         self.buttons_down = new_buttons_down;
 
         // Mouse position
-        while let Ok(mouse_pos) = self.mouse_pos_receiver.try_recv() {
+        while let Ok(mouse_pos) = self.mouse_pos_receiver.get_mut().unwrap_or_else(|e| e.into_inner()).try_recv() {
             self.mouse_pos = mouse_pos;
         }
 
         // Window extent
-        while let Ok(window_extent) = self.window_extent_receiver.try_recv() {
+        while let Ok(window_extent) = self.window_extent_receiver.get_mut().unwrap_or_else(|e| e.into_inner()).try_recv() {
             self.window_extent = window_extent;
         }
 
         // Window screen position
-        while let Ok(window_screen_position) = self.window_screen_position_receiver.try_recv() {
+        while let Ok(window_screen_position) = self.window_screen_position_receiver.get_mut().unwrap_or_else(|e| e.into_inner()).try_recv() {
             self.window_screen_position = window_screen_position;
         }
 